@@ -0,0 +1,66 @@
+/*
+ * Special boot modes, chosen by the buttons held while the clock powers up
+ *
+ * stop + sync: factory reset, asks for a confirmation with the encoder button
+ * play + encoder button: safe mode, ignores the stored state and shows the diagnostics
+ */
+
+use crate::gestures::{BUTTON1_MASK, BUTTON2_MASK, BUTTON3_MASK, BUTTON4_MASK};
+use crate::statemachine::{State, MenuPage, DEFAULT_STATE};
+
+pub const FACTORY_RESET_BUTTONS: u8 = BUTTON2_MASK | BUTTON3_MASK;
+pub const SAFE_MODE_BUTTONS: u8 = BUTTON1_MASK | BUTTON4_MASK;
+
+// ms to answer the factory reset, then the clock boots without it
+pub const CONFIRM_TIMEOUT: u32 = 10000;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BootMode {
+  Normal,
+  FactoryReset,
+  SafeMode
+}
+
+impl BootMode {
+  // only the exact combos count, so a stuck button does not wipe the memory
+  pub fn from_buttons(held: u8) -> BootMode {
+    return match held {
+      FACTORY_RESET_BUTTONS => BootMode::FactoryReset,
+      SAFE_MODE_BUTTONS => BootMode::SafeMode,
+      _ => BootMode::Normal
+    };
+  }
+}
+
+// default state on the diagnostics page
+pub fn safe_state() -> State {
+  let mut state = DEFAULT_STATE;
+  state.menu = MenuPage::Diagnostics;
+  return state;
+}
+
+/* Answer to the factory reset: the combo is released first, then the encoder button confirms and
+ * any other button cancels. It counts on release, so the buttons do not reach the statemachine. */
+pub struct Confirmation {
+  started: u32,
+  released: bool,
+  pressed: u8 // buttons pressed since the combo was released
+}
+
+impl Confirmation {
+  pub fn new(now: u32) -> Confirmation {
+    return Confirmation { started: now, released: false, pressed: 0 };
+  }
+
+  // takes the debounced buttons, returns the answer once all buttons are released after it
+  pub fn poll(&mut self, held: u8, now: u32) -> Option<bool> {
+    if held == 0 && now.wrapping_sub(self.started) >= CONFIRM_TIMEOUT { return Some(false) }
+    if !self.released {
+      self.released = held == 0;
+      return None;
+    }
+    self.pressed |= held;
+    if held != 0 || self.pressed == 0 { return None }
+    return Some(self.pressed == BUTTON4_MASK);
+  }
+}
@@ -0,0 +1,244 @@
+/*
+ * Tick logic of the clock, decides on every clock tick which outputs fire
+ */
+
+use crate::statemachine::{RampMode};
+use crate::delays::{OUTPUT_COUNT};
+
+// bitmasks for triggers
+pub const TRIGGER1_MASK : u8 = 0b00000001;
+pub const TRIGGER2_MASK : u8 = 0b00000010;
+pub const TRIGGER3_MASK : u8 = 0b00000100;
+pub const TRIGGER4_MASK : u8 = 0b00001000;
+
+// bitmasks for midi clock outputs, the lower bits are used by the triggers
+pub const MIDI1_MASK : u8 = 0b00010000;
+pub const MIDI2_MASK : u8 = 0b00100000;
+
+pub const CLOCK_TICKS_PER_QUARTER_NOTE: u32 = 24;
+
+// largest common multiple of all possible divisors and 24
+const CLOCK_TICKS_CYCLE: u32 = 806400;
+
+const RESET_BIT: u32 = 1 << 28;
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct Position {
+  pub bar: u16,
+  pub beat: u8,
+  pub sixteenth: u8
+}
+
+impl Position {
+  // position of the tick since the last reset
+  pub fn from_ticks(ticks: u32, bar_length: u8) -> Position {
+    let bar_length = bar_length.max(1) as u32;
+    let quarters = ticks / CLOCK_TICKS_PER_QUARTER_NOTE;
+    return Position {
+      bar: (quarters / bar_length + 1) as u16,
+      beat: (quarters % bar_length + 1) as u8,
+      sixteenth: (ticks % CLOCK_TICKS_PER_QUARTER_NOTE / (CLOCK_TICKS_PER_QUARTER_NOTE / 4) + 1) as u8
+    };
+  }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct ClockSettings {
+  pub divisions: [u8;2],
+  pub triggers_ppq: u8,
+  pub bar_length: u8,
+  pub reset: bool,
+  pub sync: bool
+}
+
+impl ClockSettings {
+  // packs the settings into one word, so the interrupt can read them atomically
+  pub fn pack(&self) -> u32 {
+    return (self.divisions[0] as u32) | (self.divisions[1] as u32) << 8 |
+      (self.triggers_ppq as u32) << 16 |
+      (self.bar_length as u32 & 0xF) << 24 |
+      (self.reset as u32) << 28 |
+      (self.sync as u32) << 29;
+  }
+
+  pub fn unpack(settings_u32: u32) -> ClockSettings {
+    return ClockSettings {
+      divisions: [(settings_u32) as u8, (settings_u32 >> 8) as u8],
+      triggers_ppq: (settings_u32 >> 16) as u8,
+      bar_length: (settings_u32 >> 24 & 0xF) as u8,
+      reset: settings_u32 & RESET_BIT > 0,
+      sync: (settings_u32 >> 29 & 0b1) == 1,
+    };
+  }
+
+  pub fn with_reset(settings_u32: u32, reset: bool) -> u32 {
+    return (settings_u32 & !RESET_BIT) | (reset as u32) << 28;
+  }
+}
+
+// tempo ramp that changes the bpm on every tick
+#[derive(Copy, Clone)]
+pub struct Ramp {
+  bpm: f32,
+  change: f32, // gets added for linear and multiplied for exponential ramps
+  mode: RampMode,
+  target: u16,
+  ticks: u32,
+  elapsed: u32
+}
+
+impl Ramp {
+  // glides from the start bpm to the target bpm within the given bars
+  pub fn new(start: u16, target: u16, bars: u8, bar_length: u8, mode: RampMode) -> Ramp {
+    let start = start as f32;
+    let ticks = (bars as u32 * bar_length as u32 * CLOCK_TICKS_PER_QUARTER_NOTE).max(1);
+    let change = match mode {
+      RampMode::Linear => (target as f32 - start) / ticks as f32,
+      RampMode::Exponential => nth_root(target as f64 / start as f64, ticks) as f32
+    };
+    return Ramp { bpm: start, change: change, mode: mode, target: target, ticks: ticks, elapsed: 0 };
+  }
+
+  // advances the ramp by one tick, returns true when it reached the target
+  pub fn step(&mut self) -> bool {
+    self.elapsed += 1;
+    self.bpm = match self.mode {
+      RampMode::Linear => self.bpm + self.change,
+      RampMode::Exponential => self.bpm * self.change
+    };
+    if self.elapsed >= self.ticks {
+      self.bpm = self.target as f32;
+    }
+    return self.elapsed >= self.ticks;
+  }
+
+  pub fn bpm(&self) -> f32 {
+    return self.bpm;
+  }
+
+  pub fn target(&self) -> u16 {
+    return self.target;
+  }
+
+  // returns current bpm and progress in percent
+  pub fn progress(&self) -> (u16, u8) {
+    return ((self.bpm + 0.5) as u16, (self.elapsed * 100 / self.ticks) as u8);
+  }
+}
+
+// finds the factor that multiplied n times with itself gives x
+fn nth_root(x: f64, n: u32) -> f64 {
+  fn pow(base: f64, exp: u32) -> f64 {
+    let (mut result, mut base, mut exp) = (1.0, base, exp);
+    while exp > 0 {
+      if exp & 1 == 1 { result *= base; }
+      base *= base;
+      exp >>= 1;
+    }
+    return result;
+  }
+
+  let (mut low, mut high) = if x < 1.0 { (x, 1.0) } else { (1.0, x) };
+  for _ in 0..64 {
+    let mid = (low + high) / 2.0;
+    if pow(mid, n) < x { low = mid } else { high = mid }
+  }
+  return (low + high) / 2.0;
+}
+
+// result of a clock tick
+#[derive(Copy, Clone, PartialEq)]
+pub struct Tick {
+  pub outputs: u8,
+  pub position: u32, // ticks since the last reset
+  pub song_end: bool, // the last bar of the song ended with this tick
+  pub held: bool // clock holds at the end of the song, nothing gets sent
+}
+
+/* Counts the clock ticks and derives the outputs from them */
+pub struct ClockEngine {
+  overflows: u32,
+  elapsed: u8,
+  sync: bool,
+  song_bars: u16, // length of the current song in bars, the clock holds after it
+  song_bar_count: u16
+}
+
+impl ClockEngine {
+  pub const fn new() -> ClockEngine {
+    return ClockEngine { overflows: 0, elapsed: 0, sync: false, song_bars: 0, song_bar_count: 0 };
+  }
+
+  // holds the clock after the given bars, 0 to run until stopped
+  pub fn set_song_length(&mut self, bars: u8) {
+    self.song_bars = bars as u16;
+    self.song_bar_count = 0;
+  }
+
+  // delays are the ticks every output is held back
+  pub fn tick(&mut self, settings: &ClockSettings, delays: &[u8; OUTPUT_COUNT]) -> Tick {
+    self.sync = self.sync || settings.sync;
+
+    // reset Clock
+    if settings.reset {
+      self.overflows = 0;
+      self.elapsed = 0;
+      self.song_bar_count = 0;
+    }
+
+    let mut tick = Tick { outputs: 0, position: self.overflows, song_end: false, held: false };
+
+    // count the bars of the song and hold the clock at its end
+    if self.song_bars > 0 {
+      let bar_start = self.overflows % (CLOCK_TICKS_PER_QUARTER_NOTE * settings.bar_length as u32) == 0;
+      if self.elapsed > 0 && bar_start && self.song_bar_count < self.song_bars {
+        self.song_bar_count += 1;
+        tick.song_end = self.song_bar_count == self.song_bars;
+      }
+      if self.song_bar_count >= self.song_bars {
+        tick.held = true;
+        return tick;
+      }
+    }
+
+    let (overflows, elapsed) = (self.overflows, self.elapsed);
+    // position of an output that is delayed by the given ticks, none if it has not started yet
+    let position = |delay: u8| -> Option<u32> {
+      if elapsed < delay { return None }
+      return Some((overflows + CLOCK_TICKS_CYCLE - delay as u32) % CLOCK_TICKS_CYCLE);
+    };
+
+    // handle the two midi channels
+    let midi_masks = [(MIDI1_MASK, TRIGGER1_MASK), (MIDI2_MASK, TRIGGER2_MASK)];
+    for i in 0..2 {
+      position(delays[i]).map(|pos| {
+        if pos % (settings.divisions[i] as u32 * CLOCK_TICKS_PER_QUARTER_NOTE) == 0 {
+          tick.outputs |= midi_masks[i].1;
+        }
+        if pos % settings.divisions[i] as u32 == 0 {
+          tick.outputs |= midi_masks[i].0;
+        }
+      });
+    }
+
+    // handle the trigger out
+    position(delays[2]).map(|pos| {
+      if pos % (CLOCK_TICKS_PER_QUARTER_NOTE/settings.triggers_ppq as u32) == 0 {
+        tick.outputs |= TRIGGER3_MASK;
+      }
+    });
+
+    // handle reset out after 4 quarter notes
+    let sync = &mut self.sync;
+    position(delays[3]).map(|pos| {
+      if *sync && pos % (CLOCK_TICKS_PER_QUARTER_NOTE * settings.bar_length as u32) == 0 {
+        *sync = false;
+        tick.outputs |= TRIGGER4_MASK;
+      }
+    });
+
+    self.overflows = (self.overflows + 1) % CLOCK_TICKS_CYCLE;
+    self.elapsed = self.elapsed.saturating_add(1);
+    return tick;
+  }
+}
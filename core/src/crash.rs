@@ -0,0 +1,131 @@
+/*
+ * Report of a panic or hard fault, stored in the eeprom until it is cleared
+ */
+
+use core::fmt;
+
+// bytes of a report in memory, two eeprom pages
+pub const CRASH_REPORT_SIZE: usize = 64;
+
+const REPORT_MARKER: u8 = 0xC5;
+const FILE_LENGTH: usize = 20;
+const MESSAGE_LENGTH: usize = 32;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FaultCode {
+  Panic = 1,
+  HardFault = 2
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct CrashReport {
+  pub code: FaultCode,
+  pub line: u16,
+  pub address: u32, // program counter of a hard fault
+  file: [u8; FILE_LENGTH], // end of the path of the source file
+  message: [u8; MESSAGE_LENGTH]
+}
+
+impl CrashReport {
+  pub fn new(code: FaultCode) -> CrashReport {
+    return CrashReport { code: code, line: 0, address: 0, file: [0; FILE_LENGTH], message: [0; MESSAGE_LENGTH] };
+  }
+
+  // keeps the end of long paths, it names the file
+  pub fn set_location(&mut self, file: &str, line: u32) {
+    let bytes = file.as_bytes();
+    let start = bytes.len().saturating_sub(FILE_LENGTH);
+    self.file = [0; FILE_LENGTH];
+    self.file[..bytes.len() - start].copy_from_slice(&bytes[start..]);
+    self.line = line.min(u16::MAX as u32) as u16;
+  }
+
+  // long messages are cut off
+  pub fn set_message(&mut self, args: fmt::Arguments) {
+    self.message = [0; MESSAGE_LENGTH];
+    let mut writer = TextWriter { text: &mut self.message, length: 0 };
+    fmt::Write::write_fmt(&mut writer, args).ok();
+  }
+
+  // writes the report as text and returns its length, the end of a long text is cut off
+  pub fn write_text(&self, buffer: &mut [u8]) -> usize {
+    let mut writer = TextWriter { text: buffer, length: 0 };
+    fmt::Write::write_fmt(&mut writer, format_args!("{}", self)).ok();
+    return writer.length;
+  }
+
+  pub fn file(&self) -> &str {
+    return text(&self.file);
+  }
+
+  pub fn message(&self) -> &str {
+    return text(&self.message);
+  }
+
+  // layout: marker code line[2] address[4] file[20] message[32] reserved[3] checksum
+  pub fn to_bytes(&self) -> [u8; CRASH_REPORT_SIZE] {
+    let mut bytes = [0; CRASH_REPORT_SIZE];
+    bytes[0] = REPORT_MARKER;
+    bytes[1] = self.code as u8;
+    bytes[2..4].copy_from_slice(&self.line.to_be_bytes());
+    bytes[4..8].copy_from_slice(&self.address.to_be_bytes());
+    bytes[8..28].copy_from_slice(&self.file);
+    bytes[28..60].copy_from_slice(&self.message);
+    bytes[CRASH_REPORT_SIZE - 1] = checksum(&bytes[..CRASH_REPORT_SIZE - 1]);
+    return bytes;
+  }
+
+  // erased memory and damaged reports read as none
+  pub fn from_bytes(bytes: &[u8]) -> Option<CrashReport> {
+    if bytes.len() < CRASH_REPORT_SIZE || bytes[0] != REPORT_MARKER { return None }
+    if bytes[CRASH_REPORT_SIZE - 1] != checksum(&bytes[..CRASH_REPORT_SIZE - 1]) { return None }
+
+    let code = match bytes[1] {
+      1 => FaultCode::Panic,
+      2 => FaultCode::HardFault,
+      _ => return None
+    };
+    let mut report = CrashReport::new(code);
+    report.line = u16::from_be_bytes([bytes[2], bytes[3]]);
+    report.address = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+    report.file.copy_from_slice(&bytes[8..28]);
+    report.message.copy_from_slice(&bytes[28..60]);
+    return Some(report);
+  }
+}
+
+impl fmt::Display for CrashReport {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    return match self.code {
+      FaultCode::Panic => write!(f, "panic at {}:{}: {}", self.file(), self.line, self.message()),
+      FaultCode::HardFault => write!(f, "hard fault at {:#010x}", self.address)
+    };
+  }
+}
+
+fn checksum(bytes: &[u8]) -> u8 {
+  return bytes.iter().fold(0, |sum: u8, byte| sum.wrapping_add(*byte)) ^ 0xFF;
+}
+
+// text up to the first zero byte, invalid utf8 reads as empty
+fn text(bytes: &[u8]) -> &str {
+  let length = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+  return core::str::from_utf8(&bytes[..length]).unwrap_or("");
+}
+
+struct TextWriter<'a> {
+  text: &'a mut [u8],
+  length: usize
+}
+
+impl<'a> fmt::Write for TextWriter<'a> {
+  fn write_str(&mut self, s: &str) -> fmt::Result {
+    // cut at a character boundary, so the text stays valid utf8
+    for c in s.chars() {
+      if self.length + c.len_utf8() > self.text.len() { return Err(fmt::Error) }
+      c.encode_utf8(&mut self.text[self.length..]);
+      self.length += c.len_utf8();
+    }
+    return Ok(());
+  }
+}
@@ -0,0 +1,118 @@
+/*
+ * Per output delays to compensate the latencies of connected instruments
+ */
+
+use heapless::spsc::{Queue};
+
+use crate::clock::{MIDI1_MASK, MIDI2_MASK, TRIGGER1_MASK, TRIGGER2_MASK, TRIGGER3_MASK, TRIGGER4_MASK};
+
+pub const OUTPUT_COUNT: usize = 4;
+
+// midi out1+2 with its led, midi out3+4 with its led, trigger out, reset out
+pub const OUTPUT_MASKS: [u8; OUTPUT_COUNT] = [
+  MIDI1_MASK | TRIGGER1_MASK,
+  MIDI2_MASK | TRIGGER2_MASK,
+  TRIGGER3_MASK,
+  TRIGGER4_MASK
+];
+
+// the delay line counts in the 50us base ticks of timer2
+pub const BASE_TICKS_PER_MS: u32 = 20;
+
+// at 320 bpm there are at most 13 ticks within the largest delay of 100ms
+const MAX_PENDING_TICKS: usize = 16;
+
+type DelayQueue = Queue<(u32, u8), MAX_PENDING_TICKS>;
+
+pub const OFFSET_MILLIS_RANGE: (i8,i8) = (-50, 50);
+pub const OFFSET_TICKS_RANGE: (i8,i8) = (-12, 12);
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OutputOffset {
+  pub millis: i8,
+  pub ticks: i8
+}
+
+pub const ZERO_OFFSET: OutputOffset = OutputOffset { millis: 0, ticks: 0 };
+
+// offsets converted to positive delays
+#[derive(Copy, Clone)]
+pub struct OutputDelays {
+  pub ticks: [u8; OUTPUT_COUNT],
+  pub millis: [u8; OUTPUT_COUNT]
+}
+
+impl OutputDelays {
+  // negative offsets are realized by delaying all other outputs
+  pub fn from_offsets(offsets: &[OutputOffset; OUTPUT_COUNT]) -> OutputDelays {
+    let min_ticks = offsets.iter().map(|o| o.ticks).min().unwrap_or(0).min(0);
+    let min_millis = offsets.iter().map(|o| o.millis).min().unwrap_or(0).min(0);
+
+    let mut delays = OutputDelays { ticks: [0; OUTPUT_COUNT], millis: [0; OUTPUT_COUNT] };
+    for i in 0..OUTPUT_COUNT {
+      delays.ticks[i] = (offsets[i].ticks as i16 - min_ticks as i16) as u8;
+      delays.millis[i] = (offsets[i].millis as i16 - min_millis as i16) as u8;
+    }
+    return delays;
+  }
+}
+
+/* Holds back clock ticks of every output for its delay in ms */
+pub struct DelayLine {
+  queues: [DelayQueue; OUTPUT_COUNT],
+  delays: [u32; OUTPUT_COUNT], // in base ticks
+  base_ticks: u32
+}
+
+impl DelayLine {
+  pub const fn new() -> DelayLine {
+    return DelayLine {
+      queues: [Queue::new(), Queue::new(), Queue::new(), Queue::new()],
+      delays: [0; OUTPUT_COUNT],
+      base_ticks: 0
+    };
+  }
+
+  pub fn set_delays(&mut self, millis: [u8; OUTPUT_COUNT]) {
+    for i in 0..OUTPUT_COUNT {
+      self.delays[i] = millis[i] as u32 * BASE_TICKS_PER_MS;
+    }
+  }
+
+  pub fn clear(&mut self) {
+    for queue in self.queues.iter_mut() {
+      while queue.dequeue().is_some() {}
+    }
+  }
+
+  // queues the delayed outputs, returns the ones to send right away
+  pub fn send(&mut self, outputs: u8) -> u8 {
+    let mut immediate: u8 = 0;
+    for i in 0..OUTPUT_COUNT {
+      let ticks = outputs & OUTPUT_MASKS[i];
+      if ticks == 0 { continue }
+
+      if self.delays[i] == 0 {
+        immediate |= ticks;
+      } else {
+        // drop the tick if the queue overflows, it only happens when delays change at high bpm
+        self.queues[i].enqueue((self.base_ticks.wrapping_add(self.delays[i]), ticks)).ok();
+      }
+    }
+    return immediate;
+  }
+
+  // gets called on every base tick, returns the outputs that are due
+  pub fn on_base_tick(&mut self) -> u8 {
+    self.base_ticks = self.base_ticks.wrapping_add(1);
+    let now = self.base_ticks;
+
+    let mut due: u8 = 0;
+    for queue in self.queues.iter_mut() {
+      while queue.peek().map_or(false, |(time, _)| (now.wrapping_sub(*time) as i32) >= 0) {
+        due |= queue.dequeue().unwrap().1;
+      }
+    }
+    return due;
+  }
+}
@@ -0,0 +1,126 @@
+/*
+ * Timing statistics of the clock output and the sysex messages to read them out
+ */
+
+use crate::crash::{CrashReport};
+
+// manufacturer id for non commercial use
+pub const SYSEX_MANUFACTURER: u8 = 0x7D;
+
+const SYSEX_DUMP_REQUEST: u8 = 0x01;
+const SYSEX_RESET_REQUEST: u8 = 0x02;
+const SYSEX_DUMP: u8 = 0x03;
+const SYSEX_CRASH_REQUEST: u8 = 0x04;
+const SYSEX_CRASH_REPORT: u8 = 0x05;
+
+// start, manufacturer, type, 6 values of 5 bytes, end
+pub const SYSEX_DUMP_LENGTH: usize = 34;
+
+// start, manufacturer, type, report as text, end
+pub const SYSEX_CRASH_LENGTH: usize = 84;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Request {
+  Dump,
+  Reset,
+  CrashReport
+}
+
+// request in the data bytes of a sysex message: F0 7D 01 F7 dumps, F0 7D 02 F7 resets the statistics,
+// F0 7D 04 F7 reads the crash report
+pub fn parse_request(data: &[u8]) -> Option<Request> {
+  return match data {
+    [SYSEX_MANUFACTURER, SYSEX_DUMP_REQUEST] => Some(Request::Dump),
+    [SYSEX_MANUFACTURER, SYSEX_RESET_REQUEST] => Some(Request::Reset),
+    [SYSEX_MANUFACTURER, SYSEX_CRASH_REQUEST] => Some(Request::CrashReport),
+    _ => None
+  };
+}
+
+/* Running statistics of the deviation of tick intervals from their ideal interval */
+#[derive(Copy, Clone, PartialEq)]
+pub struct TickStats {
+  pub count: u32,
+  pub min: i32,
+  pub max: i32,
+  sum: i64,
+  sum_squares: u64
+}
+
+impl TickStats {
+  pub const fn new() -> TickStats {
+    return TickStats { count: 0, min: 0, max: 0, sum: 0, sum_squares: 0 };
+  }
+
+  pub fn add(&mut self, deviation: i32) {
+    if self.count == 0 {
+      self.min = deviation;
+      self.max = deviation;
+    }
+    self.min = self.min.min(deviation);
+    self.max = self.max.max(deviation);
+    self.count = self.count.saturating_add(1);
+    self.sum = self.sum.saturating_add(deviation as i64);
+    self.sum_squares = self.sum_squares.saturating_add((deviation as i64 * deviation as i64) as u64);
+  }
+
+  // positive if the ticks are slower than ideal
+  pub fn mean(&self) -> i32 {
+    if self.count == 0 { return 0 }
+    return (self.sum / self.count as i64) as i32;
+  }
+
+  pub fn variance(&self) -> u64 {
+    if self.count == 0 { return 0 }
+    let mean = self.sum / self.count as i64;
+    return (self.sum_squares / self.count as u64).saturating_sub((mean * mean) as u64);
+  }
+
+  pub fn std_dev(&self) -> u32 {
+    // integer square root by bisection
+    let variance = self.variance();
+    let (mut low, mut high) = (0u64, variance.min(u32::MAX as u64) + 1);
+    while high - low > 1 {
+      let mid = (low + high) / 2;
+      if mid * mid <= variance { low = mid } else { high = mid }
+    }
+    return low as u32;
+  }
+
+  // largest deviation in either direction
+  pub fn jitter(&self) -> u32 {
+    return (self.min as i64).abs().max((self.max as i64).abs()) as u32;
+  }
+}
+
+// values are sent with 7 bits per byte, the lowest bits first
+pub fn dump_sysex(stats: &TickStats, dropped: u32) -> [u8; SYSEX_DUMP_LENGTH] {
+  let mut sysex = [0; SYSEX_DUMP_LENGTH];
+  sysex[0] = 0xF0;
+  sysex[1] = SYSEX_MANUFACTURER;
+  sysex[2] = SYSEX_DUMP;
+
+  let values = [stats.count, stats.min as u32, stats.max as u32, stats.mean() as u32, stats.std_dev(), dropped];
+  for (i, value) in values.iter().enumerate() {
+    for j in 0..5 {
+      sysex[3 + i * 5 + j] = (value >> (j * 7)) as u8 & 0x7F;
+    }
+  }
+  sysex[SYSEX_DUMP_LENGTH - 1] = 0xF7;
+  return sysex;
+}
+
+// returns the message and its length, the text is empty without a report
+pub fn crash_sysex(report: Option<&CrashReport>) -> ([u8; SYSEX_CRASH_LENGTH], usize) {
+  let mut sysex = [0; SYSEX_CRASH_LENGTH];
+  sysex[0] = 0xF0;
+  sysex[1] = SYSEX_MANUFACTURER;
+  sysex[2] = SYSEX_CRASH_REPORT;
+
+  let length = 3 + report.map_or(0, |report| report.write_text(&mut sysex[3..SYSEX_CRASH_LENGTH - 1]));
+  for byte in sysex[3..length].iter_mut() {
+    if *byte > 0x7F { *byte = b'?' }
+  }
+  sysex[length] = 0xF7;
+  return (sysex, length + 1);
+}
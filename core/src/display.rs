@@ -0,0 +1,378 @@
+/*
+ * Content of the display pages, shared by the character lcd and the tft
+ */
+
+use core::sync::atomic::{AtomicBool, AtomicU16, Ordering};
+
+use crate::statemachine::{State, RunState, MenuPage, ClockSource, RampMode};
+use crate::clock::{Position};
+use crate::utils::{u16_to_string, i16_to_string, u32_to_string};
+use crate::glyphs::*;
+
+static UPDATE_TIME_ARRIVED: AtomicBool = AtomicBool::new(false);
+
+const DISPLAY_UPDATE_OVERFLOWS: u8 = 50;
+
+// ms left until the shown message gets removed
+static MESSAGE_TIMEOUT: AtomicU16 = AtomicU16::new(0);
+
+const MESSAGE_LENGTH: usize = 32;
+
+// how long messages are shown in ms, indexed by priority
+const MESSAGE_DURATIONS: [u16; 3] = [1500, 2000, 3000];
+
+// messages can only be replaced by messages of the same or higher priority
+#[derive(Copy, Clone, PartialEq, PartialOrd)]
+pub enum Priority {
+  Info = 0,
+  Warning = 1,
+  Error = 2
+}
+
+#[derive(Copy, Clone)]
+pub struct Message {
+  text: [u8; MESSAGE_LENGTH],
+  length: usize,
+  pub priority: Priority
+}
+
+// largest supported text size
+pub const MAX_COLUMNS: usize = 40;
+pub const MAX_ROWS: usize = 4;
+
+const OUTPUT_NAMES: [&str; 4] = ["Midi1", "Midi2", "Trig", "Reset"];
+
+pub trait Display {
+  fn content(&mut self) -> &mut Content;
+
+  fn init(&mut self);
+
+  // redraws the display if the content changed
+  fn render(&mut self);
+
+  fn update(&mut self, state: &State) {
+    let content = self.content();
+    content.state = Some(*state);
+    content.updated = true;
+  }
+
+  fn update_ramp(&mut self, ramp: Option<(u16, u8)>) {
+    let content = self.content();
+    if content.ramp != ramp {
+      content.ramp = ramp;
+      content.updated = true;
+    }
+  }
+
+  fn update_position(&mut self, position: Position) {
+    let content = self.content();
+    if content.position != position {
+      content.position = position;
+      content.updated = content.updated || content.shows_position();
+    }
+  }
+
+  // outputs that sent clock ticks since the last update
+  fn update_activity(&mut self, _outputs: u8) {}
+
+  // largest deviation of the tick intervals in us and the dropped midi bytes
+  fn update_diagnostics(&mut self, jitter: u32, dropped: u32) {
+    let content = self.content();
+    if content.diagnostics != (jitter, dropped) {
+      content.diagnostics = (jitter, dropped);
+      content.updated = content.updated || content.state.map_or(false, |state| state.menu == MenuPage::Diagnostics);
+    }
+  }
+
+  // shows the text instead of the current page until it times out
+  fn show_message(&mut self, text: &str, priority: Priority) {
+    let content = self.content();
+    if content.message.map_or(false, |message| message.priority > priority) { return }
+
+    let mut message = Message { text: [b' '; MESSAGE_LENGTH], length: text.len().min(MESSAGE_LENGTH), priority: priority };
+    message.text[..message.length].copy_from_slice(&text.as_bytes()[..message.length]);
+    content.message = Some(message);
+    content.updated = true;
+    MESSAGE_TIMEOUT.store(MESSAGE_DURATIONS[priority as usize], Ordering::Relaxed);
+  }
+
+  // removes a message that was answered, e.g. a question, so any priority can follow it
+  fn clear_message(&mut self) {
+    let content = self.content();
+    if content.message.is_some() {
+      content.message = None;
+      content.updated = true;
+    }
+  }
+}
+
+// returns true once every display update interval
+pub fn update_time_arrived() -> bool {
+  return UPDATE_TIME_ARRIVED.fetch_and(false, Ordering::Relaxed);
+}
+
+pub unsafe fn on_timer_tick() {
+  static mut OVERFLOWS : u8 = 0;
+
+  let timeout = MESSAGE_TIMEOUT.load(Ordering::Relaxed);
+  if timeout > 0 {
+    MESSAGE_TIMEOUT.store(timeout - 1, Ordering::Relaxed);
+  }
+
+  if OVERFLOWS > DISPLAY_UPDATE_OVERFLOWS {
+    UPDATE_TIME_ARRIVED.store(true, Ordering::Relaxed);
+    OVERFLOWS = 0;
+  } else {
+    OVERFLOWS += 1;
+  }
+}
+
+// characters of the display, pages get rendered into it before they are sent
+pub struct FrameBuffer {
+  pub cells: [[u8; MAX_COLUMNS]; MAX_ROWS],
+  pub columns: usize,
+  pub rows: usize,
+  cursor: (usize, usize)
+}
+
+impl FrameBuffer {
+  pub fn new(columns: u8, rows: u8) -> FrameBuffer {
+    return FrameBuffer {
+      cells: [[b' '; MAX_COLUMNS]; MAX_ROWS],
+      columns: (columns as usize).min(MAX_COLUMNS),
+      rows: (rows as usize).min(MAX_ROWS),
+      cursor: (0, 0)
+    }
+  }
+
+  pub fn clear(&mut self) {
+    self.cells = [[b' '; MAX_COLUMNS]; MAX_ROWS];
+    self.cursor = (0, 0);
+  }
+
+  pub fn set_cursor(&mut self, position: (u8,u8)) {
+    self.cursor = (position.0 as usize, position.1 as usize);
+  }
+
+  // text that does not fit into the row gets cut off
+  pub fn write_str(&mut self, text: &str) {
+    let (column, row) = self.cursor;
+    if row >= self.rows { return }
+    for (i, c) in text.bytes().enumerate().take(self.columns.saturating_sub(column)) {
+      self.cells[row][column + i] = c;
+    }
+    self.cursor = (column + text.len(), row);
+  }
+
+  pub fn write_char(&mut self, c: u8) {
+    let (column, row) = self.cursor;
+    if row < self.rows && column < self.columns {
+      self.cells[row][column] = c;
+    }
+    self.cursor = (column + 1, row);
+  }
+}
+
+/* What the display shows */
+pub struct Content {
+  pub state: Option<State>,
+  pub ramp: Option<(u16, u8)>, // current bpm and progress of a tempo ramp
+  pub position: Position,
+  pub message: Option<Message>,
+  pub diagnostics: (u32, u32), // jitter in us and dropped midi bytes
+  pub updated: bool
+}
+
+impl Content {
+  pub fn new() -> Content {
+    return Content {
+      state: None,
+      ramp: None,
+      position: Position { bar: 1, beat: 1, sixteenth: 1 },
+      message: None,
+      diagnostics: (0, 0),
+      updated: true
+    }
+  }
+
+  // bpm of the clock, follows the tempo ramp while it is running
+  pub fn bpm(&self) -> u16 {
+    let bpm = self.state.map_or(0, |state| state.bpm);
+    return self.ramp.map_or(bpm, |(bpm, _)| bpm);
+  }
+
+  pub fn shows_position(&self) -> bool {
+    return self.state.map_or(false, |state| {
+      return state.menu == MenuPage::Bpm && state.running == RunState::RUNNING && self.ramp.is_none();
+    });
+  }
+
+  // returns to the page when the message timed out
+  pub fn expire_message(&mut self) {
+    if self.message.is_some() && MESSAGE_TIMEOUT.load(Ordering::Relaxed) == 0 {
+      self.message = None;
+      self.updated = true;
+    }
+  }
+
+  pub fn render_page(&self, frame: &mut FrameBuffer) {
+    let state = self.state.unwrap();
+    frame.clear();
+
+    if let Some(message) = self.message {
+      render_message(frame, &message);
+      return;
+    }
+
+    match state.menu {
+      MenuPage::Bpm => self.render_bpm(frame, &state),
+      MenuPage::Song => self.render_song(frame, &state),
+      MenuPage::Division(i) => {
+        render_option(frame, ["Div 1+2", "Div 3+4"][i as usize], u16_to_string(state.clock_divisions[i as usize] as u16));
+      },
+      MenuPage::BarLength => {
+        frame.write_str("Bar");
+        frame.set_cursor((0,1));
+        frame.write_str(u16_to_string(state.clock_bar_length as u16));
+        frame.write_str("/4");
+      },
+      MenuPage::AutoAdvance => {
+        render_option(frame, "Advance", if state.auto_advance { "on" } else { "off" });
+      },
+      MenuPage::Ramp => {
+        frame.write_str("Ramp");
+        frame.set_cursor((0,1));
+        match self.ramp {
+          Some((_, progress)) => write_progress(frame, progress),
+          None => frame.write_str("off")
+        }
+      },
+      MenuPage::RampTarget => {
+        render_option(frame, "Ramp to", u16_to_string(state.ramp_target));
+      },
+      MenuPage::RampBars => {
+        render_option(frame, "Bars", u16_to_string(state.ramp_bars as u16));
+      },
+      MenuPage::RampMode => {
+        render_option(frame, "Curve", if state.ramp_mode == RampMode::Linear { "linear" } else { "exp" });
+      },
+      MenuPage::ClockSource => {
+        let source = if state.clock_source == ClockSource::Internal { "internal" } else { "midi in" };
+        render_option(frame, "Source", source);
+      },
+      MenuPage::QuantizedStart => {
+        render_option(frame, "Q-Start", if state.quantized_start { "on" } else { "off" });
+      },
+      MenuPage::OffsetMillis(i) => render_offset(frame, i, state.output_offsets[i as usize].millis, " ms"),
+      MenuPage::OffsetTicks(i) => render_offset(frame, i, state.output_offsets[i as usize].ticks, " tk"),
+      MenuPage::Diagnostics => {
+        frame.write_str("J ");
+        frame.write_str(u32_to_string(self.diagnostics.0));
+        frame.write_str("us");
+        frame.set_cursor((0,1));
+        frame.write_str("D ");
+        frame.write_str(u32_to_string(self.diagnostics.1));
+      }
+    }
+  }
+
+  fn render_bpm(&self, frame: &mut FrameBuffer, state: &State) {
+    // write transport and bpm
+    frame.write_char(match state.running {
+      RunState::RUNNING => GLYPH_PLAY,
+      RunState::PAUSED => GLYPH_PAUSE,
+      RunState::ARMED => GLYPH_ARMED,
+      _ => GLYPH_STOP
+    });
+    frame.write_str(" ");
+    frame.write_str(u16_to_string(self.bpm()));
+    frame.write_str("bpm");
+
+    // show sync state in the last column
+    frame.set_cursor((frame.columns as u8 - 1, 0));
+    if state.clock_sync {
+      frame.write_char(GLYPH_SYNC);
+    } else if state.clock_source != ClockSource::Internal {
+      frame.write_char(GLYPH_MIDI);
+    }
+
+    //write run state
+    frame.set_cursor((0,1));
+    if let Some((_, progress)) = self.ramp {
+      frame.write_str("ramp ");
+      write_progress(frame, progress);
+      return;
+    }
+    if self.shows_position() {
+      self.write_position(frame);
+
+      // flash a beat marker on every quarter
+      if self.position.sixteenth == 1 {
+        frame.set_cursor((frame.columns as u8 - 1, 1));
+        frame.write_char(if self.position.beat == 1 { GLYPH_DOWNBEAT } else { GLYPH_BEAT });
+      }
+      return;
+    }
+    match state.running {
+      RunState::RUNNING => frame.write_str("running"),
+      RunState::PAUSED => frame.write_str("paused"),
+      RunState::ARMED => frame.write_str("armed"),
+      _ => frame.write_str("stopped")
+    }
+  }
+
+  // writes bar.beat.sixteenth
+  fn write_position(&self, frame: &mut FrameBuffer) {
+    let values = [self.position.bar % 1000, self.position.beat as u16, self.position.sixteenth as u16];
+    for (i, value) in values.iter().enumerate() {
+      if i > 0 {
+        frame.write_str(".");
+      }
+      frame.write_str(u16_to_string(*value));
+    }
+  }
+
+  fn render_song(&self, frame: &mut FrameBuffer, state: &State) {
+    match state.song {
+      Some(song) => {
+        frame.write_str(song.name());
+        frame.set_cursor((0,1));
+        frame.write_str("Song ");
+        frame.write_str(u16_to_string(song.index as u16 + 1));
+      },
+      None => render_option(frame, "Song", "free")
+    }
+  }
+}
+
+// continues in the next row if the text is longer than a row
+fn render_message(frame: &mut FrameBuffer, message: &Message) {
+  for (i, c) in message.text[..message.length].iter().enumerate() {
+    let row = i / frame.columns;
+    if row >= frame.rows { break }
+    frame.set_cursor(((i % frame.columns) as u8, row as u8));
+    frame.write_char(*c);
+  }
+}
+
+fn write_progress(frame: &mut FrameBuffer, percent: u8) {
+  frame.write_str(u16_to_string(percent as u16));
+  frame.write_str("%");
+}
+
+fn render_option(frame: &mut FrameBuffer, name: &str, value: &str) {
+  frame.write_str(name);
+  frame.set_cursor((0,1));
+  frame.write_str(value);
+}
+
+fn render_offset(frame: &mut FrameBuffer, output: u8, offset: i8, unit: &str) {
+  frame.write_str(OUTPUT_NAMES[output as usize]);
+  frame.write_str(unit);
+
+  frame.set_cursor((0,1));
+  if offset > 0 {
+    frame.write_str("+");
+  }
+  frame.write_str(i16_to_string(offset as i16));
+}
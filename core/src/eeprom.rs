@@ -0,0 +1,130 @@
+/*
+ * Driver of the 24Cxx i2c eeproms on any embedded-hal i2c bus
+ *
+ * The chips wrap around to the start of a page when a write crosses its end, so longer writes are
+ * split at the pages. After every page the driver polls until the chip finished its write cycle and
+ * reads the page back.
+ */
+
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+
+// a0 to a2 tied low
+const DEVICE_ADDRESS: u8 = 0b1010_0000 >> 1;
+
+// largest page of the family, sizes the write buffer
+const MAX_PAGE_SIZE: usize = 128;
+
+// the write cycle takes up to 5ms, a poll about 70us at 400khz
+const ACK_POLLS: u16 = 200;
+
+/* Size and addressing of a chip of the family */
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Chip {
+  pub capacity: usize,
+  pub page_size: usize,
+  // chips up to 16kbit take one address byte, the bits above go into the device address
+  pub address_bytes: usize
+}
+
+pub const CHIP_24C02: Chip = Chip { capacity: 0x100, page_size: 8, address_bytes: 1 };
+pub const CHIP_24C16: Chip = Chip { capacity: 0x800, page_size: 16, address_bytes: 1 };
+pub const CHIP_24C64: Chip = Chip { capacity: 0x2000, page_size: 32, address_bytes: 2 };
+pub const CHIP_24C256: Chip = Chip { capacity: 0x8000, page_size: 64, address_bytes: 2 };
+pub const CHIP_24C512: Chip = Chip { capacity: 0x10000, page_size: 128, address_bytes: 2 };
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum EepromError<E> {
+  I2c(E), // error of the bus, e.g. the chip did not acknowledge
+  Timeout, // the chip did not finish the write cycle
+  Verify, // the bytes read back differ from the written ones
+  OutOfRange
+}
+
+pub struct Eeprom<I2C> {
+  i2c: I2C,
+  chip: Chip
+}
+
+impl<I2C, E> Eeprom<I2C> where I2C: Write<Error = E> + WriteRead<Error = E> {
+  pub fn new(i2c: I2C, chip: Chip) -> Eeprom<I2C> {
+    return Eeprom {
+      i2c: i2c,
+      chip: chip
+    }
+  }
+
+  pub fn chip(&self) -> Chip {
+    return self.chip;
+  }
+
+  // sequential reads continue over the page boundaries
+  pub fn read(&mut self, mem_addr: u16, buffer: &mut [u8]) -> Result<(), EepromError<E>> {
+    self.check_range(mem_addr, buffer.len())?;
+    let (device, address, length) = self.address(mem_addr);
+    return self.i2c.write_read(device, &address[..length], buffer).map_err(EepromError::I2c);
+  }
+
+  // returns when all bytes are written and verified
+  pub fn write(&mut self, mem_addr: u16, data: &[u8]) -> Result<(), EepromError<E>> {
+    self.check_range(mem_addr, data.len())?;
+    let mut address = mem_addr as usize;
+    let mut rest = data;
+    while !rest.is_empty() {
+      let length = rest.len().min(self.chip.page_size - address % self.chip.page_size);
+      self.write_page(address as u16, &rest[..length])?;
+      address += length;
+      rest = &rest[length..];
+    }
+    return Ok(());
+  }
+
+  pub fn write_u16(&mut self, mem_addr: u16, data: u16) -> Result<(), EepromError<E>> {
+    return self.write(mem_addr, &data.to_be_bytes());
+  }
+
+  pub fn read_u16(&mut self, mem_addr: u16) -> Result<u16, EepromError<E>> {
+    let mut buffer: [u8;2] = [0,0];
+    self.read(mem_addr, &mut buffer)?;
+    return Ok(u16::from_be_bytes(buffer));
+  }
+
+  // the data must not cross the end of the page
+  fn write_page(&mut self, mem_addr: u16, data: &[u8]) -> Result<(), EepromError<E>> {
+    let (device, address, length) = self.address(mem_addr);
+    // address bytes + one page
+    let mut buffer = [0u8; 2 + MAX_PAGE_SIZE];
+    buffer[..length].copy_from_slice(&address[..length]);
+    buffer[length..length + data.len()].copy_from_slice(data);
+    self.i2c.write(device, &buffer[..length + data.len()]).map_err(EepromError::I2c)?;
+    self.wait_for_write(mem_addr)?;
+
+    let mut written = [0u8; MAX_PAGE_SIZE];
+    self.read(mem_addr, &mut written[..data.len()])?;
+    if &written[..data.len()] != data { return Err(EepromError::Verify) }
+    return Ok(());
+  }
+
+  // the chip does not acknowledge its address until the write cycle is done,
+  // the bus can not tell that apart from other errors, so all of them count as busy
+  fn wait_for_write(&mut self, mem_addr: u16) -> Result<(), EepromError<E>> {
+    let (device, address, length) = self.address(mem_addr);
+    for _ in 0..ACK_POLLS {
+      // only sets the address pointer of the chip
+      if self.i2c.write(device, &address[..length]).is_ok() { return Ok(()) }
+    }
+    return Err(EepromError::Timeout);
+  }
+
+  // device address and the address bytes sent before the data
+  fn address(&self, mem_addr: u16) -> (u8, [u8; 2], usize) {
+    if self.chip.address_bytes == 1 {
+      return (DEVICE_ADDRESS | (mem_addr >> 8) as u8 & 0b111, [mem_addr as u8, 0], 1);
+    }
+    return (DEVICE_ADDRESS, mem_addr.to_be_bytes(), 2);
+  }
+
+  fn check_range(&self, mem_addr: u16, length: usize) -> Result<(), EepromError<E>> {
+    if mem_addr as usize + length > self.chip.capacity { return Err(EepromError::OutOfRange) }
+    return Ok(());
+  }
+}
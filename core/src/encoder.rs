@@ -0,0 +1,77 @@
+/*
+ * Decodes the quadrature signal of the rotary encoder into detents
+ */
+
+#[derive(Copy,Clone)]
+enum EncoderState {
+  CwStart = 0x01,
+  CwStep1 = 0x02,
+  CwStep2 = 0x03,
+  CwFinal = 0x10,
+
+  CcwStart = 0x04,
+  CcwStep1 = 0x05,
+  CcwStep2 = 0x06,
+  CcwFinal = 0x20,
+
+  Undefined = 0x40,
+}
+
+const TRANSITION_LOOKUPTABLE: [[EncoderState; 4]; 7] = [
+  [ EncoderState::Undefined, EncoderState::CcwStart, EncoderState::CwStart, EncoderState::Undefined ],  // init state -> 1,2
+
+  [ EncoderState::Undefined, EncoderState::Undefined, EncoderState::Undefined, EncoderState::CwStep1 ], // cw start -> 3
+  [ EncoderState::Undefined, EncoderState::CwStep2, EncoderState::CwStart, EncoderState::Undefined ], // cw step1 -> 1, <- 2
+  [ EncoderState::CwFinal, EncoderState::CwStep2, EncoderState::Undefined, EncoderState::CwStep1 ], // cw step2 -> 0, <- 2
+
+  [ EncoderState::Undefined, EncoderState::Undefined, EncoderState::Undefined, EncoderState::CcwStep1 ], // ccw start -> 3
+  [ EncoderState::Undefined, EncoderState::Undefined, EncoderState::CcwStep2, EncoderState::Undefined ], // ccw step1 -> 2, <- 0
+  [ EncoderState::CcwFinal, EncoderState::Undefined, EncoderState::Undefined , EncoderState::Undefined ] // ccw step2 -> 0, <- 2
+];
+
+fn get_transition(state: u8, transition: u8) -> EncoderState {
+  return TRANSITION_LOOKUPTABLE[state as usize][transition as usize];
+}
+
+// ms between detents and the acceleration when turning faster
+const ACCELERATION_STEPS: [(u32, u8); 3] = [(20, 8), (40, 4), (80, 2)];
+
+// acceleration for the ms between two detents
+pub fn acceleration(interval: u32) -> u8 {
+  return ACCELERATION_STEPS.iter()
+    .find(|(max_interval, _)| interval < *max_interval)
+    .map_or(1, |(_, acceleration)| *acceleration);
+}
+
+pub struct EncoderDecoder {
+  state: EncoderState,
+  reading: u8
+}
+
+impl EncoderDecoder {
+  pub const fn new() -> EncoderDecoder {
+    return EncoderDecoder { state: EncoderState::Undefined, reading: 0 };
+  }
+
+  // gets called with the pin that changed, true if it is low. returns 1 for a detent clockwise, -1 counterclockwise
+  pub fn on_change(&mut self, pin1: Option<bool>, pin2: Option<bool>) -> i8 {
+    pin1.map(|r| {
+      self.reading &= !0b01;
+      self.reading |= r as u8;
+    });
+
+    pin2.map(|r| {
+      self.reading &= !0b10;
+      self.reading |= (r as u8) << 1;
+    });
+
+    self.state = get_transition((self.state as u8) & 0x0F, self.reading);
+
+    if self.state as u8 == EncoderState::CwFinal as u8 {
+      return 1;
+    } else if self.state as u8 == EncoderState::CcwFinal as u8 {
+      return -1;
+    }
+    return 0;
+  }
+}
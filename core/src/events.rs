@@ -0,0 +1,18 @@
+/*
+ * Input events that are handled by the statemachine in the order they happened
+ */
+
+use crate::gestures::{ButtonEvent};
+use crate::diagnostics::{Request};
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Event {
+  Button(ButtonEvent),
+  EncoderTurn(i16, u8), // detents and acceleration
+  ControlChange(u8, u8), // controller and value
+  QuantizedStart, // clock started on a bar of the external clock
+  RampEnd(u16), // bpm the ramp ended with
+  SongEnd,
+  SyncLost, // external clock stopped sending while running
+  DiagnosticsRequest(Request) // sysex request for the timing statistics
+}
@@ -0,0 +1,139 @@
+/*
+ * Turns debounced button states into press, long press, double click, repeat and combo events
+ */
+
+use heapless::spsc::{Queue};
+
+const BUTTON_COUNT: usize = 4;
+
+// bitmasks of the buttons in the debounced readings
+pub const BUTTON1_MASK : u8 = 0b00000001;
+pub const BUTTON2_MASK : u8 = 0b00000010;
+pub const BUTTON3_MASK : u8 = 0b00000100;
+pub const BUTTON4_MASK : u8 = 0b00001000;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum ButtonEvent {
+  Press(u8), // button mask
+  Release(u8),
+  LongPress(u8),
+  DoubleClick(u8),
+  AutoRepeat(u8),
+  Combo(u8) // mask of all buttons held together
+}
+
+// times in ms
+#[derive(Copy, Clone)]
+pub struct GestureConfig {
+  pub long_press: u16,
+  pub double_click: u16,
+  pub repeat_interval: u16,
+  pub repeat_mask: u8 // buttons that repeat while held, starting at the long press
+}
+
+pub const DEFAULT_GESTURE_CONFIG: GestureConfig = GestureConfig {
+  long_press: 800,
+  double_click: 300,
+  repeat_interval: 100,
+  repeat_mask: 0
+};
+
+#[derive(Copy, Clone)]
+struct ButtonTiming {
+  pressed_at: u32,
+  released_at: Option<u32>, // last release that can start a double click
+  long_pressed: bool,
+  double_clicked: bool,
+  next_repeat: u32
+}
+
+pub struct GestureDetector {
+  config: GestureConfig,
+  pressed: u8,
+  ignored: u8, // buttons that were down before the detector saw them, until their release
+  combo: u8, // buttons of the last reported combo
+  timings: [ButtonTiming; BUTTON_COUNT],
+  events: Queue<ButtonEvent, 8>
+}
+
+impl GestureDetector {
+  pub const fn new(config: GestureConfig) -> GestureDetector {
+    return GestureDetector {
+      config: config,
+      pressed: 0,
+      ignored: 0,
+      combo: 0,
+      timings: [ButtonTiming { pressed_at: 0, released_at: None, long_pressed: false, double_clicked: false, next_repeat: 0 }; BUTTON_COUNT],
+      events: Queue::new()
+    }
+  }
+
+  // buttons held right now, e.g. since power up, do not report their release
+  pub fn ignore(&mut self, buttons: u8) {
+    self.ignored |= buttons & !self.pressed;
+  }
+
+  // gets called with the debounced button changes, now is the time in ms
+  pub fn on_change(&mut self, changes: u8, reading: u8, now: u32) {
+    let config = self.config;
+    let changes = changes & !self.ignored;
+    self.ignored &= reading;
+    for i in 0..BUTTON_COUNT {
+      let mask = 1 << i;
+      if changes & mask == 0 { continue }
+
+      let timing = &mut self.timings[i];
+      if reading & mask > 0 {
+        self.pressed |= mask;
+        timing.pressed_at = now;
+        timing.long_pressed = false;
+        timing.double_clicked = timing.released_at.map_or(false, |t| now.wrapping_sub(t) <= config.double_click as u32);
+        timing.released_at = None;
+
+        self.events.enqueue(ButtonEvent::Press(mask)).ok();
+        if timing.double_clicked {
+          self.events.enqueue(ButtonEvent::DoubleClick(mask)).ok();
+        }
+      } else {
+        self.pressed &= !mask;
+        // long presses and double clicks do not count as the first click of the next double click
+        timing.released_at = if timing.long_pressed || timing.double_clicked { None } else { Some(now) };
+        self.events.enqueue(ButtonEvent::Release(mask)).ok();
+      }
+    }
+
+    // combos get reported once when the last button of it gets pressed
+    if self.pressed.count_ones() > 1 && self.pressed & !self.combo > 0 {
+      self.events.enqueue(ButtonEvent::Combo(self.pressed)).ok();
+    }
+    // buttons stay part of the combo until all of them are released
+    if self.pressed.count_ones() > 1 || self.pressed == 0 {
+      self.combo = self.pressed;
+    }
+  }
+
+  // checks the held buttons and returns the next event
+  pub fn poll(&mut self, now: u32) -> Option<ButtonEvent> {
+    let config = self.config;
+    for i in 0..BUTTON_COUNT {
+      let mask = 1 << i;
+      if self.pressed & mask == 0 { continue }
+
+      // buttons of a combo do not long press
+      if self.combo & mask > 0 { continue }
+
+      let timing = &mut self.timings[i];
+      let held = now.wrapping_sub(timing.pressed_at);
+      if !timing.long_pressed && held >= config.long_press as u32 {
+        timing.long_pressed = true;
+        timing.next_repeat = now;
+        self.events.enqueue(ButtonEvent::LongPress(mask)).ok();
+      }
+      if timing.long_pressed && config.repeat_mask & mask > 0 && (now.wrapping_sub(timing.next_repeat) as i32) >= 0 {
+        timing.next_repeat = now.wrapping_add(config.repeat_interval as u32);
+        self.events.enqueue(ButtonEvent::AutoRepeat(mask)).ok();
+      }
+    }
+    return self.events.dequeue();
+  }
+}
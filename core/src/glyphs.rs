@@ -0,0 +1,35 @@
+/*
+ * Custom 5x8 characters that get uploaded into the cgram of the lcd
+ */
+
+pub const GLYPH_COUNT: usize = 8;
+
+// character codes of the glyphs
+pub const GLYPH_PLAY: u8 = 0;
+pub const GLYPH_PAUSE: u8 = 1;
+pub const GLYPH_STOP: u8 = 2;
+pub const GLYPH_SYNC: u8 = 3;
+pub const GLYPH_DOWNBEAT: u8 = 4;
+pub const GLYPH_BEAT: u8 = 5;
+pub const GLYPH_MIDI: u8 = 6;
+pub const GLYPH_ARMED: u8 = 7;
+
+// one byte per row, the lower 5 bits are the pixels
+pub const GLYPHS: [[u8; 8]; GLYPH_COUNT] = [
+  // play
+  [0b01000, 0b01100, 0b01110, 0b01111, 0b01110, 0b01100, 0b01000, 0b00000],
+  // pause
+  [0b11011, 0b11011, 0b11011, 0b11011, 0b11011, 0b11011, 0b11011, 0b00000],
+  // stop
+  [0b00000, 0b11111, 0b11111, 0b11111, 0b11111, 0b11111, 0b00000, 0b00000],
+  // sync lock
+  [0b01110, 0b10001, 0b10001, 0b11111, 0b11011, 0b11011, 0b11111, 0b00000],
+  // beat bar on the first beat of a bar
+  [0b11111, 0b11111, 0b11111, 0b11111, 0b11111, 0b11111, 0b11111, 0b11111],
+  // beat bar
+  [0b00000, 0b00000, 0b00000, 0b00000, 0b11111, 0b11111, 0b11111, 0b11111],
+  // midi port
+  [0b01110, 0b10101, 0b11011, 0b10001, 0b10101, 0b01110, 0b00000, 0b00000],
+  // armed, waiting for the next bar
+  [0b11111, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b11111, 0b00000]
+];
@@ -0,0 +1,263 @@
+/*
+ * Graphical ui of the 240x240 tft, drawn on any embedded-graphics target
+ *
+ * Only the parts that changed since the last draw get redrawn, the spi bus is too slow for whole frames.
+ */
+
+use embedded_graphics::{
+  prelude::*,
+  pixelcolor::{Rgb565},
+  primitives::{Rectangle, Circle, Triangle, PrimitiveStyle},
+  mono_font::{MonoTextStyle, MonoTextStyleBuilder, ascii::{FONT_10X20, FONT_6X10}},
+  text::{Text}
+};
+
+use crate::display::{Content, FrameBuffer};
+use crate::statemachine::{State, RunState, MenuPage, ClockSource};
+use crate::delays::{OUTPUT_COUNT, OUTPUT_MASKS};
+
+pub const SCREEN_SIZE: u16 = 240;
+
+pub const TEXT_COLUMNS: usize = 22;
+pub const TEXT_ROWS: usize = 2;
+pub const TEXT_ORIGIN: Point = Point::new(10, 195);
+pub const TEXT_ROW_HEIGHT: i32 = 23;
+
+// seven segment digits of the bpm
+pub const DIGIT_ORIGIN: Point = Point::new(42, 32);
+pub const DIGIT_SIZE: Size = Size::new(40, 72);
+pub const DIGIT_SPACING: i32 = 52;
+pub const SEGMENT_WIDTH: u32 = 8;
+
+// bits are segments a-g, clockwise from the top and the middle one last
+const DIGIT_SEGMENTS: [u8; 10] = [0x3F, 0x06, 0x5B, 0x4F, 0x66, 0x6D, 0x7D, 0x07, 0x7F, 0x6F];
+
+pub const RING_CENTER: Point = Point::new(60, 140);
+pub const RING_MARKER_SIZE: u32 = 10;
+
+// 16 positions on a circle with radius 30, clockwise from the top
+pub const RING_POSITIONS: [(i32, i32); 16] = [
+  (0, -30), (11, -28), (21, -21), (28, -11), (30, 0), (28, 11), (21, 21), (11, 28),
+  (0, 30), (-11, 28), (-21, 21), (-28, 11), (-30, 0), (-28, -11), (-21, -21), (-11, -28)
+];
+
+pub const LED_ORIGIN: Point = Point::new(122, 132);
+pub const LED_SIZE: u32 = 16;
+pub const LED_SPACING: i32 = 28;
+const LED_NAMES: [&str; OUTPUT_COUNT] = ["M1", "M2", "TR", "RS"];
+
+pub const ICON_SIZE: u32 = 24;
+pub const TRANSPORT_ORIGIN: Point = Point::new(8, 4);
+pub const SYNC_ORIGIN: Point = Point::new(208, 4);
+
+pub const BACKGROUND: Rgb565 = Rgb565::BLACK;
+pub const SEGMENT_OFF: Rgb565 = Rgb565::new(3, 6, 3);
+pub const LED_OFF: Rgb565 = Rgb565::new(0, 10, 0);
+
+/* Parts of the ui that are currently on the screen */
+#[derive(Copy, Clone, PartialEq)]
+struct Shown {
+  bpm: u16,
+  transport: RunState,
+  sync: Option<Rgb565>,
+  beats: u8,
+  beat: u8,
+  activity: u8,
+  text: [[u8; TEXT_COLUMNS]; TEXT_ROWS]
+}
+
+pub struct Screen {
+  frame: FrameBuffer,
+  shown: Option<Shown>
+}
+
+impl Screen {
+  pub fn new() -> Screen {
+    return Screen {
+      frame: FrameBuffer::new(TEXT_COLUMNS as u8, TEXT_ROWS as u8),
+      shown: None
+    };
+  }
+
+  // returns true if the activity leds differ from the screen
+  pub fn activity_changed(&self, activity: u8) -> bool {
+    return self.shown.map_or(false, |s| s.activity != activity);
+  }
+
+  // draws the labels on a cleared screen, everything else gets drawn by the next draw
+  pub fn draw_labels<D: DrawTarget<Color = Rgb565>>(&mut self, target: &mut D) -> Result<(), D::Error> {
+    self.shown = None;
+
+    let style = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+    for (i, name) in LED_NAMES.iter().enumerate() {
+      let position = LED_ORIGIN + Point::new(i as i32 * LED_SPACING + 2, LED_SIZE as i32 + 12);
+      Text::new(name, position, style).draw(target)?;
+    }
+    return Ok(());
+  }
+
+  // redraws the parts of the content that changed, activity are the outputs that ticked
+  pub fn draw<D: DrawTarget<Color = Rgb565>>(&mut self, target: &mut D, content: &Content, activity: u8) -> Result<(), D::Error> {
+    let state = content.state.unwrap();
+    content.render_page(&mut self.frame);
+
+    let mut next = Shown {
+      bpm: content.bpm(),
+      transport: state.running,
+      sync: sync_color(&state),
+      beats: state.clock_bar_length,
+      beat: if content.shows_position() { content.position.beat } else { 0 },
+      activity: activity,
+      text: [[b' '; TEXT_COLUMNS]; TEXT_ROWS]
+    };
+
+    // the bpm page is drawn as graphics, its text line shows the position
+    let first_row = if state.menu == MenuPage::Bpm && content.message.is_none() { 1 } else { 0 };
+    for row in first_row..TEXT_ROWS {
+      for column in 0..TEXT_COLUMNS {
+        // the custom characters of the lcd can not be shown
+        let c = self.frame.cells[row][column];
+        next.text[row][column] = if c < b' ' { b' ' } else { c };
+      }
+    }
+
+    // forget what is shown until everything was drawn, a failed draw gets repeated
+    let shown = self.shown.take();
+    let changed = |f: &dyn Fn(&Shown) -> bool| shown.map_or(true, |s| f(&s));
+
+    if changed(&|s| s.bpm != next.bpm) {
+      draw_bpm(target, next.bpm)?;
+    }
+    if changed(&|s| s.transport != next.transport) {
+      draw_transport(target, next.transport)?;
+    }
+    if changed(&|s| s.sync != next.sync) {
+      draw_sync(target, next.sync)?;
+    }
+    if changed(&|s| s.beats != next.beats || s.beat != next.beat) {
+      draw_beat_ring(target, next.beats, next.beat)?;
+    }
+    if changed(&|s| s.activity != next.activity) {
+      draw_activity(target, next.activity)?;
+    }
+    for row in 0..TEXT_ROWS {
+      if changed(&|s| s.text[row] != next.text[row]) {
+        draw_text_row(target, row, &next.text[row])?;
+      }
+    }
+
+    self.shown = Some(next);
+    return Ok(());
+  }
+}
+
+fn sync_color(state: &State) -> Option<Rgb565> {
+  if state.clock_sync {
+    return Some(Rgb565::GREEN);
+  } else if state.clock_source != ClockSource::Internal {
+    return Some(Rgb565::BLUE);
+  }
+  return None;
+}
+
+fn fill<D: DrawTarget<Color = Rgb565>>(target: &mut D, origin: Point, size: Size, color: Rgb565) -> Result<(), D::Error> {
+  return Rectangle::new(origin, size).into_styled(PrimitiveStyle::with_fill(color)).draw(target);
+}
+
+// three seven segment digits, leading zeros are left blank
+pub fn draw_bpm<D: DrawTarget<Color = Rgb565>>(target: &mut D, bpm: u16) -> Result<(), D::Error> {
+  let (w, h, t) = (DIGIT_SIZE.width, DIGIT_SIZE.height, SEGMENT_WIDTH);
+  let vertical = Size::new(t, h / 2 - t - t / 2);
+  let horizontal = Size::new(w - 2 * t, t);
+  let segments = [
+    (Point::new(t as i32, 0), horizontal),
+    (Point::new((w - t) as i32, t as i32), vertical),
+    (Point::new((w - t) as i32, (h / 2 + t / 2) as i32), vertical),
+    (Point::new(t as i32, (h - t) as i32), horizontal),
+    (Point::new(0, (h / 2 + t / 2) as i32), vertical),
+    (Point::new(0, t as i32), vertical),
+    (Point::new(t as i32, (h / 2 - t / 2) as i32), horizontal)
+  ];
+
+  let digits = [bpm / 100 % 10, bpm / 10 % 10, bpm % 10];
+  for (i, digit) in digits.iter().enumerate() {
+    let blank = i < 2 && bpm < [100, 10][i];
+    let origin = DIGIT_ORIGIN + Point::new(i as i32 * DIGIT_SPACING, 0);
+    for (s, (offset, size)) in segments.iter().enumerate() {
+      let on = !blank && DIGIT_SEGMENTS[*digit as usize] & (1 << s) > 0;
+      fill(target, origin + *offset, *size, if on { Rgb565::WHITE } else { SEGMENT_OFF })?;
+    }
+  }
+  return Ok(());
+}
+
+pub fn draw_transport<D: DrawTarget<Color = Rgb565>>(target: &mut D, running: RunState) -> Result<(), D::Error> {
+  let origin = TRANSPORT_ORIGIN;
+  let size = ICON_SIZE as i32;
+  fill(target, origin, Size::new(ICON_SIZE, ICON_SIZE), BACKGROUND)?;
+
+  match running {
+    RunState::RUNNING => {
+      Triangle::new(origin, origin + Point::new(size, size / 2), origin + Point::new(0, size))
+        .into_styled(PrimitiveStyle::with_fill(Rgb565::GREEN)).draw(target)?;
+    },
+    RunState::PAUSED => {
+      fill(target, origin + Point::new(2, 0), Size::new(7, ICON_SIZE), Rgb565::YELLOW)?;
+      fill(target, origin + Point::new(15, 0), Size::new(7, ICON_SIZE), Rgb565::YELLOW)?;
+    },
+    RunState::ARMED => {
+      Circle::new(origin, ICON_SIZE)
+        .into_styled(PrimitiveStyle::with_stroke(Rgb565::YELLOW, 3)).draw(target)?;
+    },
+    _ => {
+      fill(target, origin + Point::new(2, 2), Size::new(ICON_SIZE - 4, ICON_SIZE - 4), Rgb565::RED)?;
+    }
+  }
+  return Ok(());
+}
+
+// green when synced, blue when following midi in
+pub fn draw_sync<D: DrawTarget<Color = Rgb565>>(target: &mut D, color: Option<Rgb565>) -> Result<(), D::Error> {
+  return Circle::new(SYNC_ORIGIN, ICON_SIZE)
+    .into_styled(PrimitiveStyle::with_fill(color.unwrap_or(BACKGROUND))).draw(target);
+}
+
+// one marker for every beat of the bar, beat 0 shows no current beat
+pub fn draw_beat_ring<D: DrawTarget<Color = Rgb565>>(target: &mut D, beats: u8, beat: u8) -> Result<(), D::Error> {
+  let radius = RING_POSITIONS[4].0 + RING_MARKER_SIZE as i32;
+  fill(target, RING_CENTER - Point::new(radius, radius), Size::new(2 * radius as u32, 2 * radius as u32), BACKGROUND)?;
+
+  let beats = beats.max(1).min(RING_POSITIONS.len() as u8) as usize;
+  for i in 0..beats {
+    let (x, y) = RING_POSITIONS[i * RING_POSITIONS.len() / beats];
+    let top_left = RING_CENTER + Point::new(x, y) - Point::new(RING_MARKER_SIZE as i32 / 2, RING_MARKER_SIZE as i32 / 2);
+    let style = if i + 1 == beat as usize {
+      PrimitiveStyle::with_fill(if i == 0 { Rgb565::RED } else { Rgb565::YELLOW })
+    } else {
+      PrimitiveStyle::with_stroke(Rgb565::WHITE, 1)
+    };
+    Circle::new(top_left, RING_MARKER_SIZE).into_styled(style).draw(target)?;
+  }
+  return Ok(());
+}
+
+pub fn draw_activity<D: DrawTarget<Color = Rgb565>>(target: &mut D, outputs: u8) -> Result<(), D::Error> {
+  for i in 0..OUTPUT_COUNT {
+    let color = if outputs & OUTPUT_MASKS[i] > 0 { Rgb565::GREEN } else { LED_OFF };
+    Circle::new(LED_ORIGIN + Point::new(i as i32 * LED_SPACING, 0), LED_SIZE)
+      .into_styled(PrimitiveStyle::with_fill(color)).draw(target)?;
+  }
+  return Ok(());
+}
+
+// the background of the font overwrites the previous text
+pub fn draw_text_row<D: DrawTarget<Color = Rgb565>>(target: &mut D, row: usize, text: &[u8; TEXT_COLUMNS]) -> Result<(), D::Error> {
+  let style = MonoTextStyleBuilder::new()
+    .font(&FONT_10X20)
+    .text_color(Rgb565::WHITE)
+    .background_color(BACKGROUND)
+    .build();
+  let position = TEXT_ORIGIN + Point::new(0, row as i32 * TEXT_ROW_HEIGHT);
+  Text::new(core::str::from_utf8(text).unwrap_or(""), position, style).draw(target)?;
+  return Ok(());
+}
@@ -0,0 +1,30 @@
+/*
+ * Hardware independent logic of the midi clock: clock engine, state machine, midi,
+ * persistence format, the content of the display and the tft ui, and the drivers of the eeprom and lcd
+ * over embedded-hal. Builds for the firmware and the host.
+ */
+#![no_std]
+
+pub mod boot;
+pub mod clock;
+pub mod crash;
+pub mod delays;
+pub mod diagnostics;
+pub mod display;
+pub mod eeprom;
+pub mod encoder;
+pub mod events;
+pub mod gestures;
+pub mod glyphs;
+pub mod graphics;
+pub mod log;
+pub mod memory;
+pub mod midi;
+pub mod recovery;
+pub mod setlist;
+pub mod shell;
+pub mod st7066;
+pub mod statemachine;
+pub mod transport;
+pub mod triggers;
+pub mod utils;
@@ -0,0 +1,122 @@
+/*
+ * Log records with a level, formatted into lines that wait in a buffer until the uart is free
+ */
+
+use core::fmt;
+use heapless::spsc::{Queue};
+
+// longer records are cut off
+pub const LOG_LINE_LENGTH: usize = 64;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Level {
+  Error,
+  Warn,
+  Info,
+  Trace
+}
+
+impl Level {
+  pub fn label(&self) -> &'static str {
+    return match self {
+      Level::Error => "[ERROR] ",
+      Level::Warn => "[WARN] ",
+      Level::Info => "[INFO] ",
+      Level::Trace => "[TRACE] "
+    };
+  }
+
+  // true if the level is as important as the other level or more
+  pub fn includes(&self, other: Level) -> bool {
+    return other as u8 <= *self as u8;
+  }
+}
+
+/* One formatted record ending with a line break */
+pub struct LogLine {
+  bytes: [u8; LOG_LINE_LENGTH],
+  length: usize
+}
+
+impl LogLine {
+  pub fn format(level: Level, args: fmt::Arguments) -> LogLine {
+    let mut line = LogLine { bytes: [0; LOG_LINE_LENGTH], length: 0 };
+    fmt::Write::write_str(&mut line, level.label()).ok();
+    fmt::Write::write_fmt(&mut line, args).ok();
+
+    // the line break always fits, it replaces the end of a long record
+    line.length = line.length.min(LOG_LINE_LENGTH - 2);
+    line.bytes[line.length..line.length + 2].copy_from_slice(b"\r\n");
+    line.length += 2;
+    return line;
+  }
+
+  pub fn as_bytes(&self) -> &[u8] {
+    return &self.bytes[..self.length];
+  }
+}
+
+impl fmt::Write for LogLine {
+  fn write_str(&mut self, s: &str) -> fmt::Result {
+    let count = s.len().min(LOG_LINE_LENGTH - self.length);
+    self.bytes[self.length..self.length + count].copy_from_slice(&s.as_bytes()[..count]);
+    self.length += count;
+    return if count < s.len() { Err(fmt::Error) } else { Ok(()) };
+  }
+}
+
+/* Bytes of the lines that are not sent yet, a line is dropped as a whole if it does not fit */
+pub struct LogBuffer<const N: usize> {
+  queue: Queue<u8, N>,
+  dropped: u32
+}
+
+impl<const N: usize> LogBuffer<N> {
+  pub const fn new() -> LogBuffer<N> {
+    return LogBuffer { queue: Queue::new(), dropped: 0 };
+  }
+
+  // returns false if the line was dropped
+  pub fn push(&mut self, line: &LogLine) -> bool {
+    // tell about the lines lost before, once there is space again
+    if self.dropped > 0 {
+      let notice = LogLine::format(Level::Warn, format_args!("{} log lines dropped", self.dropped));
+      if !self.enqueue(notice.as_bytes()) {
+        self.dropped = self.dropped.saturating_add(1);
+        return false;
+      }
+      self.dropped = 0;
+    }
+
+    if !self.enqueue(line.as_bytes()) {
+      self.dropped = self.dropped.saturating_add(1);
+      return false;
+    }
+    return true;
+  }
+
+  fn enqueue(&mut self, bytes: &[u8]) -> bool {
+    if self.queue.capacity() - self.queue.len() < bytes.len() { return false }
+    for byte in bytes.iter() {
+      self.queue.enqueue(*byte).ok();
+    }
+    return true;
+  }
+
+  // hands the bytes to send until it does not take any more, e.g. while the uart is busy
+  pub fn flush(&mut self, mut send: impl FnMut(u8) -> bool) {
+    while let Some(byte) = self.queue.peek() {
+      if !send(*byte) { return }
+      self.queue.dequeue();
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    return self.queue.is_empty();
+  }
+
+  // lines lost since the last notice
+  pub fn dropped(&self) -> u32 {
+    return self.dropped;
+  }
+}
@@ -0,0 +1,91 @@
+/*
+ * Layout of the settings, the setlist and the crash report in the eeprom
+ */
+
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+
+use crate::eeprom::{Eeprom, EepromError};
+use crate::statemachine::{State, DEFAULT_STATE, STATE_SIZE};
+use crate::setlist::{Setlist, Song, SETLIST_LENGTH, SONG_SIZE};
+use crate::crash::{CrashReport, CRASH_REPORT_SIZE};
+
+pub const STATE_ADDRESS: u16 = 0x0004;
+pub const SETLIST_ADDRESS: u16 = 0x0100;
+
+// reserved for the report of the last crash
+pub const CRASH_REPORT_ADDRESS: u16 = 0x0040;
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum MemoryError<E> {
+  ReadError(EepromError<E>),
+  WriteError(EepromError<E>)
+}
+
+pub struct Memory<I2C> {
+  eeprom: Eeprom<I2C>
+}
+
+impl<I2C, E> Memory<I2C> where I2C: Write<Error = E> + WriteRead<Error = E> {
+  pub fn new(eeprom: Eeprom<I2C>) -> Memory<I2C> {
+    return Memory {
+      eeprom: eeprom
+    }
+  }
+
+  // the selected song is looked up in the stored setlist
+  pub fn load_state(&mut self) -> Option<State> {
+    let mut buffer = [0u8; STATE_SIZE];
+    self.eeprom.read(STATE_ADDRESS, &mut buffer).ok()?;
+    return Some(State::from_bytes(&buffer, &self.load_setlist()));
+  }
+
+  pub fn write_state(&mut self, state: &State) -> Result<(), MemoryError<E>> {
+    return self.eeprom.write(STATE_ADDRESS, &state.to_bytes()).map_err(MemoryError::WriteError);
+  }
+
+  pub fn load_setlist(&mut self) -> Setlist {
+    let mut buffer = [0u8; SETLIST_LENGTH * SONG_SIZE];
+    if self.eeprom.read(SETLIST_ADDRESS, &mut buffer).is_err() {
+      return Setlist::new();
+    }
+
+    let mut songs: [Option<Song>; SETLIST_LENGTH] = [None; SETLIST_LENGTH];
+    for (i, bytes) in buffer.chunks(SONG_SIZE).enumerate() {
+      songs[i] = Song::from_bytes(i as u8, bytes);
+    }
+    return Setlist::from_songs(songs);
+  }
+
+  // raw bytes for inspecting the memory
+  pub fn read_bytes(&mut self, address: u16, buffer: &mut [u8]) -> Result<(), MemoryError<E>> {
+    return self.eeprom.read(address, buffer).map_err(MemoryError::ReadError);
+  }
+
+  pub fn load_crash_report(&mut self) -> Option<CrashReport> {
+    let mut buffer = [0u8; CRASH_REPORT_SIZE];
+    self.eeprom.read(CRASH_REPORT_ADDRESS, &mut buffer).ok()?;
+    return CrashReport::from_bytes(&buffer);
+  }
+
+  pub fn write_crash_report(&mut self, report: &CrashReport) -> Result<(), MemoryError<E>> {
+    return self.eeprom.write(CRASH_REPORT_ADDRESS, &report.to_bytes()).map_err(MemoryError::WriteError);
+  }
+
+  // an invalid marker byte erases the report
+  pub fn clear_crash_report(&mut self) -> Result<(), MemoryError<E>> {
+    return self.eeprom.write(CRASH_REPORT_ADDRESS, &[0xFF]).map_err(MemoryError::WriteError);
+  }
+
+  pub fn write_song(&mut self, song: &Song) -> Result<(), MemoryError<E>> {
+    let address = SETLIST_ADDRESS + song.index as u16 * SONG_SIZE as u16;
+    return self.eeprom.write(address, &song.to_bytes()).map_err(MemoryError::WriteError);
+  }
+
+  // back to the default state and an empty setlist, the crash report stays
+  pub fn factory_reset(&mut self) -> Result<(), MemoryError<E>> {
+    self.write_state(&DEFAULT_STATE)?;
+    // erased song entries end the setlist
+    let erased = [0xFF; SETLIST_LENGTH * SONG_SIZE];
+    return self.eeprom.write(SETLIST_ADDRESS, &erased).map_err(MemoryError::WriteError);
+  }
+}
@@ -0,0 +1,75 @@
+/*
+ * Midi messages sent by the clock and the parser of the midi in port
+ */
+
+// #[derive(Copy,Clone)]
+pub enum MidiMessage {
+  Start = 0xFA,
+  TimingClock = 0xF8,
+  Continue = 0xFB,
+  Stop = 0xFC,
+  // Reset = 0xFF
+}
+
+const CONTROL_CHANGE_STATUS: u8 = 0xB0;
+const SYSEX_START: u8 = 0xF0;
+const SYSEX_END: u8 = 0xF7;
+
+// longer sysex messages are cut off, the clock only understands short requests
+pub const SYSEX_LENGTH: usize = 4;
+
+// messages of the midi in port the clock reacts to
+#[derive(Copy, Clone, PartialEq)]
+pub enum MidiInput {
+  TimingClock,
+  Start,
+  ControlChange(u8, u8), // controller and value
+  SysEx([u8; SYSEX_LENGTH], usize) // data bytes between start and end, and their count
+}
+
+/* Collects the received bytes into messages */
+pub struct MidiParser {
+  status: u8,
+  data: Option<u8>,
+  sysex: [u8; SYSEX_LENGTH],
+  sysex_length: usize
+}
+
+impl MidiParser {
+  pub const fn new() -> MidiParser {
+    return MidiParser { status: 0, data: None, sysex: [0; SYSEX_LENGTH], sysex_length: 0 };
+  }
+
+  // returns the message that got completed by the byte
+  pub fn parse(&mut self, byte: u8) -> Option<MidiInput> {
+    if byte == MidiMessage::TimingClock as u8 {
+      return Some(MidiInput::TimingClock);
+    } else if byte == MidiMessage::Start as u8 {
+      return Some(MidiInput::Start);
+    } else if byte >= 0xF8 {
+      // other realtime messages do not interrupt running status
+    } else if byte == SYSEX_END && self.status == SYSEX_START {
+      self.status = 0;
+      return Some(MidiInput::SysEx(self.sysex, self.sysex_length));
+    } else if byte >= 0x80 {
+      self.status = byte;
+      self.data = None;
+      self.sysex_length = 0;
+    } else if self.status == SYSEX_START {
+      if self.sysex_length < SYSEX_LENGTH {
+        self.sysex[self.sysex_length] = byte;
+      }
+      self.sysex_length += 1;
+    } else if self.status & 0xF0 == CONTROL_CHANGE_STATUS {
+      // running status, the status byte stays valid for the following messages
+      match self.data {
+        None => self.data = Some(byte),
+        Some(controller) => {
+          self.data = None;
+          return Some(MidiInput::ControlChange(controller, byte));
+        }
+      }
+    }
+    return None;
+  }
+}
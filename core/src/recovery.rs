@@ -0,0 +1,67 @@
+/*
+ * Tempo and run state that survive a reboot by the watchdog, packed into one word of ram
+ */
+
+use crate::statemachine::{State, RunState, ClockSource};
+
+const CHECK: u8 = 0xA5;
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct Snapshot {
+  pub bpm: u16,
+  pub running: RunState,
+  pub source: ClockSource
+}
+
+impl Snapshot {
+  pub fn from_state(state: &State) -> Snapshot {
+    return Snapshot { bpm: state.bpm, running: state.running, source: state.clock_source };
+  }
+
+  // layout: bpm[16] running[3] source[2] unused[3] check[8]
+  pub fn pack(&self) -> u32 {
+    let bits = self.bpm as u32 | (self.running as u32) << 16 | (self.source as u32) << 19;
+    return bits | (check(bits) as u32) << 24;
+  }
+
+  // random ram after power up reads as none
+  pub fn unpack(word: u32) -> Option<Snapshot> {
+    if (word >> 24) as u8 != check(word & 0xFFFFFF) { return None }
+    let running = match (word >> 16) & 0b111 {
+      0 => RunState::STOPPED,
+      1 => RunState::STOPPING,
+      2 => RunState::RUNNING,
+      3 => RunState::PAUSED,
+      4 => RunState::ARMED,
+      _ => return None
+    };
+    let source = match (word >> 19) & 0b11 {
+      0 => ClockSource::Internal,
+      1 => ClockSource::MidiIn,
+      _ => return None
+    };
+    return Some(Snapshot { bpm: word as u16, running: running, source: source });
+  }
+
+  // continues with the tempo and transport of the snapshot, the position starts over
+  pub fn restore(&self, state: &State) -> State {
+    let mut restored = *state;
+    restored.bpm = self.bpm;
+    restored.clock_source = self.source;
+    restored.running = match self.running {
+      // the stop message was sent already
+      RunState::STOPPING => RunState::STOPPED,
+      running => running
+    };
+    return restored;
+  }
+
+  // followers lost their position with the clock, they have to go back to the start
+  pub fn needs_restart(&self) -> bool {
+    return self.running == RunState::RUNNING || self.running == RunState::PAUSED;
+  }
+}
+
+fn check(bits: u32) -> u8 {
+  return bits.to_le_bytes()[..3].iter().fold(CHECK, |check, byte| check ^ byte);
+}
@@ -0,0 +1,100 @@
+/*
+ * Ordered list of songs with their tempo settings, stored in the eeprom
+ */
+
+pub const SETLIST_LENGTH: usize = 16;
+pub const SONG_NAME_LENGTH: usize = 8;
+
+// bytes of a song entry in memory
+pub const SONG_SIZE: usize = 16;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Song {
+  pub index: u8, // position in the setlist
+  pub name: [u8; SONG_NAME_LENGTH],
+  pub bpm: u16,
+  pub bar_length: u8,
+  pub divisions: [u8; 2],
+  pub bars: u8 // duration of the song, 0 if it plays until stopped
+}
+
+impl Song {
+  // layout: name[8] bpm_msb bpm_lsb bar_length division1 division2 bars reserved[2]
+  pub fn from_bytes(index: u8, bytes: &[u8]) -> Option<Song> {
+    let bpm = (bytes[8] as u16) << 8 | bytes[9] as u16;
+
+    // erased memory reads as 0xFF
+    if bpm == 0 || bpm == 0xFFFF {
+      return None;
+    }
+
+    let mut name = [b' '; SONG_NAME_LENGTH];
+    name.copy_from_slice(&bytes[0..SONG_NAME_LENGTH]);
+    return Some(Song {
+      index: index,
+      name: name,
+      bpm: bpm,
+      bar_length: bytes[10],
+      divisions: [bytes[11], bytes[12]],
+      bars: bytes[13]
+    });
+  }
+
+  pub fn to_bytes(&self) -> [u8; SONG_SIZE] {
+    let mut bytes = [0; SONG_SIZE];
+    bytes[0..SONG_NAME_LENGTH].copy_from_slice(&self.name);
+    bytes[8] = (self.bpm >> 8) as u8;
+    bytes[9] = (self.bpm & 0xFF) as u8;
+    bytes[10] = self.bar_length;
+    bytes[11] = self.divisions[0];
+    bytes[12] = self.divisions[1];
+    bytes[13] = self.bars;
+    return bytes;
+  }
+
+  pub fn name(&self) -> &str {
+    return core::str::from_utf8(&self.name).unwrap_or("");
+  }
+}
+
+pub struct Setlist {
+  songs: [Option<Song>; SETLIST_LENGTH],
+  length: u8
+}
+
+impl Setlist {
+  pub fn new() -> Setlist {
+    return Setlist {
+      songs: [None; SETLIST_LENGTH],
+      length: 0
+    }
+  }
+
+  // the setlist ends with the first empty entry
+  pub fn from_songs(songs: [Option<Song>; SETLIST_LENGTH]) -> Setlist {
+    let length = songs.iter().take_while(|s| s.is_some()).count() as u8;
+    return Setlist {
+      songs: songs,
+      length: length
+    }
+  }
+
+  pub fn len(&self) -> u8 {
+    return self.length;
+  }
+
+  pub fn get(&self, index: u8) -> Option<Song> {
+    if index >= self.length { return None }
+    return self.songs[index as usize];
+  }
+
+  // replaces a song or appends it, returns false if it would leave a gap
+  pub fn set(&mut self, song: Song) -> bool {
+    if song.index > self.length || song.index as usize >= SETLIST_LENGTH {
+      return false;
+    }
+    self.songs[song.index as usize] = Some(song);
+    self.length = self.songs.iter().take_while(|s| s.is_some()).count() as u8;
+    return true;
+  }
+}
@@ -0,0 +1,366 @@
+/*
+ * Line based commands on the serial port for configuring the clock from a computer
+ *
+ *   bpm 128              sets the tempo
+ *   div 1 4              division of clock 1 (midi out1+2) or 2 (midi out3+4)
+ *   bar 4                quarters per bar
+ *   mult 4               ticks of the trigger outputs per quarter note
+ *   source midi          clock source, internal or midi
+ *   quantize on          starts on the next bar of an external clock, on or off
+ *   advance on           goes to the next song after its bars, on or off
+ *   offset 2 -3 1        latency compensation of output 1-4 in ms and ticks
+ *   ramp 140 8 linear    target bpm, bars and mode (linear or exp) of the next tempo ramp
+ *   run play             play, pause or stop the clock
+ *   state                prints the settings as commands that restore them
+ *   save                 stores the settings in the eeprom, except the run state
+ *   load                 restores the settings from the eeprom
+ *   preset 3             selects song 3 of the setlist, 0 for free tempo
+ *   song 3 intro 96 4 1 2 16
+ *                        stores song 3 with its name, bpm, bar length, divisions and bars (0 plays until stopped)
+ *   eeprom dump 0 64     prints eeprom bytes as hex, from address and length up to 256
+ *   stats                prints the timing statistics of the clock
+ *   stats reset          clears the timing statistics
+ *   crash                prints the report of the last panic or hard fault
+ *   crash clear          erases the crash report
+ *   reset                reboots the clock
+ *
+ * Everything after a # is a comment.
+ */
+
+use core::fmt::{Write};
+use heapless::{String};
+
+use crate::statemachine::{Statemachine, State, ClockSource, RunState, RampMode};
+use crate::delays::{OutputOffset};
+use crate::diagnostics::{TickStats};
+use crate::crash::{CrashReport};
+use crate::setlist::{Song, SONG_NAME_LENGTH};
+
+pub const LINE_LENGTH: usize = 48;
+
+// bytes per line of an eeprom dump
+const DUMP_ROW: usize = 16;
+
+// longer dumps would block the main loop until the watchdog resets the clock
+const MAX_DUMP_LENGTH: u16 = 256;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Command {
+  Bpm(u16),
+  Division(u8, u8), // clock index from 0 and division
+  BarLength(u8),
+  TriggerMultiplier(u8),
+  Source(ClockSource),
+  QuantizedStart(bool),
+  AutoAdvance(bool),
+  Offset(u8, OutputOffset), // output index from 0
+  Ramp(u16, u8, RampMode), // target bpm and bars
+  Run(RunState), // RUNNING, PAUSED or STOPPED
+  State,
+  Save,
+  Load,
+  Preset(Option<u8>), // song index from 0
+  StoreSong(Song),
+  EepromDump(u16, u16), // address and length
+  Stats,
+  StatsReset,
+  Crash,
+  CrashClear,
+  Reset
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ShellError {
+  UnknownCommand,
+  MissingArgument,
+  InvalidArgument,
+  LineTooLong,
+  MemoryError,
+  RampRunning
+}
+
+impl ShellError {
+  pub fn message(&self) -> &'static str {
+    return match self {
+      ShellError::UnknownCommand => "unknown command",
+      ShellError::MissingArgument => "missing argument",
+      ShellError::InvalidArgument => "invalid argument",
+      ShellError::LineTooLong => "line too long",
+      ShellError::MemoryError => "eeprom error",
+      ShellError::RampRunning => "tempo ramp running"
+    };
+  }
+}
+
+/* Hardware the commands need besides the statemachine */
+pub trait Device {
+  const CYCLES_PER_US: u32;
+
+  fn save(&mut self, state: &State) -> bool;
+  fn load(&mut self) -> Option<State>;
+  fn save_song(&mut self, song: &Song) -> bool;
+  fn read_eeprom(&mut self, address: u16, buffer: &mut [u8]) -> bool;
+  // deviations of the clock ticks in cpu cycles and the dropped midi bytes
+  fn stats(&mut self) -> (TickStats, u32);
+  fn reset_stats(&mut self);
+  fn crash_report(&mut self) -> Option<CrashReport>;
+  fn clear_crash_report(&mut self) -> bool;
+  fn reset(&mut self);
+}
+
+pub fn parse(line: &str) -> Result<Command, ShellError> {
+  let mut words = line.split('#').next().unwrap_or("").split_whitespace();
+  let name = match words.next() {
+    Some(name) => name,
+    None => return Err(ShellError::UnknownCommand)
+  };
+
+  let command = match name {
+    "bpm" => Command::Bpm(number(words.next())?),
+    "div" => {
+      let clock = number::<u8>(words.next())?;
+      if clock == 0 { return Err(ShellError::InvalidArgument) }
+      Command::Division(clock - 1, number(words.next())?)
+    },
+    "bar" => Command::BarLength(number(words.next())?),
+    "mult" => Command::TriggerMultiplier(number(words.next())?),
+    "source" => Command::Source(match words.next() {
+      Some("internal") => ClockSource::Internal,
+      Some("midi") => ClockSource::MidiIn,
+      Some(_) => return Err(ShellError::InvalidArgument),
+      None => return Err(ShellError::MissingArgument)
+    }),
+    "quantize" => Command::QuantizedStart(switch(words.next())?),
+    "advance" => Command::AutoAdvance(switch(words.next())?),
+    "offset" => {
+      let output = number::<u8>(words.next())?;
+      if output == 0 { return Err(ShellError::InvalidArgument) }
+      Command::Offset(output - 1, OutputOffset { millis: number(words.next())?, ticks: number(words.next())? })
+    },
+    "ramp" => {
+      let (target, bars) = (number(words.next())?, number(words.next())?);
+      Command::Ramp(target, bars, match words.next() {
+        Some("linear") => RampMode::Linear,
+        Some("exp") => RampMode::Exponential,
+        Some(_) => return Err(ShellError::InvalidArgument),
+        None => return Err(ShellError::MissingArgument)
+      })
+    },
+    "run" => Command::Run(match words.next() {
+      Some("play") => RunState::RUNNING,
+      Some("pause") => RunState::PAUSED,
+      Some("stop") => RunState::STOPPED,
+      Some(_) => return Err(ShellError::InvalidArgument),
+      None => return Err(ShellError::MissingArgument)
+    }),
+    "state" => Command::State,
+    "save" => Command::Save,
+    "load" => Command::Load,
+    "preset" => Command::Preset(number::<u8>(words.next())?.checked_sub(1)),
+    "song" => {
+      let index = number::<u8>(words.next())?.checked_sub(1).ok_or(ShellError::InvalidArgument)?;
+      let word = words.next().ok_or(ShellError::MissingArgument)?;
+      if word.len() > SONG_NAME_LENGTH { return Err(ShellError::InvalidArgument) }
+      let mut name = [b' '; SONG_NAME_LENGTH];
+      name[..word.len()].copy_from_slice(word.as_bytes());
+      Command::StoreSong(Song {
+        index: index,
+        name: name,
+        bpm: number(words.next())?,
+        bar_length: number(words.next())?,
+        divisions: [number(words.next())?, number(words.next())?],
+        bars: number(words.next())?
+      })
+    },
+    "eeprom" => {
+      if words.next() != Some("dump") { return Err(ShellError::UnknownCommand) }
+      let (address, length) = (number(words.next())?, number(words.next())?);
+      if length > MAX_DUMP_LENGTH { return Err(ShellError::InvalidArgument) }
+      Command::EepromDump(address, length)
+    },
+    "stats" => match words.next() {
+      None => Command::Stats,
+      Some("reset") => Command::StatsReset,
+      Some(_) => return Err(ShellError::InvalidArgument)
+    },
+    "crash" => match words.next() {
+      None => Command::Crash,
+      Some("clear") => Command::CrashClear,
+      Some(_) => return Err(ShellError::InvalidArgument)
+    },
+    "reset" => Command::Reset,
+    _ => return Err(ShellError::UnknownCommand)
+  };
+
+  if words.next().is_some() {
+    return Err(ShellError::InvalidArgument);
+  }
+  return Ok(command);
+}
+
+fn number<T: core::str::FromStr>(word: Option<&str>) -> Result<T, ShellError> {
+  let word = word.ok_or(ShellError::MissingArgument)?;
+  return word.parse::<T>().map_err(|_| ShellError::InvalidArgument);
+}
+
+fn switch(word: Option<&str>) -> Result<bool, ShellError> {
+  return match word {
+    Some("on") => Ok(true),
+    Some("off") => Ok(false),
+    Some(_) => Err(ShellError::InvalidArgument),
+    None => Err(ShellError::MissingArgument)
+  };
+}
+
+// runs a command and writes its output, the statemachine reports the changes as usual
+pub fn execute<D: Device>(command: Command, statemachine: &mut Statemachine, device: &mut D, out: &mut impl Write)
+  -> Result<(), ShellError> {
+  match command {
+    Command::Bpm(bpm) => {
+      if !statemachine.set_bpm(bpm) { return Err(ShellError::RampRunning) }
+    },
+    Command::Division(clock, division) => {
+      if !statemachine.set_division(clock, division) { return Err(ShellError::InvalidArgument) }
+    },
+    Command::BarLength(bar_length) => {
+      if !statemachine.set_bar_length(bar_length) { return Err(ShellError::InvalidArgument) }
+    },
+    Command::TriggerMultiplier(multiplier) => {
+      if !statemachine.set_trigger_multiplier(multiplier) { return Err(ShellError::InvalidArgument) }
+    },
+    Command::Source(source) => statemachine.set_source(source),
+    Command::QuantizedStart(quantized) => statemachine.set_quantized_start(quantized),
+    Command::AutoAdvance(auto_advance) => statemachine.set_auto_advance(auto_advance),
+    Command::Offset(output, offset) => {
+      if !statemachine.set_output_offset(output, offset) { return Err(ShellError::InvalidArgument) }
+    },
+    Command::Ramp(target, bars, mode) => {
+      if statemachine.get_state().ramping { return Err(ShellError::RampRunning) }
+      if !statemachine.set_ramp(target, bars, mode) { return Err(ShellError::InvalidArgument) }
+    },
+    Command::Run(running) => {
+      if !statemachine.set_running(running) { return Err(ShellError::InvalidArgument) }
+    },
+    Command::State => {
+      for i in 0..statemachine.setlist().len() {
+        statemachine.setlist().get(i).map(|song| write_song(&song, out));
+      }
+      write_state(&statemachine.get_state(), out);
+    },
+    Command::Save => {
+      if !device.save(&statemachine.get_state()) { return Err(ShellError::MemoryError) }
+    },
+    Command::Load => {
+      let state = device.load().ok_or(ShellError::MemoryError)?;
+      statemachine.load_state(state);
+    },
+    Command::Preset(index) => {
+      if !statemachine.select_preset(index) { return Err(ShellError::InvalidArgument) }
+    },
+    Command::StoreSong(song) => {
+      if !statemachine.store_song(song) { return Err(ShellError::InvalidArgument) }
+      if !device.save_song(&song) { return Err(ShellError::MemoryError) }
+    },
+    Command::EepromDump(address, length) => {
+      let mut buffer = [0u8; DUMP_ROW];
+      let end = address as u32 + length as u32;
+      let mut row = address as u32;
+      while row < end {
+        let bytes = &mut buffer[..(end - row).min(DUMP_ROW as u32) as usize];
+        if !device.read_eeprom(row as u16, bytes) { return Err(ShellError::MemoryError) }
+        write!(out, "{:04x}:", row).ok();
+        for byte in bytes.iter() {
+          write!(out, " {:02x}", byte).ok();
+        }
+        out.write_str("\r\n").ok();
+        row += DUMP_ROW as u32;
+      }
+    },
+    Command::Stats => {
+      let (stats, dropped) = device.stats();
+      let us = |cycles: i32| cycles / D::CYCLES_PER_US as i32;
+      write!(out, "ticks {}\r\nmin {}us\r\nmax {}us\r\nmean {}us\r\nstd dev {}us\r\njitter {}us\r\ndropped {}\r\n",
+        stats.count, us(stats.min), us(stats.max), us(stats.mean()),
+        stats.std_dev() / D::CYCLES_PER_US, stats.jitter() / D::CYCLES_PER_US, dropped).ok();
+    },
+    Command::StatsReset => device.reset_stats(),
+    Command::Crash => {
+      match device.crash_report() {
+        Some(report) => write!(out, "{}\r\n", report).ok(),
+        None => out.write_str("no crash report\r\n").ok()
+      };
+    },
+    Command::CrashClear => {
+      if !device.clear_crash_report() { return Err(ShellError::MemoryError) }
+    },
+    Command::Reset => device.reset()
+  }
+  return Ok(());
+}
+
+fn write_song(song: &Song, out: &mut impl Write) {
+  write!(out, "song {} {} {} {} {} {} {}\r\n", song.index + 1, song.name().trim_end(), song.bpm, song.bar_length,
+    song.divisions[0], song.divisions[1], song.bars).ok();
+}
+
+// prints the settings as commands, so the output can be sent to another clock
+fn write_state(state: &State, out: &mut impl Write) {
+  // the song comes first, it overwrites the tempo settings
+  write!(out, "preset {}\r\n", state.song.map_or(0, |song| song.index + 1)).ok();
+  write!(out, "bpm {}\r\n", state.bpm).ok();
+  for (i, division) in state.clock_divisions.iter().enumerate() {
+    write!(out, "div {} {}\r\n", i + 1, division).ok();
+  }
+  write!(out, "bar {}\r\nmult {}\r\n", state.clock_bar_length, state.clock_trigger_multiplier).ok();
+
+  let source = if state.clock_source == ClockSource::Internal { "internal" } else { "midi" };
+  let switch = |on: bool| if on { "on" } else { "off" };
+  write!(out, "source {}\r\nquantize {}\r\nadvance {}\r\n", source, switch(state.quantized_start), switch(state.auto_advance)).ok();
+  for (i, offset) in state.output_offsets.iter().enumerate() {
+    write!(out, "offset {} {} {}\r\n", i + 1, offset.millis, offset.ticks).ok();
+  }
+  let mode = if state.ramp_mode == RampMode::Linear { "linear" } else { "exp" };
+  write!(out, "ramp {} {} {}\r\n", state.ramp_target, state.ramp_bars, mode).ok();
+
+  // the run state comes last, an armed clock needs the source and the quantized start
+  let running = match state.running {
+    RunState::RUNNING | RunState::ARMED => "play",
+    RunState::PAUSED => "pause",
+    _ => "stop"
+  };
+  write!(out, "run {}\r\n", running).ok();
+}
+
+/* Collects the received bytes until the end of a line */
+pub struct LineBuffer {
+  line: String<LINE_LENGTH>,
+  overflow: bool
+}
+
+impl LineBuffer {
+  pub const fn new() -> LineBuffer {
+    return LineBuffer { line: String::new(), overflow: false };
+  }
+
+  // returns the line when the byte ends it, lines that are too long are dropped
+  pub fn push(&mut self, byte: u8) -> Option<Result<String<LINE_LENGTH>, ShellError>> {
+    match byte {
+      b'\r' | b'\n' => {
+        let line = core::mem::replace(&mut self.line, String::new());
+        let overflow = core::mem::replace(&mut self.overflow, false);
+        if overflow { return Some(Err(ShellError::LineTooLong)) }
+        if line.split('#').next().unwrap_or("").trim().is_empty() { return None }
+        return Some(Ok(line));
+      },
+      // backspace and delete of terminals
+      0x08 | 0x7F => { self.line.pop(); },
+      0x20..=0x7E => {
+        if self.line.push(byte as char).is_err() {
+          self.overflow = true;
+        }
+      },
+      _ => {}
+    }
+    return None;
+  }
+}
@@ -0,0 +1,347 @@
+/*
+ * Driver of st7066 / hd44780 character lcds in 4bit mode on any embedded-hal pins
+ *
+ * Without the r/w pin every command waits for its execution time. With r/w the driver polls the
+ * busy flag, which needs data pins that can be read back.
+ */
+
+
+use embedded_hal::digital::v2::{OutputPin, InputPin};
+use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+
+// execution times of the lcd commands when the busy flag can not be read
+const COMMAND_DELAY_US: u16 = 40;
+const CLEAR_DELAY_US: u16 = 2000;
+
+// ddram address of the first character in the second row
+const SECOND_ROW_ADDRESS: u8 = 0x40;
+
+/* Size of the display in characters */
+#[derive(Copy, Clone, PartialEq)]
+pub struct Geometry {
+  pub columns: u8,
+  pub rows: u8
+}
+
+pub const GEOMETRY_8X2: Geometry = Geometry { columns: 8, rows: 2 };
+pub const GEOMETRY_16X2: Geometry = Geometry { columns: 16, rows: 2 };
+pub const GEOMETRY_20X4: Geometry = Geometry { columns: 20, rows: 4 };
+pub const GEOMETRY_40X2: Geometry = Geometry { columns: 40, rows: 2 };
+
+impl Geometry {
+  // 4 row displays continue row 0 and 1 in row 2 and 3
+  fn row_address(&self, row: u8) -> u8 {
+    let address = if row % 2 == 0 { 0 } else { SECOND_ROW_ADDRESS };
+    if row >= 2 {
+      return address + self.columns;
+    }
+    return address;
+  }
+}
+
+// stop polling the busy flag after this many reads, in case the lcd does not answer
+const MAX_BUSY_READS: u16 = 100;
+
+/* Placeholder for the r/w pin if it is tied to ground */
+pub struct NoPin;
+
+/* Waits until the lcd is ready for the next command */
+pub trait ReadWrite<D4, D5, D6, D7> {
+  // returns false if the busy flag can not be read or the lcd stayed busy
+  fn wait_ready<EN: OutputPin, DELAY: DelayUs<u16>>(&mut self, en: &mut EN, data: (&mut D4, &mut D5, &mut D6, &mut D7), delay: &mut DELAY) -> bool;
+}
+
+impl<D4, D5, D6, D7> ReadWrite<D4, D5, D6, D7> for NoPin {
+  fn wait_ready<EN: OutputPin, DELAY: DelayUs<u16>>(&mut self, _: &mut EN, _: (&mut D4, &mut D5, &mut D6, &mut D7), _: &mut DELAY) -> bool {
+    return false;
+  }
+}
+
+// reading the busy flag needs open drain data pins, so the lcd can drive them
+impl<
+  RW: OutputPin,
+  D4: OutputPin + InputPin,
+  D5: OutputPin + InputPin,
+  D6: OutputPin + InputPin,
+  D7: OutputPin + InputPin
+> ReadWrite<D4, D5, D6, D7> for RW {
+  fn wait_ready<EN: OutputPin, DELAY: DelayUs<u16>>(&mut self, en: &mut EN, data: (&mut D4, &mut D5, &mut D6, &mut D7), delay: &mut DELAY) -> bool {
+    // release data pins
+    data.0.set_high().ok();
+    data.1.set_high().ok();
+    data.2.set_high().ok();
+    data.3.set_high().ok();
+
+    self.set_high().ok();
+    let mut ready = false;
+    for _ in 0..MAX_BUSY_READS {
+      // busy flag is d7 of the higher nibble, the lower nibble has to be clocked out too
+      en.set_high().ok();
+      delay.delay_us(1u16);
+      let busy = data.3.is_high().unwrap_or(false);
+      en.set_low().ok();
+      delay.delay_us(1u16);
+      en.set_high().ok();
+      delay.delay_us(1u16);
+      en.set_low().ok();
+      delay.delay_us(1u16);
+
+      if !busy {
+        ready = true;
+        break
+      }
+    }
+    self.set_low().ok();
+    return ready;
+  }
+}
+
+struct ST7066Bus<
+  RS: OutputPin,
+  EN: OutputPin,
+  D4: OutputPin,
+  D5: OutputPin,
+  D6: OutputPin,
+  D7: OutputPin
+> {
+  rs: RS,
+  en: EN,
+  d4: D4,
+  d5: D5,
+  d6: D6,
+  d7: D7,
+}
+
+pub struct ST7066<
+  RS: OutputPin,
+  EN: OutputPin,
+  D4: OutputPin,
+  D5: OutputPin,
+  D6: OutputPin,
+  D7: OutputPin,
+  DELAY: DelayUs<u16> + DelayMs<u16>,
+  RW: ReadWrite<D4, D5, D6, D7> = NoPin
+> {
+  bus: ST7066Bus<RS,EN,D4,D5,D6,D7>,
+  rw: RW,
+  delay: DELAY,
+  geometry: Geometry,
+  data_mode: bool,
+  cursor: (u8, u8)
+}
+
+impl<
+  RS: OutputPin,
+  EN: OutputPin,
+  D4: OutputPin,
+  D5: OutputPin,
+  D6: OutputPin,
+  D7: OutputPin,
+  DELAY: DelayUs<u16> + DelayMs<u16>
+> ST7066<RS,EN,D4,D5,D6,D7,DELAY,NoPin> {
+
+  // r/w is tied to ground, commands wait for their execution time
+  pub fn new(rs: RS, en: EN, d4: D4, d5: D5, d6: D6, d7: D7, delay: DELAY, geometry: Geometry) -> ST7066<RS,EN,D4,D5,D6,D7,DELAY,NoPin> {
+    return ST7066::new_with_rw(rs, NoPin, en, d4, d5, d6, d7, delay, geometry);
+  }
+}
+
+impl<
+  RS: OutputPin,
+  EN: OutputPin,
+  D4: OutputPin,
+  D5: OutputPin,
+  D6: OutputPin,
+  D7: OutputPin,
+  DELAY: DelayUs<u16> + DelayMs<u16>,
+  RW: ReadWrite<D4, D5, D6, D7>
+> ST7066<RS,EN,D4,D5,D6,D7,DELAY,RW> {
+
+  // commands poll the busy flag of the lcd
+  pub fn new_with_rw(rs: RS, rw: RW, en: EN, d4: D4, d5: D5, d6: D6, d7: D7, delay: DELAY, geometry: Geometry) -> ST7066<RS,EN,D4,D5,D6,D7,DELAY,RW> {
+
+    let mut bus = ST7066Bus {
+      rs: rs,
+      en: en,
+      d4: d4,
+      d5: d5,
+      d6: d6,
+      d7: d7
+    };
+
+    // initialize control pins
+    bus.rs.set_low().ok();
+    bus.en.set_low().ok();
+
+    return ST7066 {
+      bus: bus,
+      rw: rw,
+      delay: delay,
+      geometry: geometry,
+      data_mode: false,
+      cursor: (0, 0)
+    }
+  }
+
+  pub fn geometry(&self) -> Geometry {
+    return self.geometry;
+  }
+
+  // sends initializing commands for 4bit operation
+  pub fn init(&mut self) {
+    self.delay.delay_ms(200u16);
+
+    // init display, the busy flag can not be read yet
+    self.write_4bits(0x3);
+    self.delay.delay_ms(5u16);
+
+    self.write_4bits(0x3);
+    self.delay.delay_ms(5u16);
+
+    self.write_4bits(0x3);
+    self.delay.delay_us(COMMAND_DELAY_US);
+    self.write_4bits(0x2);
+
+    self.delay.delay_us(100u16);
+
+    //function set 0b001D_NFxx and font size D = 0 (4bit), N = 1 (2rows), F = 0 (font1), 4 row displays are driven as 2 rows
+    let lines = if self.geometry.rows > 1 { 0b0000_1000 } else { 0 };
+    self.write_command(0b0010_0000 | lines, false);
+
+    // display on, 0b0000_1DCB D=1 (on), C=1 (cursor), B=1 (blink)
+    self.write_command(0b0000_1100, false);
+
+    //entry mode, 0b0000_01IS, I=1 (increment), S = 1 (shift)
+    self.write_command(0b0000_0110, false);
+
+    self.clear();
+
+    self.write_str("starting");
+  }
+
+  pub fn clear(&mut self) {
+    self.write_command(0x01, false);
+    self.wait_ready(CLEAR_DELAY_US);
+
+    self.cursor = (0, 0);
+  }
+
+  pub fn return_home(&mut self) {
+    self.write_command(0x02, false);
+    self.wait_ready(CLEAR_DELAY_US);
+
+    self.cursor = (0, 0);
+  }
+
+  // wraps to the next row at the end of a row
+  pub fn write_char(&mut self, c: u8) {
+    self.write_command(c, true);
+
+    let (column, row) = self.cursor;
+    if column + 1 < self.geometry.columns {
+      self.cursor = (column + 1, row);
+    } else {
+      self.set_cursor((0, (row + 1) % self.geometry.rows));
+    }
+  }
+
+  pub fn write_str(&mut self, text: &str) {
+    text.bytes().map(|c| self.write_char(c as u8)).last();
+  }
+
+  // stores a 5x8 character in the cgram, it gets written with the character code index (0-7)
+  pub fn create_char(&mut self, index: u8, glyph: &[u8; 8]) {
+    self.write_command(0b0100_0000 | (index & 0x07) << 3, false);
+    for row in glyph.iter() {
+      self.write_command(row & 0x1F, true);
+    }
+
+    // switch back to the ddram
+    self.set_cursor((0,0));
+  }
+
+  // positions outside of the display get clamped
+  pub fn set_cursor(&mut self, position: (u8,u8)) {
+    let column = position.0.min(self.geometry.columns - 1);
+    let row = position.1.min(self.geometry.rows - 1);
+
+    let cmd = 0b1000_0000 | (self.geometry.row_address(row) + column);
+    self.write_command(cmd, false);
+
+    self.cursor = (column, row);
+  }
+
+  fn set_data_write_mode(&mut self, enable: bool) {
+    if enable == self.data_mode { return }
+
+    if enable {
+      self.bus.rs.set_high().ok();
+    } else {
+      self.bus.rs.set_low().ok();
+    }
+    self.data_mode = enable;
+  }
+
+  fn write_command(&mut self, cmd: u8, is_data: bool) {
+
+    self.set_data_write_mode(is_data);
+
+    // send higher nibble
+    self.write_4bits((cmd & 0xF0) >> 4);
+
+    //send lower nibble
+    self.write_4bits(cmd & 0x0F);
+
+    self.wait_ready(COMMAND_DELAY_US);
+  }
+
+  // polls the busy flag or waits for the execution time of the command,
+  // also when the busy flag did not clear within the reads
+  fn wait_ready(&mut self, execution_time_us: u16) {
+    // busy flag is read with rs low
+    let data_mode = self.data_mode;
+    self.set_data_write_mode(false);
+
+    let bus = &mut self.bus;
+    let polled = self.rw.wait_ready(&mut bus.en, (&mut bus.d4, &mut bus.d5, &mut bus.d6, &mut bus.d7), &mut self.delay);
+    if !polled {
+      self.delay.delay_us(execution_time_us);
+    }
+
+    self.set_data_write_mode(data_mode);
+  }
+
+  fn write_4bits(&mut self, cmd: u8) {
+    if (cmd & 0b0001) > 0 {
+      self.bus.d4.set_high().ok();
+    } else {
+      self.bus.d4.set_low().ok();
+    }
+
+    if (cmd & 0b0010) > 0 {
+      self.bus.d5.set_high().ok();
+    } else {
+      self.bus.d5.set_low().ok();
+    }
+
+    if (cmd & 0b0100) > 0 {
+      self.bus.d6.set_high().ok();
+    } else {
+      self.bus.d6.set_low().ok();
+    }
+
+    if (cmd & 0b1000) > 0 {
+      self.bus.d7.set_high().ok();
+    } else {
+      self.bus.d7.set_low().ok();
+    }
+
+    // pulse en
+    self.bus.en.set_low().ok();
+    self.delay.delay_us(1u16);
+    self.bus.en.set_high().ok();
+    self.delay.delay_us(1u16);
+    self.bus.en.set_low().ok();
+  }
+
+}
@@ -0,0 +1,641 @@
+use crate::delays::{OutputOffset, OUTPUT_COUNT, ZERO_OFFSET, OFFSET_MILLIS_RANGE, OFFSET_TICKS_RANGE};
+use crate::setlist::{Setlist, Song};
+use crate::events::{Event};
+use crate::gestures::{ButtonEvent, BUTTON1_MASK, BUTTON2_MASK, BUTTON3_MASK, BUTTON4_MASK};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RunState {
+  STOPPED,
+  STOPPING,
+  RUNNING,
+  PAUSED,
+  ARMED // waits for the next bar of the external clock
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ClockSource {
+  Internal,
+  MidiIn
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RampMode {
+  Linear,
+  Exponential
+}
+
+// parameter that is edited with the encoder
+#[derive(Copy, Clone, PartialEq)]
+pub enum MenuPage {
+  Bpm,
+  Song,
+  Division(u8), // clock index
+  BarLength,
+  AutoAdvance,
+  Ramp,
+  RampTarget,
+  RampBars,
+  RampMode,
+  ClockSource,
+  QuantizedStart,
+  OffsetMillis(u8), // output index
+  OffsetTicks(u8),
+  Diagnostics // timing statistics of the clock output
+}
+
+impl MenuPage {
+  pub fn next(&self) -> MenuPage {
+    match *self {
+      MenuPage::Bpm => MenuPage::Song,
+      MenuPage::Song => MenuPage::Division(0),
+      MenuPage::Division(0) => MenuPage::Division(1),
+      MenuPage::Division(_) => MenuPage::BarLength,
+      MenuPage::BarLength => MenuPage::AutoAdvance,
+      MenuPage::AutoAdvance => MenuPage::Ramp,
+      MenuPage::Ramp => MenuPage::RampTarget,
+      MenuPage::RampTarget => MenuPage::RampBars,
+      MenuPage::RampBars => MenuPage::RampMode,
+      MenuPage::RampMode => MenuPage::ClockSource,
+      MenuPage::ClockSource => MenuPage::QuantizedStart,
+      MenuPage::QuantizedStart => MenuPage::OffsetMillis(0),
+      MenuPage::OffsetMillis(i) => MenuPage::OffsetTicks(i),
+      MenuPage::OffsetTicks(i) => {
+        if (i as usize) + 1 < OUTPUT_COUNT { MenuPage::OffsetMillis(i + 1) } else { MenuPage::Diagnostics }
+      },
+      MenuPage::Diagnostics => MenuPage::Bpm
+    }
+  }
+
+  // change of the parameter per encoder detent
+  fn step_size(&self) -> StepSize {
+    match *self {
+      MenuPage::Bpm | MenuPage::RampTarget => StepSize { fine: 1, coarse: 10, accelerated: true },
+      MenuPage::RampBars => StepSize { fine: 1, coarse: 4, accelerated: true },
+      MenuPage::OffsetMillis(_) => StepSize { fine: 1, coarse: 10, accelerated: true },
+      MenuPage::OffsetTicks(_) => StepSize { fine: 1, coarse: 6, accelerated: false },
+      // songs, divisions and options are stepped through one by one
+      _ => StepSize { fine: 1, coarse: 1, accelerated: false }
+    }
+  }
+}
+
+// coarse steps are used while the encoder button is held
+struct StepSize {
+  fine: i16,
+  coarse: i16,
+  accelerated: bool
+}
+
+#[derive(Copy, Clone)]
+pub struct State {
+  pub bpm: u16,
+  pub clock_trigger_multiplier: u8, // multiply clock for both trigger outs
+  pub clock_divisions: [u8; 2], // divisions for clock 0: midi out1+2, 1: midi out 2+3, 2: trigger1, 3: trigger2
+  pub clock_bar_length: u8, // how many quarters per bar for resync
+  pub clock_sync: bool,
+  pub clock_source: ClockSource,
+  pub quantized_start: bool, // start on the next bar of an external clock
+  pub ramp_target: u16, // bpm at the end of a tempo ramp
+  pub ramp_bars: u8, // length of a tempo ramp
+  pub ramp_mode: RampMode,
+  pub ramping: bool,
+  pub song: Option<Song>, // selected song of the setlist
+  pub auto_advance: bool, // stop after the bars of a song and go to the next one
+  pub running: RunState, // run state of the clock
+  pub output_offsets: [OutputOffset; OUTPUT_COUNT], // latency compensation of each output
+  pub menu: MenuPage
+}
+
+pub struct Statemachine {
+  state: State,
+  setlist: Setlist,
+  changed: bool,
+  song_advance: bool,
+  encoder_held: bool,
+  encoder_used: bool, // encoder was turned or long pressed while held
+  song_buttons: u8 // play or stop pressed while the encoder was held, their release is ignored
+}
+
+pub const DEFAULT_STATE: State = State {
+  bpm: 120,
+  clock_trigger_multiplier: 4,
+  clock_divisions: [1,1],
+  clock_bar_length: 4,
+  clock_sync: false,
+  clock_source: ClockSource::Internal,
+  quantized_start: false,
+  ramp_target: 120,
+  ramp_bars: 4,
+  ramp_mode: RampMode::Linear,
+  ramping: false,
+  song: None,
+  auto_advance: false,
+  running: RunState::RUNNING,
+  output_offsets: [ZERO_OFFSET; OUTPUT_COUNT],
+  menu: MenuPage::Bpm
+};
+
+// define state constants
+const BPM_RANGE: (u16,u16) = (30, 320);
+const DIVISION_STEPS: [u8;10] = [1,2,3,4,5,6,7,8,16,32]; // largest common multiple is 33600
+const MULTIPLIERS: [u8;8] = [1,2,3,4,6,8,12,24];
+const BAR_LENGTHS_RANGE: (u8,u8) = (1,15);
+const RAMP_BARS_RANGE: (u8,u8) = (1,32);
+
+// midi control change that starts (value >= 64) or stops a tempo ramp
+const RAMP_CONTROLLER: u8 = 20;
+
+// bytes of the stored settings, the version follows the bpm that older firmware stored alone
+pub const STATE_SIZE: usize = 23;
+const STATE_VERSION: u8 = 1;
+
+impl State {
+  // the run state, the ramp, the sync and the menu page start fresh after a reboot
+  pub fn to_bytes(&self) -> [u8; STATE_SIZE] {
+    let mut bytes = [0; STATE_SIZE];
+    bytes[0] = (self.bpm >> 8) as u8;
+    bytes[1] = (self.bpm & 0xFF) as u8;
+    bytes[2] = STATE_VERSION;
+    bytes[3] = self.clock_trigger_multiplier;
+    bytes[4] = self.clock_divisions[0];
+    bytes[5] = self.clock_divisions[1];
+    bytes[6] = self.clock_bar_length;
+    bytes[7] = match self.clock_source {
+      ClockSource::Internal => 0,
+      ClockSource::MidiIn => 1
+    };
+    bytes[8] = self.quantized_start as u8;
+    bytes[9] = (self.ramp_target >> 8) as u8;
+    bytes[10] = (self.ramp_target & 0xFF) as u8;
+    bytes[11] = self.ramp_bars;
+    bytes[12] = (self.ramp_mode == RampMode::Exponential) as u8;
+    bytes[13] = self.song.map_or(0, |song| song.index + 1);
+    bytes[14] = self.auto_advance as u8;
+    for (i, offset) in self.output_offsets.iter().enumerate() {
+      bytes[15 + i * 2] = offset.millis as u8;
+      bytes[16 + i * 2] = offset.ticks as u8;
+    }
+    return bytes;
+  }
+
+  // settings out of range fall back to their defaults, the song is looked up in the setlist
+  pub fn from_bytes(bytes: &[u8], setlist: &Setlist) -> State {
+    fn in_range<T: PartialOrd>(value: T, range: (T,T)) -> bool {
+      return value >= range.0 && value <= range.1;
+    }
+
+    let mut state = DEFAULT_STATE;
+    let bpm = (bytes[0] as u16) << 8 | bytes[1] as u16;
+    if in_range(bpm, BPM_RANGE) {
+      state.bpm = bpm;
+    }
+    // stored by a firmware that only kept the bpm
+    if bytes[2] != STATE_VERSION {
+      return state;
+    }
+
+    if MULTIPLIERS.contains(&bytes[3]) {
+      state.clock_trigger_multiplier = bytes[3];
+    }
+    for i in 0..2 {
+      if DIVISION_STEPS.contains(&bytes[4 + i]) {
+        state.clock_divisions[i] = bytes[4 + i];
+      }
+    }
+    if in_range(bytes[6], BAR_LENGTHS_RANGE) {
+      state.clock_bar_length = bytes[6];
+    }
+    state.clock_source = match bytes[7] {
+      1 => ClockSource::MidiIn,
+      _ => ClockSource::Internal
+    };
+    state.quantized_start = bytes[8] == 1;
+    let ramp_target = (bytes[9] as u16) << 8 | bytes[10] as u16;
+    if in_range(ramp_target, BPM_RANGE) {
+      state.ramp_target = ramp_target;
+    }
+    if in_range(bytes[11], RAMP_BARS_RANGE) {
+      state.ramp_bars = bytes[11];
+    }
+    state.ramp_mode = if bytes[12] == 1 { RampMode::Exponential } else { RampMode::Linear };
+    state.song = bytes[13].checked_sub(1).and_then(|i| setlist.get(i));
+    state.auto_advance = bytes[14] == 1;
+    for i in 0..OUTPUT_COUNT {
+      let offset = OutputOffset { millis: bytes[15 + i * 2] as i8, ticks: bytes[16 + i * 2] as i8 };
+      if in_range(offset.millis, OFFSET_MILLIS_RANGE) && in_range(offset.ticks, OFFSET_TICKS_RANGE) {
+        state.output_offsets[i] = offset;
+      }
+    }
+    return state;
+  }
+}
+
+impl Statemachine {
+  pub fn new(state: Option<State>, setlist: Setlist) -> Statemachine {
+    // set initial state
+    return Statemachine { 
+      state : state.unwrap_or(DEFAULT_STATE),
+      setlist: setlist,
+      changed: true,
+      song_advance: false,
+      encoder_held: false,
+      encoder_used: false,
+      song_buttons: 0
+    }
+  }
+
+  pub fn on_change(&mut self) -> Option<State> {
+    // the stop at the end of a song was sent, reset the clock and go to the next song
+    if !self.changed && self.song_advance {
+      self.song_advance = false;
+      self.state.running = RunState::STOPPED;
+      let next = self.state.song.map(|song| song.index + 1).filter(|i| *i < self.setlist.len());
+      if next.is_some() {
+        self.select_song(next);
+      }
+      self.changed = true;
+    }
+
+    if self.changed {
+      let state = self.state.clone();
+
+      self.changed = false;
+
+      return Some(state);
+    } else {
+      return None;
+    }
+  }
+
+  pub fn get_state(&self) -> State {
+    return self.state;
+  }
+
+  pub fn encoder_turn(&mut self, detents: i16, acceleration: u8) {
+    fn add_offset(offset: i8, steps: i16, range: (i8,i8)) -> i8 {
+      return (offset as i16 + steps).min(range.1 as i16).max(range.0 as i16) as i8;
+    }
+    fn add_bpm(bpm: u16, steps: i16) -> u16 {
+      return (bpm as i16 + steps).min(BPM_RANGE.1 as i16).max(BPM_RANGE.0 as i16) as u16;
+    }
+
+    let size = self.state.menu.step_size();
+    let steps = if self.encoder_held {
+      self.encoder_used = true;
+      detents.saturating_mul(size.coarse)
+    } else if size.accelerated {
+      detents.saturating_mul(size.fine * acceleration as i16)
+    } else {
+      detents.saturating_mul(size.fine)
+    };
+
+    match self.state.menu {
+      MenuPage::Bpm => {
+        // bpm is controlled by the ramp while it is running
+        if self.state.ramping { return }
+        self.state.bpm = add_bpm(self.state.bpm, steps);
+      },
+      MenuPage::Song => {
+        // tempo of the song would be overwritten by the ramp
+        if self.state.ramping { return }
+        self.select_song(self.song_index(steps));
+      },
+      MenuPage::Division(i) => {
+        let division = &mut self.state.clock_divisions[i as usize];
+        let index = DIVISION_STEPS.iter().position(|d| d == division).unwrap_or(0) as i16 + steps;
+        *division = DIVISION_STEPS[index.min(DIVISION_STEPS.len() as i16 - 1).max(0) as usize];
+      },
+      MenuPage::BarLength => {
+        let bar_length = (self.state.clock_bar_length as i16 + steps).min(BAR_LENGTHS_RANGE.1 as i16).max(BAR_LENGTHS_RANGE.0 as i16);
+        self.state.clock_bar_length = bar_length as u8;
+      },
+      MenuPage::AutoAdvance => {
+        self.state.auto_advance = steps > 0;
+      },
+      MenuPage::Ramp => {
+        self.set_ramping(steps > 0);
+      },
+      MenuPage::RampTarget => {
+        self.state.ramp_target = add_bpm(self.state.ramp_target, steps);
+      },
+      MenuPage::RampBars => {
+        let bars = (self.state.ramp_bars as i16 + steps).min(RAMP_BARS_RANGE.1 as i16).max(RAMP_BARS_RANGE.0 as i16);
+        self.state.ramp_bars = bars as u8;
+      },
+      MenuPage::RampMode => {
+        self.state.ramp_mode = if steps > 0 { RampMode::Exponential } else { RampMode::Linear };
+      },
+      MenuPage::ClockSource => {
+        let source = match self.state.clock_source {
+          ClockSource::Internal => ClockSource::MidiIn,
+          ClockSource::MidiIn => ClockSource::Internal
+        };
+        self.set_source(source);
+      },
+      MenuPage::QuantizedStart => {
+        self.state.quantized_start = steps > 0;
+      },
+      MenuPage::OffsetMillis(i) => {
+        let offset = &mut self.state.output_offsets[i as usize];
+        offset.millis = add_offset(offset.millis, steps, OFFSET_MILLIS_RANGE);
+      },
+      MenuPage::OffsetTicks(i) => {
+        let offset = &mut self.state.output_offsets[i as usize];
+        offset.ticks = add_offset(offset.ticks, steps, OFFSET_TICKS_RANGE);
+      },
+      MenuPage::Diagnostics => return
+    }
+    self.changed = true;
+  }
+
+  pub fn button1_pressed(&mut self, pressed : bool) {
+    if pressed {
+      let quantized = self.state.quantized_start && self.state.clock_source != ClockSource::Internal;
+      self.state.running = match self.state.running {
+        RunState::RUNNING | RunState::ARMED => RunState::PAUSED,
+        _ => if quantized { RunState::ARMED } else { RunState::RUNNING }
+      };
+      self.changed = true;
+    }
+  }
+
+  // handles the events of the queue in the order they happened
+  pub fn on_event(&mut self, event: Event) {
+    match event {
+      Event::Button(ButtonEvent::Press(button)) => self.button_pressed(button, true),
+      Event::Button(ButtonEvent::Release(button)) => self.button_pressed(button, false),
+      Event::Button(ButtonEvent::LongPress(BUTTON4_MASK)) => self.encoder_long_pressed(),
+      Event::Button(_) => {},
+      Event::EncoderTurn(detents, acceleration) => self.encoder_turn(detents, acceleration),
+      Event::ControlChange(controller, value) => self.control_change(controller, value),
+      Event::QuantizedStart => self.quantized_start(),
+      Event::RampEnd(bpm) => self.ramp_ended(bpm),
+      Event::SongEnd => self.song_ended(),
+      Event::SyncLost | Event::DiagnosticsRequest(_) => {}
+    }
+  }
+
+  fn button_pressed(&mut self, button: u8, pressed: bool) {
+    // with the encoder held play goes to the next song and stop to the previous one
+    if pressed && self.encoder_held && (button == BUTTON1_MASK || button == BUTTON2_MASK) {
+      self.encoder_used = true;
+      self.song_buttons |= button;
+      if !self.state.ramping {
+        self.select_song(self.song_index(if button == BUTTON1_MASK { 1 } else { -1 }));
+        self.changed = true;
+      }
+      return;
+    }
+    if !pressed && self.song_buttons & button > 0 {
+      self.song_buttons &= !button;
+      return;
+    }
+
+    match button {
+      BUTTON1_MASK => self.button1_pressed(pressed),
+      BUTTON2_MASK => self.button2_pressed(pressed),
+      BUTTON3_MASK => self.button3_pressed(pressed),
+      BUTTON4_MASK => self.encoder_pressed(pressed),
+      _ => {}
+    }
+  }
+
+  // sets the bpm without the encoder, returns false while a ramp is running
+  pub fn set_bpm(&mut self, bpm: u16) -> bool {
+    if self.state.ramping { return false }
+    self.state.bpm = bpm.min(BPM_RANGE.1).max(BPM_RANGE.0);
+    self.changed = true;
+    return true;
+  }
+
+  // returns false if the clock index or division is not available
+  pub fn set_division(&mut self, clock: u8, division: u8) -> bool {
+    if clock as usize >= self.state.clock_divisions.len() || !DIVISION_STEPS.contains(&division) {
+      return false;
+    }
+    self.state.clock_divisions[clock as usize] = division;
+    self.changed = true;
+    return true;
+  }
+
+  pub fn set_bar_length(&mut self, bar_length: u8) -> bool {
+    if bar_length < BAR_LENGTHS_RANGE.0 || bar_length > BAR_LENGTHS_RANGE.1 {
+      return false;
+    }
+    self.state.clock_bar_length = bar_length;
+    self.changed = true;
+    return true;
+  }
+
+  // ticks of the trigger outputs per quarter note
+  pub fn set_trigger_multiplier(&mut self, multiplier: u8) -> bool {
+    if !MULTIPLIERS.contains(&multiplier) {
+      return false;
+    }
+    self.state.clock_trigger_multiplier = multiplier;
+    self.changed = true;
+    return true;
+  }
+
+  pub fn set_quantized_start(&mut self, quantized: bool) {
+    self.state.quantized_start = quantized;
+    self.changed = true;
+  }
+
+  pub fn set_auto_advance(&mut self, auto_advance: bool) {
+    self.state.auto_advance = auto_advance;
+    self.changed = true;
+  }
+
+  // returns false if the output index or one of the offsets is out of range
+  pub fn set_output_offset(&mut self, output: u8, offset: OutputOffset) -> bool {
+    let in_range = |value: i8, range: (i8,i8)| value >= range.0 && value <= range.1;
+    if output as usize >= OUTPUT_COUNT || !in_range(offset.millis, OFFSET_MILLIS_RANGE) || !in_range(offset.ticks, OFFSET_TICKS_RANGE) {
+      return false;
+    }
+    self.state.output_offsets[output as usize] = offset;
+    self.changed = true;
+    return true;
+  }
+
+  // settings of the next tempo ramp, a running ramp keeps its settings
+  pub fn set_ramp(&mut self, target: u16, bars: u8, mode: RampMode) -> bool {
+    if self.state.ramping || target < BPM_RANGE.0 || target > BPM_RANGE.1 || bars < RAMP_BARS_RANGE.0 || bars > RAMP_BARS_RANGE.1 {
+      return false;
+    }
+    self.state.ramp_target = target;
+    self.state.ramp_bars = bars;
+    self.state.ramp_mode = mode;
+    self.changed = true;
+    return true;
+  }
+
+  // play, pause or stop like the buttons, returns false if a pause has nothing to pause
+  pub fn set_running(&mut self, running: RunState) -> bool {
+    let quantized = self.state.quantized_start && self.state.clock_source != ClockSource::Internal;
+    self.state.running = match running {
+      RunState::RUNNING | RunState::ARMED => if quantized { RunState::ARMED } else { RunState::RUNNING },
+      RunState::PAUSED => match self.state.running {
+        RunState::RUNNING | RunState::ARMED | RunState::PAUSED => RunState::PAUSED,
+        _ => return false
+      },
+      RunState::STOPPING | RunState::STOPPED => RunState::STOPPED
+    };
+    self.changed = true;
+    return true;
+  }
+
+  // selects a song of the setlist like the song page, none for free tempo
+  pub fn select_preset(&mut self, index: Option<u8>) -> bool {
+    if self.state.ramping || index.map_or(false, |i| i >= self.setlist.len()) {
+      return false;
+    }
+    self.select_song(index);
+    self.changed = true;
+    return true;
+  }
+
+  // takes the settings of a stored state, but keeps the transport and the menu
+  pub fn load_state(&mut self, state: State) {
+    self.state = State {
+      running: self.state.running,
+      ramping: self.state.ramping,
+      menu: self.state.menu,
+      ..state
+    };
+    self.changed = true;
+  }
+
+  pub fn control_change(&mut self, controller: u8, value: u8) {
+    if controller == RAMP_CONTROLLER {
+      self.set_ramping(value >= 64);
+      self.changed = true;
+    }
+  }
+
+  // the clock finished or stopped the tempo ramp
+  pub fn ramp_ended(&mut self, bpm: u16) {
+    self.state.bpm = bpm;
+    self.state.ramping = false;
+    self.changed = true;
+  }
+
+  // the clock started on a bar of the external clock
+  pub fn quantized_start(&mut self) {
+    if self.state.running == RunState::ARMED {
+      self.state.running = RunState::RUNNING;
+      self.changed = true;
+    }
+  }
+
+  pub fn button2_pressed(&mut self, pressed : bool) {
+    if pressed {
+      self.state.running = RunState::STOPPING
+    } else {
+      self.state.running = RunState::STOPPED
+    }
+    self.changed = true;
+  }
+
+  pub fn button3_pressed(&mut self, pressed : bool) {
+    if pressed {
+      self.state.clock_sync = true;
+    } else {
+      self.state.clock_sync = false;
+    }
+    self.changed = true;
+  }
+
+  // the menu goes to the next page on release, unless the encoder was used as modifier
+  pub fn encoder_pressed(&mut self, pressed : bool) {
+    if pressed {
+      self.encoder_used = false;
+    } else if !self.encoder_used {
+      self.state.menu = self.state.menu.next();
+      self.changed = true;
+    }
+    self.encoder_held = pressed;
+  }
+
+  // returns to the bpm page
+  pub fn encoder_long_pressed(&mut self) {
+    if self.encoder_used { return }
+    self.encoder_used = true;
+    if self.state.menu != MenuPage::Bpm {
+      self.state.menu = MenuPage::Bpm;
+      self.changed = true;
+    }
+  }
+
+  // the clock stopped after the bars of the current song
+  pub fn song_ended(&mut self) {
+    if self.state.running != RunState::RUNNING { return }
+
+    // send stop first, like pressing the stop button
+    self.state.running = RunState::STOPPING;
+    self.song_advance = true;
+    self.changed = true;
+  }
+
+  // an armed clock starts right away when there is no external clock to wait for,
+  // a ramp ends because the external clock sets the tempo
+  pub fn set_source(&mut self, source: ClockSource) {
+    self.state.clock_source = source;
+    self.changed = true;
+    if source == ClockSource::Internal && self.state.running == RunState::ARMED {
+      self.state.running = RunState::RUNNING;
+    }
+    if source != ClockSource::Internal {
+      self.state.ramping = false;
+    }
+  }
+
+  // ramps only run on the internal clock
+  fn set_ramping(&mut self, ramping: bool) {
+    self.state.ramping = ramping && self.state.clock_source == ClockSource::Internal;
+  }
+
+  // stores a song in the setlist, returns false if it would leave a gap or its settings are out of range
+  pub fn store_song(&mut self, song: Song) -> bool {
+    if song.bpm < BPM_RANGE.0 || song.bpm > BPM_RANGE.1 || song.bar_length < BAR_LENGTHS_RANGE.0 || song.bar_length > BAR_LENGTHS_RANGE.1
+      || !song.divisions.iter().all(|d| DIVISION_STEPS.contains(d)) {
+      return false;
+    }
+    if !self.setlist.set(song) {
+      return false;
+    }
+    // the selected song keeps its tempo until it gets selected again
+    if self.state.song.map_or(false, |selected| selected.index == song.index) {
+      self.state.song = Some(song);
+      self.changed = true;
+    }
+    return true;
+  }
+
+  pub fn setlist(&self) -> &Setlist {
+    return &self.setlist;
+  }
+
+  // steps through the setlist, before the first song is free tempo
+  fn song_index(&self, steps: i16) -> Option<u8> {
+    return match self.state.song {
+      None => if steps > 0 { Some(0) } else { None },
+      Some(song) => {
+        let index = (song.index as i16).saturating_add(steps);
+        if index < 0 { None } else { Some(index.min(self.setlist.len().max(1) as i16 - 1) as u8) }
+      }
+    };
+  }
+
+  // applies the tempo settings of a song of the setlist
+  fn select_song(&mut self, index: Option<u8>) {
+    self.state.song = index.and_then(|i| self.setlist.get(i));
+    if let Some(song) = self.state.song {
+      self.state.bpm = song.bpm.min(BPM_RANGE.1).max(BPM_RANGE.0);
+      self.state.clock_bar_length = song.bar_length.min(BAR_LENGTHS_RANGE.1).max(BAR_LENGTHS_RANGE.0);
+      for i in 0..2 {
+        self.state.clock_divisions[i] = if DIVISION_STEPS.contains(&song.divisions[i]) { song.divisions[i] } else { 1 };
+      }
+    }
+  }
+}
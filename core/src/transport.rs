@@ -0,0 +1,341 @@
+/*
+ * Drives the clock engine from the timer and midi in interrupts: run state, tempo and ramps,
+ * the delay line, following an external clock and watching it for sync loss.
+ *
+ * The firmware and the simulator run the same transport, each with its own timers and outputs.
+ */
+
+use crate::clock::{ClockEngine, ClockSettings, Ramp, Position, CLOCK_TICKS_PER_QUARTER_NOTE,
+  MIDI1_MASK, MIDI2_MASK, TRIGGER1_MASK, TRIGGER2_MASK, TRIGGER3_MASK, TRIGGER4_MASK};
+use crate::delays::{DelayLine, OutputDelays, OutputOffset, OUTPUT_COUNT};
+use crate::events::{Event};
+use crate::midi::{MidiMessage};
+use crate::statemachine::{State, RunState, ClockSource, RampMode};
+
+// ms without midi clock messages until the external clock counts as lost
+pub const SYNC_LOSS_TIMEOUT: u16 = 500;
+
+// transport flags for following an external clock
+const TRANSPORT_EXTERNAL: u8 = 0b001;
+const TRANSPORT_RUNNING: u8 = 0b010;
+const TRANSPORT_ARMED: u8 = 0b100;
+
+/* Timers and outputs the transport runs on */
+pub trait ClockHardware {
+  // the clock timer calls on_timer_tick in this interval, the base ticks keep their pace
+  fn set_interval(&mut self, us: u32);
+  fn set_timer_running(&mut self, running: bool);
+  // midi out1+2 only gets clock ticks, midi out3+4 also the transport messages
+  fn send_midi(&mut self, port: u8, byte: u8);
+  fn fire(&mut self, triggers: u8);
+  fn post(&mut self, event: Event);
+
+  // tempo the timing statistics compare with, none while following an external clock
+  fn expect_bpm(&mut self, _bpm: Option<f32>) {}
+  fn restart_stats(&mut self, _division: u8) {}
+}
+
+pub struct Transport {
+  engine: ClockEngine,
+  settings: ClockSettings,
+  tick_delays: [u8; OUTPUT_COUNT],
+  delay_line: DelayLine,
+  flags: u8,
+  bpm: u16,
+  ramp: Option<Ramp>,
+  external_ticks: u32,
+  external_silence: u16,
+  position: u32, // ticks since the last reset
+  activity: u8 // outputs that sent a tick since it was last read
+}
+
+impl Transport {
+  pub const fn new() -> Transport {
+    return Transport {
+      engine: ClockEngine::new(),
+      settings: ClockSettings { divisions: [1, 1], triggers_ppq: 1, bar_length: 4, reset: false, sync: false },
+      tick_delays: [0; OUTPUT_COUNT],
+      delay_line: DelayLine::new(),
+      flags: 0,
+      bpm: 1,
+      ramp: None,
+      external_ticks: 0,
+      external_silence: 0,
+      position: 0,
+      activity: 0
+    };
+  }
+
+  pub fn init(&mut self, state: &State, hw: &mut impl ClockHardware) {
+    self.settings = ClockSettings {
+      divisions: state.clock_divisions,
+      triggers_ppq: state.clock_trigger_multiplier,
+      bar_length: state.clock_bar_length,
+      reset: false,
+      sync: state.clock_sync
+    };
+    self.set_output_offsets(&state.output_offsets);
+    self.set_bpm(state.bpm, hw);
+    self.set_source(state.clock_source, hw);
+    self.set_runstate(state.running, hw);
+  }
+
+  // follows the changes of the statemachine
+  pub fn on_state_change(&mut self, prev: &State, state: &State, hw: &mut impl ClockHardware) {
+    // source first, an armed clock starts when it goes back to the internal clock
+    if prev.clock_source != state.clock_source {
+      self.set_source(state.clock_source, hw);
+    }
+    if prev.running != state.running {
+      self.set_runstate(state.running, hw);
+      // a stop without the stopping state of the button, e.g. from the shell, stops the followers first
+      if prev.running == RunState::RUNNING && state.running == RunState::STOPPED {
+        send_transport_message(RunState::STOPPING, hw);
+      }
+      // the start message was sent already when the armed clock started
+      if prev.running != RunState::ARMED {
+        send_transport_message(state.running, hw);
+      }
+    }
+    if prev.bpm != state.bpm {
+      self.set_bpm(state.bpm, hw);
+    }
+    self.settings.triggers_ppq = state.clock_trigger_multiplier;
+    self.settings.bar_length = state.clock_bar_length;
+    self.settings.sync = state.clock_sync;
+    if prev.clock_divisions != state.clock_divisions {
+      self.settings.divisions = state.clock_divisions;
+      hw.restart_stats(state.clock_divisions[0]);
+    }
+    if prev.ramping != state.ramping {
+      if state.ramping {
+        self.start_ramp(state.ramp_target, state.ramp_bars, state.ramp_mode, hw);
+      } else {
+        self.stop_ramp(hw);
+      }
+    }
+    if prev.song != state.song || prev.auto_advance != state.auto_advance {
+      let bars = if state.auto_advance { state.song.map_or(0, |song| song.bars) } else { 0 };
+      self.engine.set_song_length(bars);
+    }
+    if prev.output_offsets != state.output_offsets {
+      self.set_output_offsets(&state.output_offsets);
+    }
+  }
+
+  // rewinds the followers together with the clock, e.g. after they lost their position
+  pub fn restart(&mut self, running: RunState, hw: &mut impl ClockHardware) {
+    self.set_runstate(RunState::STOPPED, hw);
+    send_transport_message(RunState::STOPPING, hw);
+    send_transport_message(RunState::STOPPED, hw);
+    self.set_runstate(running, hw);
+  }
+
+  // returns current bpm and progress in percent of a running ramp
+  pub fn ramp_progress(&self) -> Option<(u16, u8)> {
+    return self.ramp.map(|ramp| ramp.progress());
+  }
+
+  pub fn position(&self) -> Position {
+    return Position::from_ticks(self.position, self.settings.bar_length);
+  }
+
+  // returns the masks of the outputs that ticked since the last call
+  pub fn output_activity(&mut self) -> u8 {
+    let activity = self.activity;
+    self.activity = 0;
+    return activity;
+  }
+
+  fn set_output_offsets(&mut self, offsets: &[OutputOffset; OUTPUT_COUNT]) {
+    let delays = OutputDelays::from_offsets(offsets);
+    self.tick_delays = delays.ticks;
+    self.delay_line.set_delays(delays.millis);
+  }
+
+  fn external(&self) -> bool {
+    return self.flags & TRANSPORT_EXTERNAL > 0;
+  }
+
+  fn set_bpm(&mut self, bpm: u16, hw: &mut impl ClockHardware) {
+    self.bpm = bpm;
+
+    // sends 24 ticks for every quarter note
+    hw.set_interval(60 * 1000 * 1000 / (bpm as u32 * CLOCK_TICKS_PER_QUARTER_NOTE));
+
+    if !self.external() {
+      hw.expect_bpm(Some(bpm as f32));
+      hw.restart_stats(self.settings.divisions[0]);
+    }
+  }
+
+  fn set_source(&mut self, source: ClockSource, hw: &mut impl ClockHardware) {
+    let external = source != ClockSource::Internal;
+    self.flags = if external { self.flags | TRANSPORT_EXTERNAL } else { self.flags & !TRANSPORT_EXTERNAL };
+    if !external && self.flags & TRANSPORT_ARMED > 0 {
+      self.start_armed(hw);
+    }
+    // the external clock sets the tempo, the ramp would never advance
+    if external {
+      self.stop_ramp(hw);
+    }
+
+    // intervals of an external clock are not known in advance
+    hw.expect_bpm(if external { None } else { Some(self.bpm as f32) });
+    hw.restart_stats(self.settings.divisions[0]);
+  }
+
+  fn set_runstate(&mut self, running: RunState, hw: &mut impl ClockHardware) {
+    let flags = match running {
+      RunState::RUNNING => TRANSPORT_RUNNING,
+      RunState::ARMED => TRANSPORT_ARMED,
+      _ => 0
+    };
+    self.flags = (self.flags & TRANSPORT_EXTERNAL) | flags;
+    hw.restart_stats(self.settings.divisions[0]);
+
+    match running {
+      RunState::RUNNING | RunState::ARMED => hw.set_timer_running(true),
+      RunState::STOPPED => {
+        self.settings.reset = true;
+        hw.set_timer_running(false);
+        self.delay_line.clear();
+        self.position = 0;
+      },
+      _ => hw.set_timer_running(false)
+    }
+  }
+
+  // glides from the current bpm to the target bpm within the given bars
+  fn start_ramp(&mut self, target: u16, bars: u8, mode: RampMode, hw: &mut impl ClockHardware) {
+    if self.external() {
+      hw.post(Event::RampEnd(self.bpm));
+      return
+    }
+    self.ramp = Some(Ramp::new(self.bpm, target, bars, self.settings.bar_length, mode));
+  }
+
+  // stops the ramp at its current bpm
+  fn stop_ramp(&mut self, hw: &mut impl ClockHardware) {
+    self.ramp.take().map(|ramp| hw.post(Event::RampEnd(ramp.progress().0)));
+  }
+
+  // gets called by the clock timer in the interval of the bpm
+  pub fn on_timer_tick(&mut self, hw: &mut impl ClockHardware) {
+    // timer keeps running for the delay line, but ticks come from the external clock
+    if self.external() { return }
+
+    self.tick(hw);
+    self.update_ramp(hw);
+  }
+
+  fn update_ramp(&mut self, hw: &mut impl ClockHardware) {
+    let finished = self.ramp.as_mut().map_or(false, |ramp| {
+      let finished = ramp.step();
+      hw.set_interval((60.0 * 1000.0 * 1000.0 / (ramp.bpm() * CLOCK_TICKS_PER_QUARTER_NOTE as f32)) as u32);
+      hw.expect_bpm(Some(ramp.bpm()));
+      return finished;
+    });
+
+    if finished {
+      hw.post(Event::RampEnd(self.ramp.take().unwrap().target()));
+    }
+  }
+
+  // gets called on every base tick of the timer, sends the delayed outputs
+  pub fn on_base_tick(&mut self, hw: &mut impl ClockHardware) {
+    let due = self.delay_line.on_base_tick();
+    if due > 0 {
+      self.send(due, hw);
+    }
+  }
+
+  // gets called on every midi clock message received
+  pub fn on_external_tick(&mut self, hw: &mut impl ClockHardware) {
+    self.external_silence = 0;
+    let position = self.external_ticks;
+    self.external_ticks = self.external_ticks.wrapping_add(1);
+
+    if !self.external() { return }
+
+    if self.flags & TRANSPORT_ARMED > 0 {
+      if position % (CLOCK_TICKS_PER_QUARTER_NOTE * self.settings.bar_length as u32) != 0 { return }
+
+      // start on the first tick of the next bar
+      self.start_armed(hw);
+      hw.post(Event::QuantizedStart);
+    } else if self.flags & TRANSPORT_RUNNING == 0 {
+      return
+    }
+
+    self.tick(hw);
+  }
+
+  fn start_armed(&mut self, hw: &mut impl ClockHardware) {
+    self.flags = (self.flags & !TRANSPORT_ARMED) | TRANSPORT_RUNNING;
+    self.settings.reset = true;
+    hw.send_midi(2, MidiMessage::Start as u8);
+    hw.fire(TRIGGER4_MASK); // send sync reset trigger
+  }
+
+  // the bar position of the external clock starts with its start message
+  pub fn on_external_start(&mut self) {
+    self.external_ticks = 0;
+  }
+
+  // gets called every ms, watches the midi clock messages of the external clock
+  pub fn on_sync_timer_tick(&mut self, hw: &mut impl ClockHardware) {
+    if !self.external() || self.flags & TRANSPORT_RUNNING == 0 {
+      self.external_silence = 0;
+      return
+    }
+
+    if self.external_silence == SYNC_LOSS_TIMEOUT {
+      hw.post(Event::SyncLost);
+    }
+    if self.external_silence <= SYNC_LOSS_TIMEOUT {
+      self.external_silence += 1;
+    }
+  }
+
+  fn tick(&mut self, hw: &mut impl ClockHardware) {
+    // only one tick resets the clock
+    let settings = self.settings;
+    self.settings.reset = false;
+    let tick = self.engine.tick(&settings, &self.tick_delays);
+
+    if tick.song_end {
+      hw.post(Event::SongEnd);
+    }
+    if tick.held { return }
+
+    let immediate = self.delay_line.send(tick.outputs);
+    if immediate > 0 {
+      self.send(immediate, hw);
+    }
+    self.position = tick.position;
+  }
+
+  fn send(&mut self, outputs: u8, hw: &mut impl ClockHardware) {
+    self.activity |= outputs;
+    if outputs & MIDI1_MASK > 0 {
+      hw.send_midi(1, MidiMessage::TimingClock as u8);
+    }
+    if outputs & MIDI2_MASK > 0 {
+      hw.send_midi(2, MidiMessage::TimingClock as u8);
+    }
+    hw.fire(outputs & (TRIGGER1_MASK | TRIGGER2_MASK | TRIGGER3_MASK | TRIGGER4_MASK));
+  }
+}
+
+fn send_transport_message(running: RunState, hw: &mut impl ClockHardware) {
+  match running {
+    RunState::RUNNING => hw.send_midi(2, MidiMessage::Continue as u8),
+    RunState::PAUSED | RunState::STOPPING => hw.send_midi(2, MidiMessage::Stop as u8),
+    RunState::STOPPED => {
+      hw.send_midi(2, MidiMessage::Start as u8);
+      hw.fire(TRIGGER4_MASK); // send sync reset trigger
+    },
+    RunState::ARMED => {}
+  }
+}
@@ -0,0 +1,41 @@
+/*
+ * Length of the trigger pulses, counted in the 1ms overflows of timer3
+ */
+
+// stop pulse after 5ms
+const PULSE_OVERFLOWS: u8 = 5;
+
+const TRIGGER_COUNT: usize = 4;
+
+pub struct TriggerPulses {
+  started: u8,
+  overflows: [u8; TRIGGER_COUNT]
+}
+
+impl TriggerPulses {
+  pub const fn new() -> TriggerPulses {
+    return TriggerPulses { started: 0, overflows: [0; TRIGGER_COUNT] };
+  }
+
+  pub fn start(&mut self, triggers: u8) {
+    self.started |= triggers;
+  }
+
+  // gets called every ms, returns the triggers whose pulse ended
+  pub fn on_timer_tick(&mut self) -> u8 {
+    let mut ended: u8 = 0;
+    for i in 0..TRIGGER_COUNT {
+      if self.started & (1 << i) > 0 {
+        if self.overflows[i] > PULSE_OVERFLOWS {
+          ended |= 1 << i;
+        } else {
+          self.overflows[i] += 1;
+        }
+      } else {
+        self.overflows[i] = 0;
+      }
+    }
+    self.started &= !ended;
+    return ended;
+  }
+}
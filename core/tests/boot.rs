@@ -0,0 +1,53 @@
+use midi_clock_core::boot::*;
+use midi_clock_core::gestures::{BUTTON1_MASK, BUTTON2_MASK, BUTTON3_MASK, BUTTON4_MASK};
+use midi_clock_core::statemachine::{MenuPage, DEFAULT_STATE};
+
+#[test]
+fn only_the_exact_combos_choose_a_boot_mode() {
+  assert_eq!(BootMode::from_buttons(0), BootMode::Normal);
+  assert_eq!(BootMode::from_buttons(BUTTON2_MASK | BUTTON3_MASK), BootMode::FactoryReset);
+  assert_eq!(BootMode::from_buttons(BUTTON1_MASK | BUTTON4_MASK), BootMode::SafeMode);
+  assert_eq!(BootMode::from_buttons(BUTTON2_MASK), BootMode::Normal);
+  assert_eq!(BootMode::from_buttons(BUTTON1_MASK | BUTTON2_MASK | BUTTON3_MASK), BootMode::Normal);
+}
+
+#[test]
+fn encoder_button_confirms_after_the_combo_was_released() {
+  let mut confirmation = Confirmation::new(0);
+  // still held from power up, the encoder is no answer yet
+  assert_eq!(confirmation.poll(FACTORY_RESET_BUTTONS, 10), None);
+  assert_eq!(confirmation.poll(FACTORY_RESET_BUTTONS | BUTTON4_MASK, 20), None);
+  assert_eq!(confirmation.poll(0, 30), None);
+  assert_eq!(confirmation.poll(BUTTON4_MASK, 40), None);
+  // counts on release
+  assert_eq!(confirmation.poll(0, 50), Some(true));
+}
+
+#[test]
+fn other_buttons_cancel() {
+  let mut confirmation = Confirmation::new(0);
+  confirmation.poll(0, 10);
+  assert_eq!(confirmation.poll(BUTTON1_MASK, 20), None);
+  assert_eq!(confirmation.poll(0, 30), Some(false));
+
+  let mut confirmation = Confirmation::new(0);
+  confirmation.poll(0, 10);
+  confirmation.poll(BUTTON4_MASK, 20);
+  confirmation.poll(BUTTON4_MASK | BUTTON2_MASK, 30);
+  assert_eq!(confirmation.poll(0, 40), Some(false));
+}
+
+#[test]
+fn no_answer_cancels_after_the_timeout() {
+  let mut confirmation = Confirmation::new(u32::MAX - 100);
+  assert_eq!(confirmation.poll(0, u32::MAX), None);
+  assert_eq!(confirmation.poll(0, CONFIRM_TIMEOUT - 200), None);
+  assert_eq!(confirmation.poll(0, CONFIRM_TIMEOUT - 101), Some(false));
+}
+
+#[test]
+fn safe_mode_starts_with_the_diagnostics() {
+  let state = safe_state();
+  assert!(state.menu == MenuPage::Diagnostics);
+  assert_eq!(state.bpm, DEFAULT_STATE.bpm);
+}
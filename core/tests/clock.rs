@@ -0,0 +1,100 @@
+use midi_clock_core::clock::*;
+use midi_clock_core::statemachine::{RampMode};
+
+const NO_DELAYS: [u8; 4] = [0; 4];
+
+fn settings(divisions: [u8;2], triggers_ppq: u8, bar_length: u8) -> ClockSettings {
+  return ClockSettings { divisions: divisions, triggers_ppq: triggers_ppq, bar_length: bar_length, reset: false, sync: false };
+}
+
+#[test]
+fn settings_survive_packing() {
+  let mut s = settings([3, 32], 24, 15);
+  s.sync = true;
+  assert!(ClockSettings::unpack(s.pack()) == s);
+
+  let reset = ClockSettings::with_reset(s.pack(), true);
+  assert!(ClockSettings::unpack(reset).reset);
+  assert!(ClockSettings::unpack(ClockSettings::with_reset(reset, false)) == s);
+}
+
+#[test]
+fn midi_ticks_follow_the_divisions() {
+  let mut engine = ClockEngine::new();
+  let s = settings([1, 2], 4, 4);
+  let ticks: Vec<u8> = (0..48).map(|_| engine.tick(&s, &NO_DELAYS).outputs).collect();
+
+  assert_eq!(ticks.iter().filter(|t| *t & MIDI1_MASK > 0).count(), 48);
+  assert_eq!(ticks.iter().filter(|t| *t & MIDI2_MASK > 0).count(), 24);
+  // led of out1+2 on every quarter, out3+4 on every second quarter
+  assert_eq!(ticks.iter().filter(|t| *t & TRIGGER1_MASK > 0).count(), 2);
+  assert_eq!(ticks.iter().filter(|t| *t & TRIGGER2_MASK > 0).count(), 1);
+  // 4 triggers per quarter
+  assert_eq!(ticks.iter().filter(|t| *t & TRIGGER3_MASK > 0).count(), 8);
+}
+
+#[test]
+fn delayed_outputs_start_later() {
+  let mut engine = ClockEngine::new();
+  let s = settings([1, 1], 1, 4);
+  let ticks: Vec<u8> = (0..4).map(|_| engine.tick(&s, &[0, 2, 0, 0]).outputs).collect();
+
+  assert!(ticks[0] & MIDI1_MASK > 0);
+  assert!(ticks[0] & MIDI2_MASK == 0 && ticks[1] & MIDI2_MASK == 0);
+  assert!(ticks[2] & MIDI2_MASK > 0 && ticks[2] & TRIGGER2_MASK > 0);
+}
+
+#[test]
+fn sync_sends_one_reset_on_the_next_bar() {
+  let mut engine = ClockEngine::new();
+  let mut s = settings([1, 1], 1, 4);
+  engine.tick(&s, &NO_DELAYS);
+
+  // the sync button gets released before the bar starts
+  s.sync = true;
+  assert!(engine.tick(&s, &NO_DELAYS).outputs & TRIGGER4_MASK == 0);
+  s.sync = false;
+  let resets: Vec<usize> = (0..200).filter(|_| engine.tick(&s, &NO_DELAYS).outputs & TRIGGER4_MASK > 0).collect();
+  assert!(resets == vec![94]);
+}
+
+#[test]
+fn clock_holds_at_the_end_of_the_song() {
+  let mut engine = ClockEngine::new();
+  let s = settings([1, 1], 1, 1);
+  engine.set_song_length(2);
+
+  let ticks: Vec<Tick> = (0..60).map(|_| engine.tick(&s, &NO_DELAYS)).collect();
+  assert_eq!(ticks.iter().filter(|t| t.song_end).count(), 1);
+  assert!(!ticks[47].held);
+  assert!(ticks[48].song_end && ticks[48].held);
+  assert!(ticks[59].held);
+
+  // a reset starts the song again
+  let mut reset = s;
+  reset.reset = true;
+  let tick = engine.tick(&reset, &NO_DELAYS);
+  assert!(!tick.held && tick.position == 0);
+}
+
+#[test]
+fn position_counts_bars_beats_and_sixteenths() {
+  assert!(Position::from_ticks(0, 4) == Position { bar: 1, beat: 1, sixteenth: 1 });
+  assert!(Position::from_ticks(24 * 5 + 18, 4) == Position { bar: 2, beat: 2, sixteenth: 4 });
+  assert!(Position::from_ticks(24 * 5, 0) == Position { bar: 6, beat: 1, sixteenth: 1 });
+}
+
+#[test]
+fn ramps_reach_their_target() {
+  for mode in [RampMode::Linear, RampMode::Exponential].iter() {
+    let mut ramp = Ramp::new(100, 140, 1, 4, *mode);
+    let steps = (0..).take_while(|_| !ramp.step()).count() + 1;
+    assert_eq!(steps, 96);
+    assert_eq!(ramp.progress(), (140, 100));
+  }
+
+  let mut ramp = Ramp::new(100, 200, 1, 4, RampMode::Exponential);
+  for _ in 0..48 { ramp.step(); }
+  // halfway of an exponential ramp is the geometric mean
+  assert!((ramp.bpm() - 141.42).abs() < 0.1);
+}
@@ -0,0 +1,47 @@
+use midi_clock_core::crash::*;
+use midi_clock_core::diagnostics::{crash_sysex, parse_request, Request};
+
+fn panic_report() -> CrashReport {
+  let mut report = CrashReport::new(FaultCode::Panic);
+  report.set_location("/home/user/midi_clock/src/statemachine.rs", 215);
+  report.set_message(format_args!("index out of bounds: the len is {} but the index is {}", 2, 7));
+  return report;
+}
+
+#[test]
+fn report_keeps_the_end_of_the_path_and_cuts_the_message() {
+  let report = panic_report();
+  assert_eq!(report.file(), "/src/statemachine.rs");
+  assert_eq!(report.message(), "index out of bounds: the len is ");
+  assert_eq!(format!("{}", report), "panic at /src/statemachine.rs:215: index out of bounds: the len is ");
+
+  let mut fault = CrashReport::new(FaultCode::HardFault);
+  fault.address = 0x0800_1234;
+  assert_eq!(format!("{}", fault), "hard fault at 0x08001234");
+}
+
+#[test]
+fn report_survives_the_memory_and_rejects_damage() {
+  let report = panic_report();
+  let bytes = report.to_bytes();
+  assert_eq!(bytes.len(), CRASH_REPORT_SIZE);
+  assert!(CrashReport::from_bytes(&bytes) == Some(report));
+
+  let mut damaged = bytes;
+  damaged[30] ^= 0x01;
+  assert!(CrashReport::from_bytes(&damaged).is_none());
+  assert!(CrashReport::from_bytes(&[0xFF; CRASH_REPORT_SIZE]).is_none());
+}
+
+#[test]
+fn sysex_reads_the_report_as_text() {
+  assert!(parse_request(&[0x7D, 0x04]) == Some(Request::CrashReport));
+
+  let (sysex, length) = crash_sysex(Some(&panic_report()));
+  assert_eq!(&sysex[..3], &[0xF0, 0x7D, 0x05]);
+  assert_eq!(&sysex[3..length - 1], format!("{}", panic_report()).as_bytes());
+  assert_eq!(sysex[length - 1], 0xF7);
+
+  let (empty, length) = crash_sysex(None);
+  assert_eq!(&empty[..length], &[0xF0, 0x7D, 0x05, 0xF7]);
+}
@@ -0,0 +1,53 @@
+use midi_clock_core::diagnostics::*;
+use midi_clock_core::midi::{MidiParser, MidiInput};
+
+fn sysex_request(bytes: &[u8]) -> Option<Request> {
+  let mut parser = MidiParser::new();
+  return bytes.iter().filter_map(|b| parser.parse(*b)).find_map(|input| match input {
+    MidiInput::SysEx(data, length) => data.get(..length).and_then(parse_request),
+    _ => None
+  });
+}
+
+#[test]
+fn sysex_requests_are_parsed_between_clock_ticks() {
+  assert!(sysex_request(&[0xF0, 0x7D, 0x01, 0xF7]) == Some(Request::Dump));
+  assert!(sysex_request(&[0xF0, 0x7D, 0xF8, 0x02, 0xF7]) == Some(Request::Reset));
+  // other manufacturers and long messages are ignored
+  assert!(sysex_request(&[0xF0, 0x41, 0x01, 0xF7]) == None);
+  assert!(sysex_request(&[0xF0, 0x7D, 0x01, 0x00, 0x00, 0x00, 0xF7]) == None);
+}
+
+#[test]
+fn tick_stats_track_deviation() {
+  let mut stats = TickStats::new();
+  assert_eq!(stats.jitter(), 0);
+  for deviation in [-4, 4, -4, 4, 2].iter() {
+    stats.add(*deviation);
+  }
+  assert_eq!(stats.count, 5);
+  assert_eq!((stats.min, stats.max), (-4, 4));
+  assert_eq!(stats.mean(), 0);
+  assert_eq!(stats.variance(), 13);
+  assert_eq!(stats.std_dev(), 3);
+  assert_eq!(stats.jitter(), 4);
+}
+
+#[test]
+fn dump_encodes_values_in_seven_bit_bytes() {
+  let mut stats = TickStats::new();
+  stats.add(-1);
+  stats.add(300);
+  let dump = dump_sysex(&stats, 5);
+
+  assert_eq!(dump.len(), SYSEX_DUMP_LENGTH);
+  assert_eq!(&dump[..3], &[0xF0, SYSEX_MANUFACTURER, 0x03]);
+  assert_eq!(dump[SYSEX_DUMP_LENGTH - 1], 0xF7);
+  assert!(dump[1..SYSEX_DUMP_LENGTH - 1].iter().all(|b| *b < 0x80));
+
+  let value = |i: usize| (0..5).fold(0u32, |v, j| v | (dump[3 + i * 5 + j] as u32) << (j * 7));
+  assert_eq!(value(0), 2);
+  assert_eq!(value(1) as i32, -1);
+  assert_eq!(value(2), 300);
+  assert_eq!(value(5), 5);
+}
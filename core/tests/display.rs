@@ -0,0 +1,49 @@
+use midi_clock_core::display::*;
+use midi_clock_core::statemachine::{DEFAULT_STATE};
+
+struct MockDisplay {
+  content: Content,
+  frame: FrameBuffer
+}
+
+impl Display for MockDisplay {
+  fn content(&mut self) -> &mut Content {
+    return &mut self.content;
+  }
+
+  fn init(&mut self) {}
+
+  fn render(&mut self) {
+    self.content.render_page(&mut self.frame);
+  }
+}
+
+fn display() -> MockDisplay {
+  let mut display = MockDisplay { content: Content::new(), frame: FrameBuffer::new(16, 2) };
+  display.update(&DEFAULT_STATE);
+  return display;
+}
+
+fn first_row(display: &MockDisplay) -> String {
+  return String::from_utf8(display.frame.cells[0][..16].to_vec()).unwrap();
+}
+
+#[test]
+fn lower_priorities_wait_for_the_shown_message() {
+  let mut display = display();
+  display.show_message("reset?  push enc", Priority::Error);
+  display.show_message("settings reset", Priority::Warning);
+  display.render();
+  assert_eq!(first_row(&display), "reset?  push enc");
+}
+
+#[test]
+fn warning_appears_after_the_answered_question() {
+  let mut display = display();
+  display.show_message("reset?  push enc", Priority::Error);
+  display.clear_message();
+  display.show_message("settings reset", Priority::Warning);
+  display.render();
+  assert_eq!(first_row(&display), "settings reset  ");
+  assert!(display.content.message.map_or(false, |message| message.priority == Priority::Warning));
+}
@@ -0,0 +1,112 @@
+mod mock_eeprom;
+
+use embedded_hal::blocking::i2c::{Write};
+use midi_clock_core::eeprom::*;
+use mock_eeprom::{MockEeprom, MockError};
+
+fn setup(chip: Chip, write_cycle: u32) -> (Eeprom<MockEeprom>, MockEeprom) {
+  let mock = MockEeprom::new(chip, write_cycle);
+  return (Eeprom::new(mock.clone(), chip), mock);
+}
+
+fn pattern(length: usize) -> Vec<u8> {
+  return (0..length).map(|i| i as u8).collect();
+}
+
+#[test]
+fn mock_wraps_around_within_a_page() {
+  let mut mock = MockEeprom::new(CHIP_24C64, 0);
+  let mut bytes = vec![0x00, 0x1C];
+  bytes.extend(pattern(8));
+  mock.write(0x50, &bytes).unwrap();
+  assert_eq!(mock.contents(0x1C, 4), vec![0, 1, 2, 3]);
+  assert_eq!(mock.contents(0x00, 4), vec![4, 5, 6, 7]);
+  assert_eq!(mock.contents(0x20, 1), vec![0xFF]);
+}
+
+#[test]
+fn writes_are_split_at_the_pages() {
+  let (mut eeprom, mock) = setup(CHIP_24C64, 0);
+  let data = pattern(100);
+  eeprom.write(20, &data).unwrap();
+  assert_eq!(mock.contents(20, 100), data);
+  // 12 + 32 + 32 + 24 bytes
+  assert_eq!(mock.page_writes(), 4);
+
+  let mut buffer = [0u8; 100];
+  eeprom.read(20, &mut buffer).unwrap();
+  assert_eq!(buffer.to_vec(), data);
+}
+
+#[test]
+fn write_waits_for_the_write_cycle() {
+  let (mut eeprom, mock) = setup(CHIP_24C64, 40);
+  eeprom.write_u16(0x0004, 0x1234).unwrap();
+  assert_eq!(mock.polls(), 40);
+  assert_eq!(eeprom.read_u16(0x0004), Ok(0x1234));
+}
+
+#[test]
+fn write_cycle_that_does_not_end_times_out() {
+  let (mut eeprom, _) = setup(CHIP_24C64, 10_000);
+  assert_eq!(eeprom.write(0, &[1, 2, 3]), Err(EepromError::Timeout));
+}
+
+#[test]
+fn bus_errors_are_reported() {
+  let (mut eeprom, mock) = setup(CHIP_24C64, 5);
+  mock.nack(1);
+  assert_eq!(eeprom.write(0, &[1]), Err(EepromError::I2c(MockError::Nack)));
+  assert_eq!(mock.page_writes(), 0);
+
+  mock.fill(0, &[9, 8, 7, 6]);
+  mock.nack(1);
+  let mut buffer = [0u8; 4];
+  assert_eq!(eeprom.read(0, &mut buffer), Err(EepromError::I2c(MockError::Nack)));
+  assert_eq!(eeprom.read(0, &mut buffer), Ok(()));
+  assert_eq!(buffer, [9, 8, 7, 6]);
+}
+
+#[test]
+fn corrupt_bytes_fail_the_verification() {
+  let (mut eeprom, mock) = setup(CHIP_24C64, 5);
+  mock.corrupt(0x45);
+  assert_eq!(eeprom.write(0x40, &pattern(40)), Err(EepromError::Verify));
+  // the pages after the damaged one are not written
+  assert_eq!(mock.page_writes(), 1);
+}
+
+#[test]
+fn access_beyond_the_end_is_rejected() {
+  let (mut eeprom, mock) = setup(CHIP_24C64, 0);
+  assert_eq!(eeprom.write(0x1FFF, &[1, 2]), Err(EepromError::OutOfRange));
+  let mut buffer = [0u8; 2];
+  assert_eq!(eeprom.read(0x1FFF, &mut buffer), Err(EepromError::OutOfRange));
+  assert_eq!(eeprom.write(0x1FFE, &[1, 2]), Ok(()));
+  assert_eq!(mock.contents(0x1FFE, 2), vec![1, 2]);
+}
+
+#[test]
+fn small_chips_select_the_block_in_the_device_address() {
+  let (mut eeprom, mock) = setup(CHIP_24C16, 3);
+  let data = pattern(40);
+  eeprom.write(0x1F4, &data).unwrap();
+  assert_eq!(mock.contents(0x1F4, 40), data);
+  // 12 + 16 + 12 bytes
+  assert_eq!(mock.page_writes(), 3);
+
+  let (mut eeprom, mock) = setup(CHIP_24C02, 3);
+  eeprom.write(0xFC, &[1, 2, 3, 4]).unwrap();
+  assert_eq!(mock.contents(0xFC, 4), vec![1, 2, 3, 4]);
+  assert_eq!(eeprom.write(0xFD, &[1, 2, 3, 4]), Err(EepromError::OutOfRange));
+}
+
+#[test]
+fn large_chips_take_long_pages() {
+  let (mut eeprom, mock) = setup(CHIP_24C512, 3);
+  let data = pattern(200);
+  assert_eq!(eeprom.write(0xFF80, &data), Err(EepromError::OutOfRange));
+  eeprom.write(0xFE00, &data).unwrap();
+  assert_eq!(mock.contents(0xFE00, 200), data);
+  assert_eq!(mock.page_writes(), 2);
+}
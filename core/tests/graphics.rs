@@ -0,0 +1,206 @@
+use std::convert::Infallible;
+
+use embedded_graphics::prelude::*;
+use embedded_graphics::pixelcolor::{Rgb565};
+
+use midi_clock_core::graphics::*;
+use midi_clock_core::clock::{MIDI1_MASK, MIDI2_MASK, TRIGGER3_MASK};
+use midi_clock_core::display::{Content};
+use midi_clock_core::statemachine::{State, RunState, ClockSource, MenuPage, DEFAULT_STATE};
+
+const SIZE: usize = SCREEN_SIZE as usize;
+
+// 240x240 pixels in memory, counts the drawn pixels to check the partial redraws
+struct Framebuffer {
+  pixels: Vec<Rgb565>,
+  drawn: usize
+}
+
+impl Framebuffer {
+  fn new() -> Framebuffer {
+    return Framebuffer { pixels: vec![BACKGROUND; SIZE * SIZE], drawn: 0 };
+  }
+
+  fn at(&self, point: Point) -> Rgb565 {
+    return self.pixels[point.y as usize * SIZE + point.x as usize];
+  }
+
+  fn count(&self, origin: Point, size: Size, color: Rgb565) -> usize {
+    let mut count = 0;
+    for y in origin.y..origin.y + size.height as i32 {
+      for x in origin.x..origin.x + size.width as i32 {
+        if self.at(Point::new(x, y)) == color {
+          count += 1;
+        }
+      }
+    }
+    return count;
+  }
+}
+
+impl OriginDimensions for Framebuffer {
+  fn size(&self) -> Size {
+    return Size::new(SCREEN_SIZE as u32, SCREEN_SIZE as u32);
+  }
+}
+
+impl DrawTarget for Framebuffer {
+  type Color = Rgb565;
+  type Error = Infallible;
+
+  fn draw_iter<I: IntoIterator<Item = Pixel<Rgb565>>>(&mut self, pixels: I) -> Result<(), Infallible> {
+    for Pixel(point, color) in pixels {
+      if point.x < 0 || point.y < 0 || point.x >= SIZE as i32 || point.y >= SIZE as i32 { continue }
+      self.pixels[point.y as usize * SIZE + point.x as usize] = color;
+      self.drawn += 1;
+    }
+    return Ok(());
+  }
+}
+
+// middle of the segment with the given index a-g of a digit
+fn segment_center(digit: i32, segment: usize) -> Point {
+  let (w, h, t) = (DIGIT_SIZE.width as i32, DIGIT_SIZE.height as i32, SEGMENT_WIDTH as i32);
+  let centers = [
+    (w / 2, t / 2), (w - t / 2, h / 4), (w - t / 2, h * 3 / 4), (w / 2, h - t / 2),
+    (t / 2, h * 3 / 4), (t / 2, h / 4), (w / 2, h / 2)
+  ];
+  let (x, y) = centers[segment];
+  return DIGIT_ORIGIN + Point::new(digit * DIGIT_SPACING + x, y);
+}
+
+fn lit_segments(fb: &Framebuffer, digit: i32) -> Vec<usize> {
+  return (0..7).filter(|s| fb.at(segment_center(digit, *s)) == Rgb565::WHITE).collect();
+}
+
+fn led_center(i: i32) -> Point {
+  return LED_ORIGIN + Point::new(i * LED_SPACING + LED_SIZE as i32 / 2, LED_SIZE as i32 / 2);
+}
+
+fn content(state: State) -> Content {
+  let mut content = Content::new();
+  content.state = Some(state);
+  return content;
+}
+
+#[test]
+fn bpm_is_drawn_as_seven_segment_digits() {
+  let mut fb = Framebuffer::new();
+  draw_bpm(&mut fb, 120).unwrap();
+  assert_eq!(lit_segments(&fb, 0), vec![1, 2]);
+  assert_eq!(lit_segments(&fb, 1), vec![0, 1, 3, 4, 6]);
+  assert_eq!(lit_segments(&fb, 2), vec![0, 1, 2, 3, 4, 5]);
+
+  // the leading zero stays dark
+  draw_bpm(&mut fb, 98).unwrap();
+  assert!(lit_segments(&fb, 0).is_empty());
+  assert_eq!(fb.at(segment_center(0, 0)), SEGMENT_OFF);
+  assert_eq!(lit_segments(&fb, 1), vec![0, 1, 2, 3, 5, 6]);
+  assert_eq!(lit_segments(&fb, 2), vec![0, 1, 2, 3, 4, 5, 6]);
+}
+
+#[test]
+fn transport_icon_shows_the_run_state() {
+  let mut fb = Framebuffer::new();
+  let icon = Size::new(ICON_SIZE, ICON_SIZE);
+  let center = TRANSPORT_ORIGIN + Point::new(ICON_SIZE as i32 / 2, ICON_SIZE as i32 / 2);
+
+  draw_transport(&mut fb, RunState::RUNNING).unwrap();
+  assert_eq!(fb.at(TRANSPORT_ORIGIN + Point::new(2, ICON_SIZE as i32 / 2)), Rgb565::GREEN);
+
+  draw_transport(&mut fb, RunState::STOPPED).unwrap();
+  assert_eq!(fb.count(TRANSPORT_ORIGIN, icon, Rgb565::GREEN), 0);
+  assert_eq!(fb.at(center), Rgb565::RED);
+
+  // two bars with a gap
+  draw_transport(&mut fb, RunState::PAUSED).unwrap();
+  assert_eq!(fb.count(TRANSPORT_ORIGIN, icon, Rgb565::YELLOW), 2 * 7 * ICON_SIZE as usize);
+  assert_eq!(fb.at(center), BACKGROUND);
+
+  // ring around an empty center
+  draw_transport(&mut fb, RunState::ARMED).unwrap();
+  assert_eq!(fb.at(center), BACKGROUND);
+  assert_eq!(fb.at(TRANSPORT_ORIGIN + Point::new(ICON_SIZE as i32 / 2, 1)), Rgb565::YELLOW);
+}
+
+#[test]
+fn beat_ring_fills_the_current_beat() {
+  let mut fb = Framebuffer::new();
+  let marker = |i: usize| RING_CENTER + Point::new(RING_POSITIONS[i].0, RING_POSITIONS[i].1);
+
+  // 4 beats on every 4th position, the first beat is red
+  draw_beat_ring(&mut fb, 4, 1).unwrap();
+  assert_eq!(fb.at(marker(0)), Rgb565::RED);
+  assert_eq!(fb.at(marker(4)), BACKGROUND);
+  assert_eq!(fb.at(marker(4) + Point::new(0, -(RING_MARKER_SIZE as i32) / 2)), Rgb565::WHITE);
+
+  draw_beat_ring(&mut fb, 4, 3).unwrap();
+  assert_eq!(fb.at(marker(0)), BACKGROUND);
+  assert_eq!(fb.at(marker(8)), Rgb565::YELLOW);
+
+  // a smaller bar clears the markers of the larger one
+  draw_beat_ring(&mut fb, 2, 0).unwrap();
+  assert_eq!(fb.count(marker(4) - Point::new(5, 5), Size::new(10, 10), Rgb565::WHITE), 0);
+}
+
+#[test]
+fn leds_show_the_outputs_that_ticked() {
+  let mut fb = Framebuffer::new();
+  draw_activity(&mut fb, MIDI1_MASK | TRIGGER3_MASK).unwrap();
+  let colors: Vec<Rgb565> = (0..4).map(|i| fb.at(led_center(i))).collect();
+  assert_eq!(colors, vec![Rgb565::GREEN, LED_OFF, Rgb565::GREEN, LED_OFF]);
+}
+
+#[test]
+fn sync_icon_is_green_when_synced_and_blue_when_following() {
+  let mut screen = Screen::new();
+  let mut fb = Framebuffer::new();
+  let center = SYNC_ORIGIN + Point::new(ICON_SIZE as i32 / 2, ICON_SIZE as i32 / 2);
+
+  screen.draw(&mut fb, &content(DEFAULT_STATE), 0).unwrap();
+  assert_eq!(fb.at(center), BACKGROUND);
+  screen.draw(&mut fb, &content(State { clock_source: ClockSource::MidiIn, ..DEFAULT_STATE }), 0).unwrap();
+  assert_eq!(fb.at(center), Rgb565::BLUE);
+  screen.draw(&mut fb, &content(State { clock_sync: true, ..DEFAULT_STATE }), 0).unwrap();
+  assert_eq!(fb.at(center), Rgb565::GREEN);
+}
+
+#[test]
+fn only_the_changed_parts_get_redrawn() {
+  let mut screen = Screen::new();
+  let mut fb = Framebuffer::new();
+  screen.draw_labels(&mut fb).unwrap();
+  screen.draw(&mut fb, &content(DEFAULT_STATE), 0).unwrap();
+  assert_eq!(lit_segments(&fb, 0), vec![1, 2]);
+
+  fb.drawn = 0;
+  screen.draw(&mut fb, &content(DEFAULT_STATE), 0).unwrap();
+  assert_eq!(fb.drawn, 0);
+
+  // activity only redraws the leds
+  assert!(screen.activity_changed(MIDI2_MASK));
+  screen.draw(&mut fb, &content(DEFAULT_STATE), MIDI2_MASK).unwrap();
+  assert_eq!(fb.at(led_center(1)), Rgb565::GREEN);
+  let mut leds = Framebuffer::new();
+  draw_activity(&mut leds, 0).unwrap();
+  assert_eq!(fb.drawn, leds.drawn);
+}
+
+#[test]
+fn other_pages_are_drawn_as_text() {
+  let mut screen = Screen::new();
+  let mut fb = Framebuffer::new();
+  let first_row = Size::new(TEXT_COLUMNS as u32 * 10, 20);
+  let first_row_origin = TEXT_ORIGIN - Point::new(0, 15);
+
+  // the bpm page shows its value as digits, not as text
+  screen.draw(&mut fb, &content(State { running: RunState::STOPPED, ..DEFAULT_STATE }), 0).unwrap();
+  assert_eq!(fb.count(first_row_origin, first_row, Rgb565::WHITE), 0);
+
+  screen.draw(&mut fb, &content(State { menu: MenuPage::BarLength, ..DEFAULT_STATE }), 0).unwrap();
+  assert!(fb.count(first_row_origin, first_row, Rgb565::WHITE) > 0);
+
+  // back on the bpm page the text gets cleared by the font background
+  screen.draw(&mut fb, &content(State { running: RunState::STOPPED, ..DEFAULT_STATE }), 0).unwrap();
+  assert_eq!(fb.count(first_row_origin, first_row, Rgb565::WHITE), 0);
+}
@@ -0,0 +1,93 @@
+use midi_clock_core::encoder::{EncoderDecoder, acceleration};
+use midi_clock_core::midi::{MidiParser, MidiInput};
+use midi_clock_core::gestures::*;
+
+// pin levels of a detent, true if the pin is low
+const CW: [(bool, bool); 4] = [(false, true), (true, true), (true, false), (false, false)];
+const CCW: [(bool, bool); 4] = [(true, false), (true, true), (false, true), (false, false)];
+
+fn turn(decoder: &mut EncoderDecoder, levels: &[(bool, bool)]) -> i32 {
+  let mut detents = 0;
+  let mut previous = (false, false);
+  for (pin1, pin2) in levels.iter() {
+    if *pin1 != previous.0 { detents += decoder.on_change(Some(*pin1), None) as i32 }
+    if *pin2 != previous.1 { detents += decoder.on_change(None, Some(*pin2)) as i32 }
+    previous = (*pin1, *pin2);
+  }
+  return detents;
+}
+
+#[test]
+fn encoder_counts_detents_in_both_directions() {
+  let mut decoder = EncoderDecoder::new();
+  assert_eq!(turn(&mut decoder, &CW), 1);
+  assert_eq!(turn(&mut decoder, &CW), 1);
+  assert_eq!(turn(&mut decoder, &CCW), -1);
+}
+
+#[test]
+fn encoder_accelerates_fast_turns() {
+  assert_eq!(acceleration(10), 8);
+  assert_eq!(acceleration(50), 2);
+  assert_eq!(acceleration(500), 1);
+}
+
+#[test]
+fn midi_parser_handles_running_status_and_realtime() {
+  let mut parser = MidiParser::new();
+  let bytes = [0xB3, 20, 0xF8, 100, 21, 0, 0xFA, 0x90, 1, 2];
+  let inputs: Vec<MidiInput> = bytes.iter().filter_map(|b| parser.parse(*b)).collect();
+  assert!(inputs == vec![
+    MidiInput::TimingClock,
+    MidiInput::ControlChange(20, 100),
+    MidiInput::ControlChange(21, 0),
+    MidiInput::Start
+  ]);
+}
+
+fn drain(detector: &mut GestureDetector, now: u32) -> Vec<ButtonEvent> {
+  let mut events = Vec::new();
+  while let Some(event) = detector.poll(now) {
+    events.push(event);
+  }
+  return events;
+}
+
+#[test]
+fn gestures_detect_double_clicks_and_long_presses() {
+  let mut detector = GestureDetector::new(DEFAULT_GESTURE_CONFIG);
+  detector.on_change(BUTTON1_MASK, BUTTON1_MASK, 0);
+  detector.on_change(BUTTON1_MASK, 0, 50);
+  detector.on_change(BUTTON1_MASK, BUTTON1_MASK, 100);
+  assert!(drain(&mut detector, 100).contains(&ButtonEvent::DoubleClick(BUTTON1_MASK)));
+
+  detector.on_change(BUTTON1_MASK, 0, 150);
+  detector.on_change(BUTTON2_MASK, BUTTON2_MASK, 1000);
+  drain(&mut detector, 1000);
+  assert!(drain(&mut detector, 1900) == vec![ButtonEvent::LongPress(BUTTON2_MASK)]);
+}
+
+#[test]
+fn gestures_report_combos_without_long_press() {
+  let mut detector = GestureDetector::new(DEFAULT_GESTURE_CONFIG);
+  detector.on_change(BUTTON1_MASK, BUTTON1_MASK, 0);
+  detector.on_change(BUTTON3_MASK, BUTTON1_MASK | BUTTON3_MASK, 10);
+  assert!(drain(&mut detector, 10).contains(&ButtonEvent::Combo(BUTTON1_MASK | BUTTON3_MASK)));
+  assert!(drain(&mut detector, 2000).is_empty());
+}
+
+#[test]
+fn buttons_held_through_boot_are_ignored_until_released() {
+  let mut detector = GestureDetector::new(DEFAULT_GESTURE_CONFIG);
+  // play and encoder of the safe mode, their press was never debounced
+  detector.ignore(BUTTON1_MASK | BUTTON4_MASK);
+  detector.on_change(BUTTON4_MASK, BUTTON1_MASK, 100);
+  assert!(drain(&mut detector, 2000).is_empty());
+  detector.on_change(BUTTON1_MASK, 0, 2100);
+  assert!(drain(&mut detector, 2100).is_empty());
+
+  // the next press counts
+  detector.on_change(BUTTON4_MASK, BUTTON4_MASK, 2200);
+  detector.on_change(BUTTON4_MASK, 0, 2300);
+  assert!(drain(&mut detector, 2300) == vec![ButtonEvent::Press(BUTTON4_MASK), ButtonEvent::Release(BUTTON4_MASK)]);
+}
@@ -0,0 +1,52 @@
+use midi_clock_core::log::*;
+
+fn drain<const N: usize>(buffer: &mut LogBuffer<N>) -> String {
+  let mut out = Vec::new();
+  buffer.flush(|byte| { out.push(byte); true });
+  return String::from_utf8(out).unwrap();
+}
+
+#[test]
+fn lines_are_formatted_with_their_level() {
+  let line = LogLine::format(Level::Warn, format_args!("bpm {} div {}", 120, 4));
+  assert_eq!(line.as_bytes(), b"[WARN] bpm 120 div 4\r\n");
+
+  let long = LogLine::format(Level::Info, format_args!("{}", "x".repeat(100)));
+  assert_eq!(long.as_bytes().len(), LOG_LINE_LENGTH);
+  assert!(long.as_bytes().ends_with(b"xx\r\n"));
+}
+
+#[test]
+fn levels_include_the_more_important_ones() {
+  assert!(Level::Info.includes(Level::Error));
+  assert!(Level::Info.includes(Level::Info));
+  assert!(!Level::Info.includes(Level::Trace));
+  assert!(Level::Trace.includes(Level::Trace));
+}
+
+#[test]
+fn flush_stops_while_the_uart_is_busy() {
+  let mut buffer: LogBuffer<64> = LogBuffer::new();
+  buffer.push(&LogLine::format(Level::Error, format_args!("stuck")));
+
+  let mut sent = Vec::new();
+  buffer.flush(|byte| { if sent.len() < 3 { sent.push(byte); true } else { false } });
+  assert_eq!(sent, b"[ER");
+  assert_eq!(drain(&mut buffer), "ROR] stuck\r\n");
+  assert!(buffer.is_empty());
+}
+
+#[test]
+fn full_buffer_drops_whole_lines_and_reports_them() {
+  let mut buffer: LogBuffer<64> = LogBuffer::new();
+  let first = "x".repeat(40);
+  assert!(buffer.push(&LogLine::format(Level::Info, format_args!("{}", first))));
+  assert!(!buffer.push(&LogLine::format(Level::Info, format_args!("second line"))));
+  assert!(!buffer.push(&LogLine::format(Level::Info, format_args!("third line"))));
+  assert_eq!(buffer.dropped(), 2);
+
+  assert_eq!(drain(&mut buffer), format!("[INFO] {}\r\n", first));
+  assert!(buffer.push(&LogLine::format(Level::Info, format_args!("x"))));
+  assert_eq!(drain(&mut buffer), "[WARN] 2 log lines dropped\r\n[INFO] x\r\n");
+  assert_eq!(buffer.dropped(), 0);
+}
@@ -0,0 +1,159 @@
+mod mock_eeprom;
+
+use midi_clock_core::memory::*;
+use midi_clock_core::eeprom::{Eeprom, EepromError, CHIP_24C64};
+use midi_clock_core::crash::{CrashReport, FaultCode};
+use midi_clock_core::setlist::{Song, SONG_SIZE};
+use midi_clock_core::statemachine::{State, ClockSource, RampMode, RunState, DEFAULT_STATE, STATE_SIZE};
+use midi_clock_core::delays::{OutputOffset};
+use mock_eeprom::{MockEeprom, MockError};
+
+fn memory() -> (Memory<MockEeprom>, MockEeprom) {
+  let mock = MockEeprom::new(CHIP_24C64, 20);
+  return (Memory::new(Eeprom::new(mock.clone(), CHIP_24C64)), mock);
+}
+
+fn song(index: u8, bpm: u16) -> Song {
+  return Song { index: index, name: *b"intro   ", bpm: bpm, bar_length: 4, divisions: [1, 2], bars: 16 };
+}
+
+#[test]
+fn state_can_be_read_right_after_writing() {
+  let (mut memory, mock) = memory();
+  let mut state = DEFAULT_STATE;
+  state.bpm = 142;
+  memory.write_state(&state).unwrap();
+  assert_eq!(mock.polls(), 20);
+  assert_eq!(memory.load_state().map(|state| state.bpm), Some(142));
+}
+
+#[test]
+fn every_setting_survives_a_reboot() {
+  let (mut memory, _) = memory();
+  memory.write_song(&song(0, 120)).unwrap();
+  memory.write_song(&song(1, 96)).unwrap();
+
+  let mut state = State {
+    bpm: 96, clock_trigger_multiplier: 12, clock_divisions: [1, 2], clock_bar_length: 4,
+    clock_source: ClockSource::MidiIn, quantized_start: true,
+    ramp_target: 180, ramp_bars: 16, ramp_mode: RampMode::Exponential,
+    song: Some(song(1, 96)), auto_advance: true, ..DEFAULT_STATE
+  };
+  state.output_offsets[3] = OutputOffset { millis: -20, ticks: 3 };
+  memory.write_state(&State { running: RunState::PAUSED, ramping: true, clock_sync: true, ..state }).unwrap();
+
+  let loaded = memory.load_state().unwrap();
+  assert_eq!(loaded.to_bytes(), state.to_bytes());
+  assert!(loaded.song == Some(song(1, 96)));
+  // the transport starts fresh
+  assert!(loaded.running == DEFAULT_STATE.running);
+  assert!(!loaded.ramping && !loaded.clock_sync);
+}
+
+#[test]
+fn invalid_settings_fall_back_to_their_defaults() {
+  let (mut memory, mock) = memory();
+  let mut bytes = DEFAULT_STATE.to_bytes();
+  bytes[3] = 5; // no multiplier
+  bytes[6] = 0; // bar length
+  bytes[13] = 3; // song that is not in the setlist
+  bytes[15] = -60i8 as u8; // offset of output 1
+  bytes[17] = 10;
+  mock.fill(STATE_ADDRESS as usize, &bytes);
+
+  let loaded = memory.load_state().unwrap();
+  assert_eq!((loaded.clock_trigger_multiplier, loaded.clock_bar_length), (4, 4));
+  assert!(loaded.song.is_none());
+  assert!(loaded.output_offsets[0] == DEFAULT_STATE.output_offsets[0]);
+  assert!(loaded.output_offsets[1] == OutputOffset { millis: 10, ticks: 0 });
+}
+
+#[test]
+fn crash_report_spans_two_pages_and_can_be_cleared() {
+  let (mut memory, mock) = memory();
+  let mut report = CrashReport::new(FaultCode::Panic);
+  report.set_location("src/main.rs", 42);
+  report.set_message(format_args!("oops"));
+
+  assert!(memory.load_crash_report().is_none());
+  memory.write_crash_report(&report).unwrap();
+  assert_eq!(mock.page_writes(), 2);
+  assert!(memory.load_crash_report() == Some(report));
+
+  memory.clear_crash_report().unwrap();
+  assert!(memory.load_crash_report().is_none());
+}
+
+#[test]
+fn songs_end_up_in_the_setlist() {
+  let (mut memory, mock) = memory();
+  assert_eq!(memory.load_setlist().len(), 0);
+
+  memory.write_song(&song(0, 120)).unwrap();
+  memory.write_song(&song(1, 96)).unwrap();
+  let setlist = memory.load_setlist();
+  assert_eq!(setlist.len(), 2);
+  assert!(setlist.get(1) == Some(song(1, 96)));
+  assert_eq!(mock.contents(SETLIST_ADDRESS as usize + SONG_SIZE, SONG_SIZE), song(1, 96).to_bytes().to_vec());
+}
+
+#[test]
+fn layout_regions_do_not_overlap() {
+  let (mut memory, mock) = memory();
+  assert!(STATE_ADDRESS as usize + STATE_SIZE <= CRASH_REPORT_ADDRESS as usize);
+  mock.fill(STATE_ADDRESS as usize, &[0, 100]);
+  memory.write_crash_report(&CrashReport::new(FaultCode::HardFault)).unwrap();
+  memory.write_song(&song(0, 120)).unwrap();
+  assert_eq!(memory.load_state().map(|state| state.bpm), Some(100));
+}
+
+#[test]
+fn bpm_of_the_older_layout_is_kept() {
+  let (mut memory, mock) = memory();
+  mock.fill(STATE_ADDRESS as usize, &[0, 100, 0xFF, 24, 8]);
+  let loaded = memory.load_state().unwrap();
+  assert_eq!(loaded.bpm, 100);
+  assert_eq!((loaded.clock_trigger_multiplier, loaded.clock_divisions), (4, [1, 1]));
+}
+
+#[test]
+fn faults_surface_as_memory_errors() {
+  let (mut memory, mock) = memory();
+  mock.nack(1);
+  assert_eq!(memory.write_state(&DEFAULT_STATE), Err(MemoryError::WriteError(EepromError::I2c(MockError::Nack))));
+
+  mock.corrupt(CRASH_REPORT_ADDRESS as usize + 40);
+  let report = CrashReport::new(FaultCode::HardFault);
+  assert_eq!(memory.write_crash_report(&report), Err(MemoryError::WriteError(EepromError::Verify)));
+  assert!(memory.load_crash_report().is_none());
+
+  let mut buffer = [0u8; 8];
+  mock.nack(1);
+  assert_eq!(memory.read_bytes(0, &mut buffer), Err(MemoryError::ReadError(EepromError::I2c(MockError::Nack))));
+}
+
+#[test]
+fn unreadable_memory_falls_back_to_defaults() {
+  let (mut memory, mock) = memory();
+  memory.write_song(&song(0, 120)).unwrap();
+  mock.nack(2);
+  assert!(memory.load_state().is_none());
+  assert_eq!(memory.load_setlist().len(), 0);
+  assert_eq!(memory.load_setlist().len(), 1);
+}
+
+#[test]
+fn factory_reset_restores_the_defaults_and_keeps_the_crash_report() {
+  let (mut memory, _) = memory();
+  let mut state = DEFAULT_STATE;
+  state.bpm = 200;
+  memory.write_state(&state).unwrap();
+  memory.write_song(&song(0, 120)).unwrap();
+  memory.write_song(&song(15, 90)).unwrap();
+  memory.write_crash_report(&CrashReport::new(FaultCode::HardFault)).unwrap();
+
+  memory.factory_reset().unwrap();
+  assert_eq!(memory.load_state().map(|state| state.bpm), Some(DEFAULT_STATE.bpm));
+  assert_eq!(memory.load_setlist().len(), 0);
+  assert!(memory.load_crash_report().is_some());
+}
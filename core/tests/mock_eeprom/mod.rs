@@ -0,0 +1,144 @@
+/*
+ * In-memory 24Cxx eeprom on a simulated i2c bus, shared by the eeprom and memory tests
+ *
+ * Like the real chips it wraps around within a page and does not acknowledge while it writes.
+ * The write cycle lasts a number of bus transactions instead of a time.
+ */
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use midi_clock_core::eeprom::{Chip};
+
+const DEVICE_ADDRESS: u8 = 0b1010_0000 >> 1;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MockError {
+  Nack
+}
+
+struct Device {
+  chip: Chip,
+  memory: Vec<u8>,
+  pointer: usize,
+  write_cycle: u32, // transactions the chip stays busy after a write
+  busy: u32,
+  nacks: u32, // injected faults
+  corrupt: Vec<usize>, // addresses that store the written bytes inverted
+  page_writes: u32,
+  polls: u32
+}
+
+// clones share the device, so a test can inspect it while the driver owns one
+#[derive(Clone)]
+pub struct MockEeprom {
+  device: Rc<RefCell<Device>>
+}
+
+impl MockEeprom {
+  // erased memory reads as 0xFF
+  pub fn new(chip: Chip, write_cycle: u32) -> MockEeprom {
+    let device = Device {
+      chip: chip, memory: vec![0xFF; chip.capacity], pointer: 0, write_cycle: write_cycle, busy: 0,
+      nacks: 0, corrupt: Vec::new(), page_writes: 0, polls: 0
+    };
+    return MockEeprom { device: Rc::new(RefCell::new(device)) };
+  }
+
+  // the next transactions are not acknowledged
+  pub fn nack(&self, count: u32) {
+    self.device.borrow_mut().nacks = count;
+  }
+
+  pub fn corrupt(&self, address: usize) {
+    self.device.borrow_mut().corrupt.push(address);
+  }
+
+  pub fn contents(&self, address: usize, length: usize) -> Vec<u8> {
+    return self.device.borrow().memory[address..address + length].to_vec();
+  }
+
+  pub fn fill(&self, address: usize, bytes: &[u8]) {
+    self.device.borrow_mut().memory[address..address + bytes.len()].copy_from_slice(bytes);
+  }
+
+  pub fn page_writes(&self) -> u32 {
+    return self.device.borrow().page_writes;
+  }
+
+  // transactions that were not acknowledged because of the write cycle
+  pub fn polls(&self) -> u32 {
+    return self.device.borrow().polls;
+  }
+}
+
+impl Device {
+  fn start(&mut self, address: u8) -> Result<(), MockError> {
+    if self.nacks > 0 {
+      self.nacks -= 1;
+      return Err(MockError::Nack);
+    }
+    if self.busy > 0 {
+      self.busy -= 1;
+      self.polls += 1;
+      return Err(MockError::Nack);
+    }
+    if address & !0b111 != DEVICE_ADDRESS { return Err(MockError::Nack) }
+    return Ok(());
+  }
+
+  // sets the address pointer and returns the data after the address bytes
+  fn set_pointer<'a>(&mut self, address: u8, bytes: &'a [u8]) -> &'a [u8] {
+    let (pointer, data) = if self.chip.address_bytes == 1 {
+      ((address as usize & 0b111) << 8 | bytes[0] as usize, &bytes[1..])
+    } else {
+      ((bytes[0] as usize) << 8 | bytes[1] as usize, &bytes[2..])
+    };
+    // unused high address bits are ignored
+    self.pointer = pointer % self.chip.capacity;
+    return data;
+  }
+
+  fn program(&mut self, data: &[u8]) {
+    let page = self.pointer - self.pointer % self.chip.page_size;
+    let offset = self.pointer % self.chip.page_size;
+    for (i, byte) in data.iter().enumerate() {
+      let address = page + (offset + i) % self.chip.page_size;
+      let inverted = self.corrupt.contains(&address);
+      self.memory[address] = if inverted { !byte } else { *byte };
+    }
+    self.busy = self.write_cycle;
+    self.page_writes += 1;
+  }
+}
+
+impl Write for MockEeprom {
+  type Error = MockError;
+
+  fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), MockError> {
+    let mut device = self.device.borrow_mut();
+    device.start(address)?;
+    let data = device.set_pointer(address, bytes);
+    if !data.is_empty() {
+      device.program(data);
+    }
+    return Ok(());
+  }
+}
+
+impl WriteRead for MockEeprom {
+  type Error = MockError;
+
+  // sequential reads wrap around at the end of the memory
+  fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), MockError> {
+    let mut device = self.device.borrow_mut();
+    device.start(address)?;
+    device.set_pointer(address, bytes);
+    for byte in buffer.iter_mut() {
+      *byte = device.memory[device.pointer];
+      device.pointer = (device.pointer + 1) % device.chip.capacity;
+    }
+    return Ok(());
+  }
+}
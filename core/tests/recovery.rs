@@ -0,0 +1,40 @@
+use midi_clock_core::recovery::*;
+use midi_clock_core::statemachine::{RunState, ClockSource, DEFAULT_STATE};
+
+#[test]
+fn snapshot_survives_packing() {
+  let mut state = DEFAULT_STATE;
+  state.bpm = 137;
+  state.running = RunState::PAUSED;
+  state.clock_source = ClockSource::MidiIn;
+
+  let snapshot = Snapshot::from_state(&state);
+  assert!(Snapshot::unpack(snapshot.pack()) == Some(snapshot));
+}
+
+#[test]
+fn random_words_are_rejected() {
+  let word = Snapshot::from_state(&DEFAULT_STATE).pack();
+  assert!(Snapshot::unpack(word ^ 0x0000_0100).is_none());
+  assert!(Snapshot::unpack(0).is_none());
+  assert!(Snapshot::unpack(0xFFFF_FFFF).is_none());
+}
+
+#[test]
+fn restore_continues_tempo_and_transport() {
+  let mut before = DEFAULT_STATE;
+  before.bpm = 96;
+  before.running = RunState::STOPPING;
+  let mut stored = DEFAULT_STATE;
+  stored.clock_divisions = [2, 4];
+
+  let restored = Snapshot::from_state(&before).restore(&stored);
+  assert_eq!(restored.bpm, 96);
+  assert!(restored.running == RunState::STOPPED);
+  assert_eq!(restored.clock_divisions, [2, 4]);
+  assert!(!Snapshot::from_state(&before).needs_restart());
+
+  before.running = RunState::RUNNING;
+  assert!(Snapshot::from_state(&before).restore(&stored).running == RunState::RUNNING);
+  assert!(Snapshot::from_state(&before).needs_restart());
+}
@@ -0,0 +1,239 @@
+use midi_clock_core::shell::*;
+use midi_clock_core::statemachine::{Statemachine, State, ClockSource, RunState, RampMode};
+use midi_clock_core::delays::{OutputOffset};
+use midi_clock_core::diagnostics::{TickStats};
+use midi_clock_core::crash::{CrashReport, FaultCode};
+use midi_clock_core::setlist::{Setlist, Song, SETLIST_LENGTH};
+
+struct MockDevice {
+  stored: Option<State>,
+  songs: Vec<Song>,
+  eeprom: Vec<u8>,
+  stats_reset: bool,
+  crash: Option<CrashReport>
+}
+
+impl Device for MockDevice {
+  const CYCLES_PER_US: u32 = 72;
+
+  fn save(&mut self, state: &State) -> bool {
+    self.stored = Some(*state);
+    return true;
+  }
+
+  fn load(&mut self) -> Option<State> {
+    return self.stored;
+  }
+
+  fn save_song(&mut self, song: &Song) -> bool {
+    self.songs.push(*song);
+    return true;
+  }
+
+  fn read_eeprom(&mut self, address: u16, buffer: &mut [u8]) -> bool {
+    let start = address as usize;
+    if start + buffer.len() > self.eeprom.len() { return false }
+    buffer.copy_from_slice(&self.eeprom[start..start + buffer.len()]);
+    return true;
+  }
+
+  fn stats(&mut self) -> (TickStats, u32) {
+    let mut stats = TickStats::new();
+    stats.add(-144);
+    stats.add(720);
+    return (stats, 3);
+  }
+
+  fn reset_stats(&mut self) {
+    self.stats_reset = true;
+  }
+
+  fn crash_report(&mut self) -> Option<CrashReport> {
+    return self.crash;
+  }
+
+  fn clear_crash_report(&mut self) -> bool {
+    self.crash = None;
+    return true;
+  }
+
+  fn reset(&mut self) {}
+}
+
+fn device() -> MockDevice {
+  return MockDevice { stored: None, songs: Vec::new(), eeprom: (0..40).collect(), stats_reset: false, crash: None };
+}
+
+fn setlist() -> Setlist {
+  let mut songs: [Option<Song>; SETLIST_LENGTH] = [None; SETLIST_LENGTH];
+  songs[0] = Some(Song { index: 0, name: *b"intro   ", bpm: 90, bar_length: 3, divisions: [2, 1], bars: 0 });
+  return Setlist::from_songs(songs);
+}
+
+// runs the lines like they were sent over the uart and returns the output
+fn run(statemachine: &mut Statemachine, device: &mut MockDevice, input: &str) -> String {
+  let mut buffer = LineBuffer::new();
+  let mut out = String::new();
+  for byte in input.bytes() {
+    let result = match buffer.push(byte) {
+      Some(line) => line.and_then(|line| parse(&line)),
+      None => continue
+    };
+    match result.and_then(|command| execute(command, statemachine, device, &mut out)) {
+      Ok(()) => out.push_str("ok\r\n"),
+      Err(error) => { out.push_str("error: "); out.push_str(error.message()); out.push_str("\r\n") }
+    }
+  }
+  return out;
+}
+
+#[test]
+fn parse_commands() {
+  assert_eq!(parse("bpm 128"), Ok(Command::Bpm(128)));
+  assert_eq!(parse("  div 2 4 "), Ok(Command::Division(1, 4)));
+  assert_eq!(parse("preset 3"), Ok(Command::Preset(Some(2))));
+  assert_eq!(parse("preset 0"), Ok(Command::Preset(None)));
+  assert_eq!(parse("eeprom dump 256 64"), Ok(Command::EepromDump(256, 64)));
+  assert_eq!(parse("eeprom dump 0 8192"), Err(ShellError::InvalidArgument));
+  assert_eq!(parse("stats reset"), Ok(Command::StatsReset));
+  assert_eq!(parse("song 2 verse 96 3 1 2 16"),
+    Ok(Command::StoreSong(Song { index: 1, name: *b"verse   ", bpm: 96, bar_length: 3, divisions: [1, 2], bars: 16 })));
+  assert_eq!(parse("song 0 verse 96 3 1 2 16"), Err(ShellError::InvalidArgument));
+  assert_eq!(parse("song 1 intermezzo 96 3 1 2 16"), Err(ShellError::InvalidArgument));
+  assert_eq!(parse("song 1 intro 96 3 1 2"), Err(ShellError::MissingArgument));
+  assert_eq!(parse("save # keep it"), Ok(Command::Save));
+  assert_eq!(parse("bar 3"), Ok(Command::BarLength(3)));
+  assert_eq!(parse("mult 24"), Ok(Command::TriggerMultiplier(24)));
+  assert_eq!(parse("source midi"), Ok(Command::Source(ClockSource::MidiIn)));
+  assert_eq!(parse("quantize on"), Ok(Command::QuantizedStart(true)));
+  assert_eq!(parse("advance off"), Ok(Command::AutoAdvance(false)));
+  assert_eq!(parse("offset 4 -3 2"), Ok(Command::Offset(3, OutputOffset { millis: -3, ticks: 2 })));
+  assert_eq!(parse("ramp 140 8 exp"), Ok(Command::Ramp(140, 8, RampMode::Exponential)));
+  assert_eq!(parse("run pause"), Ok(Command::Run(RunState::PAUSED)));
+
+  assert_eq!(parse("tempo 120"), Err(ShellError::UnknownCommand));
+  assert_eq!(parse("bpm"), Err(ShellError::MissingArgument));
+  assert_eq!(parse("bpm fast"), Err(ShellError::InvalidArgument));
+  assert_eq!(parse("div 0 4"), Err(ShellError::InvalidArgument));
+  assert_eq!(parse("state now"), Err(ShellError::InvalidArgument));
+  assert_eq!(parse("source usb"), Err(ShellError::InvalidArgument));
+  assert_eq!(parse("quantize"), Err(ShellError::MissingArgument));
+  assert_eq!(parse("offset 0 1 1"), Err(ShellError::InvalidArgument));
+  assert_eq!(parse("ramp 140 8"), Err(ShellError::MissingArgument));
+  assert_eq!(parse("run fast"), Err(ShellError::InvalidArgument));
+}
+
+#[test]
+fn line_buffer_handles_backspace_and_long_lines() {
+  let mut buffer = LineBuffer::new();
+  let lines: Vec<_> = b"bpx\x08m 99\r\n\r\n".iter().filter_map(|b| buffer.push(*b)).collect();
+  assert_eq!(lines.len(), 1);
+  assert_eq!(lines[0].as_ref().map(|line| line.as_str()), Ok("bpm 99"));
+
+  let long = [b'x'; LINE_LENGTH + 1];
+  assert!(long.iter().all(|b| buffer.push(*b).is_none()));
+  assert_eq!(buffer.push(b'\n').map(|line| line.map(|_| ())), Some(Err(ShellError::LineTooLong)));
+}
+
+#[test]
+fn commands_change_the_state() {
+  let mut statemachine = Statemachine::new(None, setlist());
+  let mut device = device();
+  statemachine.on_change();
+
+  assert_eq!(run(&mut statemachine, &mut device, "bpm 128\ndiv 2 4\n"), "ok\r\nok\r\n");
+  let state = statemachine.on_change().unwrap();
+  assert_eq!((state.bpm, state.clock_divisions), (128, [1, 4]));
+
+  assert_eq!(run(&mut statemachine, &mut device, "div 1 9\npreset 2\n"), "error: invalid argument\r\nerror: invalid argument\r\n");
+  assert_eq!(run(&mut statemachine, &mut device, "preset 1\n"), "ok\r\n");
+  let state = statemachine.get_state();
+  assert_eq!((state.bpm, state.clock_bar_length, state.song.map(|song| song.index)), (90, 3, Some(0)));
+
+  assert_eq!(run(&mut statemachine, &mut device, "bar 16\nmult 5\noffset 1 60 0\nrun stop\nrun pause\n"),
+    "error: invalid argument\r\nerror: invalid argument\r\nerror: invalid argument\r\nok\r\nerror: invalid argument\r\n");
+  assert!(statemachine.get_state().running == RunState::STOPPED);
+}
+
+#[test]
+fn bpm_is_refused_while_a_ramp_runs() {
+  let mut statemachine = Statemachine::new(None, setlist());
+  let mut device = device();
+  statemachine.control_change(20, 127);
+
+  assert_eq!(run(&mut statemachine, &mut device, "bpm 100\nramp 100 4 linear\n"),
+    "error: tempo ramp running\r\nerror: tempo ramp running\r\n");
+  assert_eq!(statemachine.get_state().bpm, 120);
+}
+
+#[test]
+fn state_output_restores_the_settings() {
+  let mut source = Statemachine::new(None, setlist());
+  let mut device = device();
+  run(&mut source, &mut device, "preset 1\nbpm 140\ndiv 2 8\nmult 2\nsource midi\nquantize on\nadvance on\n\
+    offset 2 -3 1\nramp 150 8 exp\nrun stop\nrun play\n");
+
+  let mut out = String::new();
+  execute(Command::State, &mut source, &mut device, &mut out).unwrap();
+  assert_eq!(out, "song 1 intro 90 3 2 1 0\r\npreset 1\r\nbpm 140\r\ndiv 1 2\r\ndiv 2 8\r\nbar 3\r\nmult 2\r\n\
+    source midi\r\nquantize on\r\nadvance on\r\n\
+    offset 1 0 0\r\noffset 2 -3 1\r\noffset 3 0 0\r\noffset 4 0 0\r\nramp 150 8 exp\r\nrun play\r\n");
+
+  let mut target = Statemachine::new(None, setlist());
+  assert!(!run(&mut target, &mut device, &out).contains("error"));
+  let (a, b) = (source.get_state(), target.get_state());
+  assert_eq!((a.bpm, a.clock_divisions, a.song.map(|s| s.index)), (b.bpm, b.clock_divisions, b.song.map(|s| s.index)));
+  assert_eq!((a.clock_bar_length, a.clock_trigger_multiplier, a.output_offsets), (b.clock_bar_length, b.clock_trigger_multiplier, b.output_offsets));
+  assert!((a.clock_source, a.quantized_start, a.auto_advance) == (b.clock_source, b.quantized_start, b.auto_advance));
+  assert!((a.ramp_target, a.ramp_bars, a.ramp_mode) == (b.ramp_target, b.ramp_bars, b.ramp_mode));
+  assert!(b.running == RunState::ARMED);
+}
+
+#[test]
+fn songs_are_stored_and_printed_with_the_state() {
+  let mut statemachine = Statemachine::new(None, setlist());
+  let mut device = device();
+
+  assert_eq!(run(&mut statemachine, &mut device, "song 3 outro 80 4 1 1 8\nsong 2 verse 400 4 1 1 8\n"),
+    "error: invalid argument\r\nerror: invalid argument\r\n");
+  assert_eq!(run(&mut statemachine, &mut device, "song 2 verse 132 4 2 4 32\npreset 2\n"), "ok\r\nok\r\n");
+  assert_eq!(device.songs.len(), 1);
+  assert_eq!((device.songs[0].index, device.songs[0].name()), (1, "verse   "));
+  assert_eq!(statemachine.get_state().bpm, 132);
+
+  let mut out = String::new();
+  execute(Command::State, &mut statemachine, &mut device, &mut out).unwrap();
+  assert!(out.starts_with("song 1 intro 90 3 2 1 0\r\nsong 2 verse 132 4 2 4 32\r\npreset 2\r\n"));
+}
+
+#[test]
+fn save_load_and_diagnostics() {
+  let mut statemachine = Statemachine::new(None, Setlist::new());
+  let mut device = device();
+
+  assert_eq!(run(&mut statemachine, &mut device, "load\n"), "error: eeprom error\r\n");
+  run(&mut statemachine, &mut device, "bpm 100\nsave\nbpm 150\nload\n");
+  assert_eq!(statemachine.get_state().bpm, 100);
+  assert_eq!(device.stored.map(|state| state.bpm), Some(100));
+
+  let dump = run(&mut statemachine, &mut device, "eeprom dump 16 20\n");
+  assert_eq!(dump, "0010: 10 11 12 13 14 15 16 17 18 19 1a 1b 1c 1d 1e 1f\r\n0020: 20 21 22 23\r\nok\r\n");
+  assert_eq!(run(&mut statemachine, &mut device, "eeprom dump 32 16\n"), "error: eeprom error\r\n");
+
+  let stats = run(&mut statemachine, &mut device, "stats\nstats reset\n");
+  assert!(stats.starts_with("ticks 2\r\nmin -2us\r\nmax 10us\r\nmean 4us\r\n"));
+  assert!(stats.contains("jitter 10us\r\ndropped 3\r\nok\r\nok\r\n"));
+  assert!(device.stats_reset);
+}
+
+#[test]
+fn crash_report_is_printed_until_cleared() {
+  let mut statemachine = Statemachine::new(None, Setlist::new());
+  let mut device = device();
+  let mut report = CrashReport::new(FaultCode::HardFault);
+  report.address = 0x0800_0100;
+  device.crash = Some(report);
+
+  assert_eq!(run(&mut statemachine, &mut device, "crash\ncrash clear\ncrash\n"),
+    "hard fault at 0x08000100\r\nok\r\nok\r\nno crash report\r\nok\r\n");
+}
@@ -0,0 +1,222 @@
+use std::cell::RefCell;
+use std::convert::Infallible;
+use std::rc::Rc;
+
+use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+use embedded_hal::digital::v2::{OutputPin, InputPin};
+
+use midi_clock_core::st7066::*;
+
+const RS: usize = 0;
+const RW: usize = 1;
+const EN: usize = 2;
+const D4: usize = 3;
+const D7: usize = 6;
+
+// levels of the pins and the nibbles the lcd latched on the falling edges of en
+#[derive(Default)]
+struct Bus {
+  levels: [bool; 7],
+  nibbles: Vec<(bool, u8)>, // rs and d7-d4
+  busy_reads: u16, // reads of d7 that see the busy flag, u16::MAX stays busy
+  reads: u16,
+  delays_us: Vec<u32>
+}
+
+type SharedBus = Rc<RefCell<Bus>>;
+
+struct MockPin {
+  bus: SharedBus,
+  index: usize
+}
+
+impl OutputPin for MockPin {
+  type Error = Infallible;
+
+  fn set_low(&mut self) -> Result<(), Infallible> {
+    let mut bus = self.bus.borrow_mut();
+    if self.index == EN && bus.levels[EN] && !bus.levels[RW] {
+      let nibble = (D4..=D7).rev().fold(0, |nibble, pin| nibble << 1 | bus.levels[pin] as u8);
+      let rs = bus.levels[RS];
+      bus.nibbles.push((rs, nibble));
+    }
+    bus.levels[self.index] = false;
+    return Ok(());
+  }
+
+  fn set_high(&mut self) -> Result<(), Infallible> {
+    self.bus.borrow_mut().levels[self.index] = true;
+    return Ok(());
+  }
+}
+
+impl InputPin for MockPin {
+  type Error = Infallible;
+
+  fn is_high(&self) -> Result<bool, Infallible> {
+    let mut bus = self.bus.borrow_mut();
+    if self.index != D7 { return Ok(false) }
+
+    bus.reads += 1;
+    if bus.busy_reads == 0 { return Ok(false) }
+    if bus.busy_reads != u16::MAX {
+      bus.busy_reads -= 1;
+    }
+    return Ok(true);
+  }
+
+  fn is_low(&self) -> Result<bool, Infallible> {
+    return self.is_high().map(|high| !high);
+  }
+}
+
+struct MockDelay {
+  bus: SharedBus
+}
+
+impl DelayUs<u16> for MockDelay {
+  fn delay_us(&mut self, us: u16) {
+    self.bus.borrow_mut().delays_us.push(us as u32);
+  }
+}
+
+impl DelayMs<u16> for MockDelay {
+  fn delay_ms(&mut self, ms: u16) {
+    self.bus.borrow_mut().delays_us.push(ms as u32 * 1000);
+  }
+}
+
+fn pin(bus: &SharedBus, index: usize) -> MockPin {
+  return MockPin { bus: bus.clone(), index: index };
+}
+
+fn setup(geometry: Geometry) -> (ST7066<MockPin, MockPin, MockPin, MockPin, MockPin, MockPin, MockDelay>, SharedBus) {
+  let bus = SharedBus::default();
+  let lcd = ST7066::new(
+    pin(&bus, RS), pin(&bus, EN), pin(&bus, D4), pin(&bus, D4 + 1), pin(&bus, D4 + 2), pin(&bus, D7),
+    MockDelay { bus: bus.clone() }, geometry
+  );
+  return (lcd, bus);
+}
+
+// splits bytes into the nibbles the lcd receives
+fn nibbles(rs: bool, bytes: &[u8]) -> Vec<(bool, u8)> {
+  return bytes.iter().flat_map(|b| vec![(rs, b >> 4), (rs, b & 0x0F)]).collect();
+}
+
+fn take_nibbles(bus: &SharedBus) -> Vec<(bool, u8)> {
+  return std::mem::take(&mut bus.borrow_mut().nibbles);
+}
+
+#[test]
+fn init_switches_to_4bit_and_shows_starting() {
+  let (mut lcd, bus) = setup(GEOMETRY_8X2);
+  lcd.init();
+
+  // three times 8bit mode, then 4bit mode without the lower nibbles
+  let mut expected = vec![(false, 0x3), (false, 0x3), (false, 0x3), (false, 0x2)];
+  // function set 2 rows, display on, increment, clear
+  expected.extend(nibbles(false, &[0x28, 0x0C, 0x06, 0x01]));
+  expected.extend(nibbles(true, b"starting"));
+  // the cursor wraps to the second row
+  expected.extend(nibbles(false, &[0xC0]));
+  assert_eq!(take_nibbles(&bus), expected);
+}
+
+#[test]
+fn clear_and_cursor_moves() {
+  let (mut lcd, bus) = setup(GEOMETRY_8X2);
+  lcd.clear();
+  lcd.return_home();
+  lcd.set_cursor((3, 1));
+  // clamped to the last cell
+  lcd.set_cursor((20, 5));
+  assert_eq!(take_nibbles(&bus), nibbles(false, &[0x01, 0x02, 0xC3, 0xC7]));
+  // clear waits for its execution time
+  assert!(bus.borrow().delays_us.contains(&2000));
+}
+
+#[test]
+fn characters_are_written_as_data() {
+  let (mut lcd, bus) = setup(GEOMETRY_8X2);
+  lcd.set_cursor((6, 0));
+  lcd.write_str("ab");
+  lcd.create_char(1, &[0xFF; 8]);
+
+  let mut expected = nibbles(false, &[0x86]);
+  expected.extend(nibbles(true, b"a"));
+  // wraps after the last column
+  expected.extend(nibbles(true, b"b"));
+  expected.extend(nibbles(false, &[0xC0]));
+  // cgram address of character 1, then the rows cut to 5 pixels
+  expected.extend(nibbles(false, &[0x48]));
+  expected.extend(nibbles(true, &[0x1F; 8]));
+  expected.extend(nibbles(false, &[0x80]));
+  assert_eq!(take_nibbles(&bus), expected);
+}
+
+fn setup_with_rw(busy_reads: u16) -> (ST7066<MockPin, MockPin, MockPin, MockPin, MockPin, MockPin, MockDelay, MockPin>, SharedBus) {
+  let bus = SharedBus::default();
+  bus.borrow_mut().busy_reads = busy_reads;
+  let lcd = ST7066::new_with_rw(
+    pin(&bus, RS), pin(&bus, RW), pin(&bus, EN), pin(&bus, D4), pin(&bus, D4 + 1), pin(&bus, D4 + 2), pin(&bus, D7),
+    MockDelay { bus: bus.clone() }, GEOMETRY_8X2
+  );
+  return (lcd, bus);
+}
+
+#[test]
+fn busy_flag_is_polled_until_it_clears() {
+  let (mut lcd, bus) = setup_with_rw(3);
+  lcd.write_char(b'x');
+
+  assert_eq!(bus.borrow().reads, 4);
+  // no execution time needed
+  assert!(!bus.borrow().delays_us.contains(&40));
+  // reads do not latch nibbles
+  assert_eq!(take_nibbles(&bus), nibbles(true, b"x"));
+  assert!(!bus.borrow().levels[RW]);
+}
+
+#[test]
+fn busy_flag_that_never_clears_falls_back_to_the_execution_time() {
+  let (mut lcd, bus) = setup_with_rw(u16::MAX);
+  lcd.write_char(b'x');
+
+  assert_eq!(bus.borrow().reads, 100);
+  assert!(bus.borrow().delays_us.contains(&40));
+}
+
+#[test]
+fn rows_start_at_the_ddram_addresses_of_the_geometry() {
+  let (mut lcd, bus) = setup(GEOMETRY_16X2);
+  lcd.set_cursor((15, 1));
+  assert_eq!(take_nibbles(&bus), nibbles(false, &[0xCF]));
+
+  // rows 2 and 3 continue rows 0 and 1
+  let (mut lcd, bus) = setup(GEOMETRY_20X4);
+  lcd.set_cursor((0, 2));
+  lcd.set_cursor((19, 3));
+  assert_eq!(take_nibbles(&bus), nibbles(false, &[0x94, 0xE7]));
+
+  let (mut lcd, bus) = setup(GEOMETRY_40X2);
+  lcd.set_cursor((39, 0));
+  lcd.write_char(b'x');
+  let mut expected = nibbles(false, &[0xA7]);
+  expected.extend(nibbles(true, b"x"));
+  expected.extend(nibbles(false, &[0xC0]));
+  assert_eq!(take_nibbles(&bus), expected);
+  assert!(lcd.geometry() == GEOMETRY_40X2);
+}
+
+#[test]
+fn last_row_wraps_to_the_first() {
+  let (mut lcd, bus) = setup(GEOMETRY_20X4);
+  lcd.set_cursor((19, 3));
+  take_nibbles(&bus);
+  lcd.write_char(b'x');
+
+  let mut expected = nibbles(true, b"x");
+  expected.extend(nibbles(false, &[0x80]));
+  assert_eq!(take_nibbles(&bus), expected);
+}
@@ -0,0 +1,218 @@
+use midi_clock_core::statemachine::*;
+use midi_clock_core::events::{Event};
+use midi_clock_core::gestures::{ButtonEvent, BUTTON1_MASK, BUTTON2_MASK, BUTTON4_MASK};
+use midi_clock_core::setlist::{Setlist, Song, SETLIST_LENGTH};
+
+fn press(statemachine: &mut Statemachine, button: u8) {
+  statemachine.on_event(Event::Button(ButtonEvent::Press(button)));
+  statemachine.on_event(Event::Button(ButtonEvent::Release(button)));
+}
+
+fn song(index: u8, bpm: u16, bars: u8) -> Song {
+  return Song { index: index, name: *b"song    ", bpm: bpm, bar_length: 4, divisions: [1, 2], bars: bars };
+}
+
+#[test]
+fn play_pauses_and_stop_stops() {
+  let mut statemachine = Statemachine::new(None, Setlist::new());
+  assert!(statemachine.on_change().unwrap().running == RunState::RUNNING);
+
+  press(&mut statemachine, BUTTON1_MASK);
+  assert!(statemachine.on_change().unwrap().running == RunState::PAUSED);
+
+  statemachine.on_event(Event::Button(ButtonEvent::Press(BUTTON2_MASK)));
+  assert!(statemachine.on_change().unwrap().running == RunState::STOPPING);
+  statemachine.on_event(Event::Button(ButtonEvent::Release(BUTTON2_MASK)));
+  assert!(statemachine.on_change().unwrap().running == RunState::STOPPED);
+  assert!(statemachine.on_change().is_none());
+}
+
+#[test]
+fn encoder_steps_coarse_while_held() {
+  let mut statemachine = Statemachine::new(None, Setlist::new());
+  statemachine.on_event(Event::EncoderTurn(2, 1));
+  assert_eq!(statemachine.get_state().bpm, 122);
+  statemachine.on_event(Event::EncoderTurn(1, 4));
+  assert_eq!(statemachine.get_state().bpm, 126);
+
+  statemachine.on_event(Event::Button(ButtonEvent::Press(BUTTON4_MASK)));
+  statemachine.on_event(Event::EncoderTurn(-1, 1));
+  statemachine.on_event(Event::Button(ButtonEvent::Release(BUTTON4_MASK)));
+  assert_eq!(statemachine.get_state().bpm, 116);
+  // the held encoder does not switch the page
+  assert!(statemachine.get_state().menu == MenuPage::Bpm);
+
+  statemachine.on_event(Event::EncoderTurn(-100, 8));
+  assert_eq!(statemachine.get_state().bpm, 30);
+}
+
+#[test]
+fn encoder_click_goes_through_the_pages() {
+  let mut statemachine = Statemachine::new(None, Setlist::new());
+  press(&mut statemachine, BUTTON4_MASK);
+  assert!(statemachine.get_state().menu == MenuPage::Song);
+  press(&mut statemachine, BUTTON4_MASK);
+  assert!(statemachine.get_state().menu == MenuPage::Division(0));
+
+  statemachine.on_event(Event::EncoderTurn(3, 1));
+  assert_eq!(statemachine.get_state().clock_divisions, [4, 1]);
+
+  statemachine.on_event(Event::Button(ButtonEvent::LongPress(BUTTON4_MASK)));
+  assert!(statemachine.get_state().menu == MenuPage::Bpm);
+}
+
+#[test]
+fn song_end_advances_to_the_next_song() {
+  let mut songs = [None; SETLIST_LENGTH];
+  songs[0] = Some(song(0, 100, 8));
+  songs[1] = Some(song(1, 140, 16));
+  let mut statemachine = Statemachine::new(None, Setlist::from_songs(songs));
+
+  press(&mut statemachine, BUTTON4_MASK);
+  statemachine.on_event(Event::EncoderTurn(1, 1));
+  assert_eq!(statemachine.on_change().unwrap().bpm, 100);
+
+  statemachine.on_event(Event::SongEnd);
+  assert!(statemachine.on_change().unwrap().running == RunState::STOPPING);
+  let state = statemachine.on_change().unwrap();
+  assert!(state.running == RunState::STOPPED);
+  assert_eq!(state.song.unwrap().index, 1);
+  assert_eq!(state.bpm, 140);
+}
+
+#[test]
+fn play_and_stop_step_through_the_setlist_while_the_encoder_is_held() {
+  let mut songs = [None; SETLIST_LENGTH];
+  songs[0] = Some(song(0, 100, 8));
+  songs[1] = Some(song(1, 140, 16));
+  let mut statemachine = Statemachine::new(None, Setlist::from_songs(songs));
+  let hold = |statemachine: &mut Statemachine, pressed: bool| {
+    let event = if pressed { ButtonEvent::Press(BUTTON4_MASK) } else { ButtonEvent::Release(BUTTON4_MASK) };
+    statemachine.on_event(Event::Button(event));
+  };
+
+  hold(&mut statemachine, true);
+  press(&mut statemachine, BUTTON1_MASK);
+  press(&mut statemachine, BUTTON1_MASK);
+  // stays on the last song
+  press(&mut statemachine, BUTTON1_MASK);
+  let state = statemachine.on_change().unwrap();
+  assert_eq!((state.song.map(|song| song.index), state.bpm), (Some(1), 140));
+  assert!(state.running == RunState::RUNNING);
+
+  // the stop button is released after the encoder
+  statemachine.on_event(Event::Button(ButtonEvent::Press(BUTTON2_MASK)));
+  hold(&mut statemachine, false);
+  statemachine.on_event(Event::Button(ButtonEvent::Release(BUTTON2_MASK)));
+  let state = statemachine.on_change().unwrap();
+  assert_eq!(state.song.map(|song| song.index), Some(0));
+  assert!(state.running == RunState::RUNNING);
+  // the encoder was used as modifier, the menu stays
+  assert!(state.menu == MenuPage::Bpm);
+
+  // without the encoder the buttons play and stop again
+  press(&mut statemachine, BUTTON2_MASK);
+  assert!(statemachine.on_change().unwrap().running == RunState::STOPPED);
+}
+
+#[test]
+fn stored_songs_extend_the_setlist_without_gaps() {
+  let mut statemachine = Statemachine::new(None, Setlist::new());
+  assert!(!statemachine.store_song(song(1, 100, 8)));
+  assert!(!statemachine.store_song(song(0, 400, 8)));
+  assert!(!statemachine.store_song(Song { divisions: [1, 9], ..song(0, 100, 8) }));
+  assert!(statemachine.store_song(song(0, 100, 8)));
+  assert!(statemachine.store_song(song(1, 120, 8)));
+  assert_eq!(statemachine.setlist().len(), 2);
+
+  // the selected song gets the new settings, its tempo stays until it gets selected again
+  assert!(statemachine.select_preset(Some(1)));
+  assert!(statemachine.store_song(song(1, 150, 8)));
+  let state = statemachine.get_state();
+  assert_eq!((state.song.map(|song| song.bpm), state.bpm), (Some(150), 120));
+}
+
+#[test]
+fn fast_turns_stop_at_the_last_song() {
+  let mut songs = [None; SETLIST_LENGTH];
+  songs[0] = Some(song(0, 100, 8));
+  songs[1] = Some(song(1, 140, 16));
+  let mut statemachine = Statemachine::new(None, Setlist::from_songs(songs));
+
+  press(&mut statemachine, BUTTON4_MASK);
+  statemachine.on_event(Event::EncoderTurn(1, 1));
+  // 256 songs further would wrap around to the first song as u8
+  statemachine.on_event(Event::EncoderTurn(256, 1));
+  assert_eq!(statemachine.get_state().song.map(|song| song.index), Some(1));
+  statemachine.on_event(Event::EncoderTurn(i16::MAX, 4));
+  assert_eq!(statemachine.get_state().song.map(|song| song.index), Some(1));
+}
+
+#[test]
+fn songs_survive_the_memory_format() {
+  let song = song(3, 123, 32);
+  assert!(Song::from_bytes(3, &song.to_bytes()) == Some(song));
+  assert!(Song::from_bytes(0, &[0xFF; 16]).is_none());
+}
+
+#[test]
+fn armed_clock_starts_when_the_source_goes_back_to_internal() {
+  let state = State { clock_source: ClockSource::MidiIn, quantized_start: true, running: RunState::STOPPED, menu: MenuPage::ClockSource, ..DEFAULT_STATE };
+  let mut statemachine = Statemachine::new(Some(state), Setlist::new());
+  press(&mut statemachine, BUTTON1_MASK);
+  assert!(statemachine.on_change().unwrap().running == RunState::ARMED);
+
+  statemachine.on_event(Event::EncoderTurn(1, 1));
+  let state = statemachine.on_change().unwrap();
+  assert!(state.clock_source == ClockSource::Internal);
+  assert!(state.running == RunState::RUNNING);
+}
+
+#[test]
+fn ramps_only_run_on_the_internal_clock() {
+  let state = State { clock_source: ClockSource::MidiIn, menu: MenuPage::Ramp, ..DEFAULT_STATE };
+  let mut statemachine = Statemachine::new(Some(state), Setlist::new());
+  statemachine.on_event(Event::EncoderTurn(1, 1));
+  assert!(!statemachine.get_state().ramping);
+  statemachine.on_event(Event::ControlChange(20, 127)); // ramp controller
+  assert!(!statemachine.get_state().ramping);
+
+  let state = State { menu: MenuPage::ClockSource, ramping: true, ..DEFAULT_STATE };
+  let mut statemachine = Statemachine::new(Some(state), Setlist::new());
+  statemachine.on_event(Event::EncoderTurn(1, 1));
+  assert!(!statemachine.get_state().ramping);
+}
+
+#[test]
+fn run_state_can_be_set_without_the_buttons() {
+  let mut statemachine = Statemachine::new(None, Setlist::new());
+  assert!(statemachine.set_running(RunState::STOPPED));
+  assert!(!statemachine.set_running(RunState::PAUSED));
+  assert!(statemachine.get_state().running == RunState::STOPPED);
+
+  // play arms the clock while it follows an external clock with quantized start
+  statemachine.set_source(ClockSource::MidiIn);
+  statemachine.set_quantized_start(true);
+  assert!(statemachine.set_running(RunState::RUNNING));
+  assert!(statemachine.get_state().running == RunState::ARMED);
+  assert!(statemachine.set_running(RunState::PAUSED));
+  assert!(statemachine.get_state().running == RunState::PAUSED);
+}
+
+#[test]
+fn settings_outside_their_range_are_refused() {
+  let mut statemachine = Statemachine::new(None, Setlist::new());
+  assert!(!statemachine.set_bar_length(16));
+  assert!(!statemachine.set_trigger_multiplier(5));
+  assert!(statemachine.set_trigger_multiplier(24));
+  assert!(!statemachine.set_ramp(400, 4, RampMode::Linear));
+  assert!(!statemachine.set_ramp(140, 0, RampMode::Linear));
+  assert!(statemachine.set_ramp(140, 8, RampMode::Exponential));
+
+  // the bpm and the ramp settings belong to a running ramp
+  statemachine.on_event(Event::ControlChange(20, 127));
+  assert!(!statemachine.set_bpm(100));
+  assert!(!statemachine.set_ramp(100, 8, RampMode::Linear));
+  let state = statemachine.get_state();
+  assert_eq!((state.bpm, state.ramp_target, state.clock_trigger_multiplier), (120, 140, 24));
+}
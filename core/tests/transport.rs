@@ -0,0 +1,185 @@
+use midi_clock_core::clock::{MIDI1_MASK, MIDI2_MASK, TRIGGER4_MASK};
+use midi_clock_core::delays::{OutputOffset, ZERO_OFFSET};
+use midi_clock_core::events::{Event};
+use midi_clock_core::midi::{MidiMessage};
+use midi_clock_core::statemachine::{State, RunState, ClockSource, DEFAULT_STATE};
+use midi_clock_core::transport::*;
+
+// records everything the transport asks the timers and outputs to do
+struct MockHardware {
+  interval: u32,
+  timer_running: bool,
+  midi: Vec<(u8, u8)>,
+  fired: Vec<u8>,
+  events: Vec<Event>
+}
+
+impl ClockHardware for MockHardware {
+  fn set_interval(&mut self, us: u32) {
+    self.interval = us;
+  }
+
+  fn set_timer_running(&mut self, running: bool) {
+    self.timer_running = running;
+  }
+
+  fn send_midi(&mut self, port: u8, byte: u8) {
+    self.midi.push((port, byte));
+  }
+
+  fn fire(&mut self, triggers: u8) {
+    self.fired.push(triggers);
+  }
+
+  fn post(&mut self, event: Event) {
+    self.events.push(event);
+  }
+}
+
+fn setup(state: &State) -> (Transport, MockHardware) {
+  let mut hw = MockHardware { interval: 0, timer_running: false, midi: Vec::new(), fired: Vec::new(), events: Vec::new() };
+  let mut transport = Transport::new();
+  transport.init(state, &mut hw);
+  return (transport, hw);
+}
+
+fn clock_ticks(hw: &MockHardware, port: u8) -> usize {
+  return hw.midi.iter().filter(|m| **m == (port, MidiMessage::TimingClock as u8)).count();
+}
+
+#[test]
+fn internal_clock_ticks_with_the_timer() {
+  let (mut transport, mut hw) = setup(&DEFAULT_STATE);
+  assert!(hw.timer_running);
+  assert_eq!(hw.interval, 60 * 1000 * 1000 / (120 * 24));
+
+  // the 25th tick starts the second beat
+  for _ in 0..25 {
+    transport.on_timer_tick(&mut hw);
+  }
+  assert_eq!(clock_ticks(&hw, 1), 25);
+  assert_eq!(clock_ticks(&hw, 2), 25);
+  assert_eq!(transport.position().beat, 2);
+}
+
+#[test]
+fn stop_sends_start_and_the_reset_trigger() {
+  let (mut transport, mut hw) = setup(&DEFAULT_STATE);
+  let stopping = State { running: RunState::STOPPING, ..DEFAULT_STATE };
+  let stopped = State { running: RunState::STOPPED, ..DEFAULT_STATE };
+  transport.on_state_change(&DEFAULT_STATE, &stopping, &mut hw);
+  transport.on_state_change(&stopping, &stopped, &mut hw);
+
+  assert!(!hw.timer_running);
+  assert_eq!(hw.midi, vec![(2, MidiMessage::Stop as u8), (2, MidiMessage::Start as u8)]);
+  assert_eq!(hw.fired, vec![TRIGGER4_MASK]);
+}
+
+#[test]
+fn stop_without_the_stopping_state_stops_the_followers_first() {
+  let (mut transport, mut hw) = setup(&DEFAULT_STATE);
+  let stopped = State { running: RunState::STOPPED, ..DEFAULT_STATE };
+  transport.on_state_change(&DEFAULT_STATE, &stopped, &mut hw);
+
+  assert!(!hw.timer_running);
+  assert_eq!(hw.midi, vec![(2, MidiMessage::Stop as u8), (2, MidiMessage::Start as u8)]);
+  assert_eq!(hw.fired, vec![TRIGGER4_MASK]);
+}
+
+#[test]
+fn armed_clock_starts_on_the_next_bar_of_the_external_clock() {
+  let external = State { clock_source: ClockSource::MidiIn, running: RunState::STOPPED, ..DEFAULT_STATE };
+  let (mut transport, mut hw) = setup(&external);
+  transport.on_external_start();
+  for _ in 0..5 {
+    transport.on_external_tick(&mut hw);
+  }
+
+  let armed = State { running: RunState::ARMED, ..external };
+  transport.on_state_change(&external, &armed, &mut hw);
+  // the timer ticks are ignored while following
+  transport.on_timer_tick(&mut hw);
+  for _ in 5..96 {
+    transport.on_external_tick(&mut hw);
+  }
+  assert!(hw.midi.is_empty());
+  assert!(hw.events.is_empty());
+
+  // first tick of the second bar
+  transport.on_external_tick(&mut hw);
+  assert_eq!(hw.midi[0], (2, MidiMessage::Start as u8));
+  assert!(hw.events == vec![Event::QuantizedStart]);
+  assert_eq!(clock_ticks(&hw, 1), 1);
+  assert_eq!(hw.fired[0], TRIGGER4_MASK);
+}
+
+#[test]
+fn silence_of_the_external_clock_is_reported_once() {
+  let external = State { clock_source: ClockSource::MidiIn, ..DEFAULT_STATE };
+  let (mut transport, mut hw) = setup(&external);
+  transport.on_external_tick(&mut hw);
+
+  for _ in 0..SYNC_LOSS_TIMEOUT {
+    transport.on_sync_timer_tick(&mut hw);
+  }
+  assert!(hw.events.is_empty());
+  for _ in 0..SYNC_LOSS_TIMEOUT {
+    transport.on_sync_timer_tick(&mut hw);
+  }
+  assert!(hw.events == vec![Event::SyncLost]);
+}
+
+#[test]
+fn delayed_outputs_are_sent_by_the_base_ticks() {
+  let mut offsets = [ZERO_OFFSET; 4];
+  offsets[1] = OutputOffset { millis: 2, ticks: 0 };
+  let (mut transport, mut hw) = setup(&State { output_offsets: offsets, ..DEFAULT_STATE });
+
+  transport.on_timer_tick(&mut hw);
+  assert_eq!(clock_ticks(&hw, 1), 1);
+  assert_eq!(clock_ticks(&hw, 2), 0);
+
+  // 2ms are 40 base ticks of 50us
+  for _ in 0..39 {
+    transport.on_base_tick(&mut hw);
+  }
+  assert_eq!(clock_ticks(&hw, 2), 0);
+  transport.on_base_tick(&mut hw);
+  assert_eq!(clock_ticks(&hw, 2), 1);
+  assert_eq!(transport.output_activity() & (MIDI1_MASK | MIDI2_MASK), MIDI1_MASK | MIDI2_MASK);
+}
+
+#[test]
+fn armed_clock_starts_when_the_source_goes_back_to_internal() {
+  let armed = State { clock_source: ClockSource::MidiIn, running: RunState::ARMED, ..DEFAULT_STATE };
+  let (mut transport, mut hw) = setup(&armed);
+  let internal = State { clock_source: ClockSource::Internal, running: RunState::RUNNING, ..armed };
+  transport.on_state_change(&armed, &internal, &mut hw);
+
+  assert!(hw.timer_running);
+  assert_eq!(hw.midi, vec![(2, MidiMessage::Start as u8)]);
+  assert_eq!(hw.fired, vec![TRIGGER4_MASK]);
+
+  transport.on_timer_tick(&mut hw);
+  assert_eq!(clock_ticks(&hw, 2), 1);
+  assert_eq!(transport.position().bar, 1);
+}
+
+#[test]
+fn ramp_ends_when_the_source_becomes_external() {
+  let (mut transport, mut hw) = setup(&DEFAULT_STATE);
+  let ramping = State { ramping: true, ramp_target: 140, ..DEFAULT_STATE };
+  transport.on_state_change(&DEFAULT_STATE, &ramping, &mut hw);
+  assert!(transport.ramp_progress().is_some());
+
+  let external = State { clock_source: ClockSource::MidiIn, ramping: false, ..ramping };
+  transport.on_state_change(&ramping, &external, &mut hw);
+  assert!(transport.ramp_progress().is_none());
+  assert!(hw.events == vec![Event::RampEnd(120)]);
+
+  // refused while following
+  hw.events.clear();
+  transport.on_state_change(&external, &State { ramping: true, ..external }, &mut hw);
+  assert!(transport.ramp_progress().is_none());
+  assert!(hw.events == vec![Event::RampEnd(120)]);
+}
@@ -1,104 +1,142 @@
-use crate::peripherals::*;
-use core::sync::atomic::{AtomicU16, Ordering};
+/*
+ * Reads and debounces the buttons in the timer3 interrupt and posts their gestures to the event queue
+ */
+
+use crate::peripherals::{Button1Gpio, Button2Gpio, Button3Gpio, Button4Gpio};
+use core::sync::atomic::{AtomicBool, AtomicU8, AtomicU16, AtomicU32, Ordering};
 use embedded_hal::digital::v2::{InputPin};
 use core::convert::{Infallible};
+use cortex_m::interrupt;
+
+use midi_clock_core::gestures::{GestureDetector, DEFAULT_GESTURE_CONFIG};
+use crate::events::{EventQueue, Event};
+use crate::utils::{CSCell};
 
 // 2 bits per button. can count to 4, can handle 8 buttons
 static BUTTON_DEBOUNCE_COUNTERS: AtomicU16 = AtomicU16::new(0);
 
-pub const BUTTON1_MASK : u8 = 0b00000001;
-pub const BUTTON2_MASK : u8 = 0b00000010;
-pub const BUTTON3_MASK : u8 = 0b00000100;
-pub const BUTTON4_MASK : u8 = 0b00001000;
+// ms since start, used for timing gestures
+static BUTTON_MILLIS: AtomicU32 = AtomicU32::new(0);
+
+// last readings of the pins, and the buttons down after debouncing
+static BUTTON_READINGS: AtomicU8 = AtomicU8::new(0);
+static BUTTON_STATES: AtomicU8 = AtomicU8::new(0);
+
+// gestures are only posted once the main loop runs
+static EVENTS_ENABLED: AtomicBool = AtomicBool::new(false);
+
+static PINS: CSCell<Option<ButtonPins>> = CSCell::new(None);
+static GESTURES: CSCell<GestureDetector> = CSCell::new(GestureDetector::new(DEFAULT_GESTURE_CONFIG));
 
 const TIMER_OVERFLOW_COUNT: u8 = 20;
 
-pub struct Buttons<
-  B1: InputPin<Error=Infallible>,
-  B2: InputPin<Error=Infallible>,
-  B3: InputPin<Error=Infallible>,
-  B4: InputPin<Error=Infallible>
-> {
-  button1: B1,
-  button2: B2,
-  button3: B3,
-  button4: B4
+struct ButtonPins {
+  button1: Button1Gpio,
+  button2: Button2Gpio,
+  button3: Button3Gpio,
+  button4: Button4Gpio
 }
 
-// reads and debounces buttons.
-impl<
-  B1: InputPin<Error=Infallible>,
-  B2: InputPin<Error=Infallible>,
-  B3: InputPin<Error=Infallible>,
-  B4: InputPin<Error=Infallible>
-> Buttons<B1,B2,B3,B4> {
-  pub fn new(
-    button1: B1, 
-    button2: B2, 
-    button3: B3, 
-    button4: B4
-  ) -> Buttons<B1,B2,B3,B4>  {
-    return Buttons {
-      button1: button1,
-      button2: button2,
-      button3: button3,
-      button4: button4
-    }
+impl ButtonPins {
+  fn read(&self) -> Result<u8, Infallible> {
+    let readings = self.button1.is_low()? as u8
+    | (self.button2.is_low()? as u8) << 1
+    | (self.button3.is_low()? as u8) << 2
+    | (self.button4.is_low()? as u8) << 3;
+    return Ok(readings);
+  }
+}
+
+pub struct Buttons;
+impl Buttons {
+  pub fn init(button1: Button1Gpio, button2: Button2Gpio, button3: Button3Gpio, button4: Button4Gpio) {
+    let pins = ButtonPins { button1: button1, button2: button2, button3: button3, button4: button4 };
+    interrupt::free(|cs| PINS.set(Some(pins), cs));
+  }
+
+  pub fn millis() -> u32 {
+    return BUTTON_MILLIS.load(Ordering::Relaxed);
+  }
+
+  // buttons held right now, not debounced
+  pub fn held() -> u8 {
+    return interrupt::free(|cs| PINS.get(cs).as_ref().map_or(0, |pins| pins.read().unwrap_or(0)));
+  }
+
+  // buttons held after debouncing
+  pub fn debounced() -> u8 {
+    return BUTTON_STATES.load(Ordering::Relaxed);
   }
 
-  pub fn on_change(&self) -> Option<(u8,u8)>  {
-    static mut BUTTON_STATES: u8 = 0;
-
-    let read_button_pins = || -> Result<u8, Infallible> {
-      let readings = self.button1.is_low()? as u8
-      | (self.button2.is_low()? as u8) << 1
-      | (self.button3.is_low()? as u8) << 2
-      | (self.button4.is_low()? as u8) << 3;
-      return Ok(readings);
-    };
-
-    // read input pins
-    let readings = read_button_pins().ok()?;
-
-    // button state was changed
-    let changes = unsafe { (BUTTON_STATES ^ readings) as u16 };
-    if changes > 0  {
-      // read value from debounce counter
-      let counts = BUTTON_DEBOUNCE_COUNTERS.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| {
+  // starts posting the gestures, buttons held right now only count after their release,
+  // the raw readings, a button held since power up never got debounced as pressed
+  pub fn enable_events() {
+    interrupt::free(|cs| {
+      GESTURES.get(cs).ignore(Buttons::held());
+      EVENTS_ENABLED.store(true, Ordering::Relaxed);
+    });
+  }
+
+  pub unsafe fn on_timer_tick() {
+    static mut OVERFLOWS : u8 = 0;
+
+    let now = BUTTON_MILLIS.fetch_add(1, Ordering::Relaxed).wrapping_add(1);
+
+    if OVERFLOWS > TIMER_OVERFLOW_COUNT {
+      BUTTON_DEBOUNCE_COUNTERS.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| {
         let low = x & 0xFF;
         let high = x >> 8;
 
-        // set debounce counter to zero on the changed bits
-        return Some((low & !changes) | ((high & !changes) << 8));
-      }).unwrap();
-
-      let low = counts & 0xFF;
-      let high = counts >> 8;
-      
-      unsafe { BUTTON_STATES = readings; }
-        // check if both bits are 1
-      if (changes & low & high) > 0 {
-        return Some((changes as u8, readings as u8));
-      }
+        // bitwise vertical increment until count to 4 = 0b11 in each column
+        let increment : u16 = (x | high) | ((!(low | high) | high) << 8);
+        return Some(increment)
+      }).ok();
+      OVERFLOWS = 0;
+    } else {
+      OVERFLOWS += 1;
     }
-    return None;
+
+    interrupt::free(|cs| {
+      let readings = match PINS.get(cs).as_ref().map(|pins| pins.read()) {
+        Some(Ok(readings)) => readings,
+        _ => return
+      };
+      let change = debounce(readings);
+      if !EVENTS_ENABLED.load(Ordering::Relaxed) { return }
+
+      let gestures = GESTURES.get(cs);
+      if let Some((changes, reading)) = change {
+        gestures.on_change(changes, reading, now);
+      }
+      while let Some(event) = gestures.poll(now) {
+        EventQueue::post(Event::Button(event), cs);
+      }
+    });
   }
 }
 
-pub unsafe fn buttons_on_timer_tick() {
-  static mut OVERFLOWS : u8 = 0;
-
-  if OVERFLOWS > TIMER_OVERFLOW_COUNT {
-    BUTTON_DEBOUNCE_COUNTERS.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| {
+// returns the changed buttons and the readings if the changed buttons were stable before
+fn debounce(readings: u8) -> Option<(u8,u8)> {
+  // button state was changed
+  let changes = (BUTTON_READINGS.swap(readings, Ordering::Relaxed) ^ readings) as u16;
+  if changes > 0  {
+    // read value from debounce counter
+    let counts = BUTTON_DEBOUNCE_COUNTERS.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| {
       let low = x & 0xFF;
       let high = x >> 8;
 
-      // bitwise vertical increment until count to 4 = 0b11 in each column
-      let increment : u16 = (x | high) | ((!(low | high) | high) << 8);
-      return Some(increment)
-    }).ok();
-    OVERFLOWS = 0;
-  } else {
-    OVERFLOWS += 1;
+      // set debounce counter to zero on the changed bits
+      return Some((low & !changes) | ((high & !changes) << 8));
+    }).unwrap();
+
+    let low = counts & 0xFF;
+    let high = counts >> 8;
+
+    // check if both bits are 1
+    if (changes & low & high) > 0 {
+      BUTTON_STATES.store(readings, Ordering::Relaxed);
+      return Some((changes as u8, readings));
+    }
   }
-}
\ No newline at end of file
+  return None;
+}
@@ -1,182 +1,123 @@
-use core::sync::atomic::{AtomicU32, Ordering};
-use cortex_m::interrupt::{ CriticalSection };
+/*
+ * Runs the transport of the core on timer2, midi in and the outputs of the context
+ */
 
-use crate::context::{Context};
-use crate::midi::{MidiMessage};
-
-use crate::statemachine::{State, RunState};
-use crate::triggers::{TRIGGER3_MASK, TRIGGER4_MASK};
-
-pub struct Clock {
-  bpm: u16
-}
+use cortex_m::interrupt::{CriticalSection};
 
+use crate::context::{Context};
 use crate::timers::{Timer2};
+use crate::utils::{CSCell};
+use crate::events::{EventQueue, Event};
+use crate::diagnostics::{Diagnostics};
 
-type ClockTickHandler = fn(u8, [bool;2], &CriticalSection);
+use midi_clock_core::statemachine::{State, RunState};
+use midi_clock_core::transport::{Transport, ClockHardware};
 
-const CLOCK_TICKS_PER_QUARTER_NOTE: u32 = 24;
+pub use midi_clock_core::clock::{Position};
 
-static CLOCK_TICK_SETTINGS: AtomicU32 = AtomicU32::new(0);
+// shared by the timer2, timer3 and midi in interrupts and the main loop
+static TRANSPORT: CSCell<Transport> = CSCell::new(Transport::new());
 
-struct ClockSettings {
-  divisions: [u8;2],
-  triggers_ppq: u8,
-  bar_length: u8,
-  reset: bool,
-  sync: bool
+/* Timers and outputs of the clock, only used within a critical section */
+struct Hardware<'a> {
+  cs: &'a CriticalSection
 }
-impl ClockSettings {
-  // pub fn store(divisions: [u8;2], triggers_ppq: u8, bar_length: u8, reset: bool, sync: bool) {
-  pub fn store(s: ClockSettings) {
-    let settings_u32 : u32 =  
-      (s.divisions[0] as u32) | (s.divisions[1] as u32) << 8 |
-      (s.triggers_ppq as u32) << 16 |
-      (s.bar_length as u32) << 24 |
-      (s.reset as u32) << 28 |
-      (s.sync as u32) << 29;
-    CLOCK_TICK_SETTINGS.store(settings_u32, Ordering::Relaxed)
-  }
-
-  pub fn store_reset(reset: bool) {
-    CLOCK_TICK_SETTINGS.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| {
-      return Some((x & !(1 << 28)) | (reset as u32) << 28);
-    }).ok();
-  }
-
-  pub fn read(reset: bool) -> ClockSettings {
-    let settings_u32 = CLOCK_TICK_SETTINGS.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |s| {
-      // set reset bit to zero
-      return if reset { Some(s & !(1 << 28)) } else { Some(s) };
-    }).unwrap();
-    return ClockSettings {
-      divisions: [(settings_u32) as u8, (settings_u32 >> 8) as u8],
-      triggers_ppq: (settings_u32 >> 16) as u8,
-      bar_length: (settings_u32 >> 24 & 0xF) as u8,
-      reset: (settings_u32 >> 28 & 0b1) == 1,
-      sync: (settings_u32 >> 29 & 0b1) == 1,
-    };
+
+impl<'a> ClockHardware for Hardware<'a> {
+  fn set_interval(&mut self, us: u32) {
+    Timer2::set_interval(us);
   }
-}
 
-impl Clock {
-  pub fn new(state: &State) -> Clock {
-    let mut clock = Clock{ bpm: 1 };
-    clock.set_bpm(state.bpm);
-    clock.set_runstate(state.running);
-
-    ClockSettings::store( ClockSettings {
-      divisions: state.clock_divisions, 
-      triggers_ppq: state.clock_trigger_multiplier, 
-      bar_length: state.clock_bar_length, 
-      reset: false, 
-      sync: state.clock_sync
-      }
-    );
+  fn set_timer_running(&mut self, running: bool) {
+    Timer2::set_running(running);
+  }
 
-    Timer2::set_handler(Clock::on_timer_tick);
-    return clock;
+  fn send_midi(&mut self, port: u8, byte: u8) {
+    if port == 1 {
+      Diagnostics::on_midi_tick(self.cs);
+      // the debug feature logs on uart1
+      if cfg!(feature = "debug") { return }
+    }
+    Context::get_instance(self.cs, &|ctx| ctx.serial.send(port, byte));
   }
 
-  pub fn set_divisions(&self, divisions: [u8;2]) {
-    let mut settings = ClockSettings::read(false);
-    settings.divisions = [divisions[0], divisions[1]];
-    ClockSettings::store(settings)
+  fn fire(&mut self, triggers: u8) {
+    Context::get_instance(self.cs, &|ctx| ctx.triggers.fire(triggers));
   }
 
-  pub fn set_trigger_multiplier(&self, multiplier: u8) {
-    let mut settings = ClockSettings::read(false);
-    settings.triggers_ppq = multiplier;
-    ClockSettings::store(settings);
+  fn post(&mut self, event: Event) {
+    EventQueue::post(event, self.cs);
   }
 
-  pub fn set_bar_length(&self, bar_length: u8) {
-    let mut settings = ClockSettings::read(false);
-    settings.bar_length = bar_length;
-    ClockSettings::store(settings);
+  fn expect_bpm(&mut self, bpm: Option<f32>) {
+    match bpm {
+      Some(bpm) => Diagnostics::set_bpm(bpm),
+      None => Diagnostics::set_external()
+    }
   }
 
-  pub fn sync(&self, sync: bool) {
-    let mut settings = ClockSettings::read(false);
-    settings.sync = sync;
-    ClockSettings::store(settings);
+  fn restart_stats(&mut self, division: u8) {
+    Diagnostics::set_division(division);
+    Diagnostics::restart();
   }
+}
 
-  pub fn set_bpm(&mut self, bpm: u16) {
-    self.bpm = bpm;
+fn with_transport<T>(f: impl FnOnce(&mut Transport, &mut Hardware) -> T) -> T {
+  return cortex_m::interrupt::free(|cs| f(TRANSPORT.get(cs), &mut Hardware { cs: cs }));
+}
+
+pub struct Clock;
 
-    // sends 24 triggers for every quarternote
-    let intervall_in_us : u32 = 60 * 1000 * 1000 / ((self.bpm as u32) * CLOCK_TICKS_PER_QUARTER_NOTE);
-    Timer2::set_interval(intervall_in_us);
+impl Clock {
+  pub fn new(state: &State) -> Clock {
+    with_transport(|transport, hw| transport.init(state, hw));
+    Timer2::set_handler(Clock::on_timer_tick);
+    Timer2::set_base_handler(Clock::on_base_tick);
+    return Clock;
   }
 
-  pub fn set_runstate(&mut self, running: RunState) {
-    match running {
-      RunState::RUNNING => {
-        Timer2::set_running(true);
-      },
-      RunState::STOPPED => {
-        ClockSettings::store_reset(true);
-        Timer2::set_running(false);
-      },
-      _ => {
-        Timer2::set_running(false);
-      }
-    }
+  pub fn on_state_change(&mut self, prev: &State, state: &State) {
+    with_transport(|transport, hw| transport.on_state_change(prev, state, hw));
   }
 
-  pub unsafe fn on_timer_tick(cs : &CriticalSection) {
-    static mut OVERFLOWS : u32 = 0;
-    static mut SYNC : bool = false;
+  // rewinds the followers together with the clock
+  pub fn restart(&mut self, running: RunState) {
+    with_transport(|transport, hw| transport.restart(running, hw));
+  }
 
-    let csettings = ClockSettings::read(true);
+  // returns current bpm and progress in percent of a running ramp
+  pub fn ramp_progress(&self) -> Option<(u16, u8)> {
+    return with_transport(|transport, _| transport.ramp_progress());
+  }
 
-    SYNC = SYNC || csettings.sync;
+  pub fn position(&self) -> Position {
+    return with_transport(|transport, _| transport.position());
+  }
 
-    // reset Clock
-    OVERFLOWS = if csettings.reset { 0 } else { OVERFLOWS };
+  // returns the masks of the outputs that ticked since the last call
+  pub fn output_activity(&self) -> u8 {
+    return with_transport(|transport, _| transport.output_activity());
+  }
 
-    let mut triggers: u8 = 0;
-    let mut midi_outs = [ false, false ];
-    
-    // handle the two midi channels
-    for i in 0..2 {
-      if OVERFLOWS % (csettings.divisions[i] as u32 * CLOCK_TICKS_PER_QUARTER_NOTE) == 0 {
-        triggers |= 1 << i;
-      }
-      if OVERFLOWS % csettings.divisions[i] as u32 == 0 {
-        midi_outs[i] = true
-      }
-    }
+  pub unsafe fn on_timer_tick(cs: &CriticalSection) {
+    TRANSPORT.get(cs).on_timer_tick(&mut Hardware { cs: cs });
+  }
 
-    // handle the trigger out
-    if OVERFLOWS % (CLOCK_TICKS_PER_QUARTER_NOTE/csettings.triggers_ppq as u32) == 0 {
-      triggers |= TRIGGER3_MASK;
-    }
+  unsafe fn on_base_tick(cs: &CriticalSection) {
+    TRANSPORT.get(cs).on_base_tick(&mut Hardware { cs: cs });
+  }
 
-    // handle reset out after 4 quarter notes
-    if SYNC && OVERFLOWS % (CLOCK_TICKS_PER_QUARTER_NOTE * csettings.bar_length as u32) == 0 {
-      SYNC = false;
-      triggers |= TRIGGER4_MASK;
-    }
+  // gets called on every midi clock message received
+  pub fn on_external_tick(cs: &CriticalSection) {
+    TRANSPORT.get(cs).on_external_tick(&mut Hardware { cs: cs });
+  }
 
-    on_clock_tick(triggers, midi_outs, cs); 
+  pub fn on_external_start(cs: &CriticalSection) {
+    TRANSPORT.get(cs).on_external_start();
+  }
 
-    // reset overflows when reached largest common multiple of all possible divisors and 24
-    OVERFLOWS = (OVERFLOWS + 1) % 806400; 
+  // gets called every ms by timer3, watches the midi clock messages of the external clock
+  pub unsafe fn on_sync_timer_tick() {
+    with_transport(|transport, hw| transport.on_sync_timer_tick(hw));
   }
 }
-
-pub fn on_clock_tick(trigger_ticks: u8, midi_tick: [bool;2], cs: &CriticalSection) {
-
-  Context::get_instance(cs, &|ctx| {
-    if midi_tick[0] {
-      #[cfg(not(feature = "debug"))]
-      ctx.serial.write(1, MidiMessage::TimingClock as u8).ok();
-    }
-    if midi_tick[1] {
-      ctx.serial.write(2, MidiMessage::TimingClock as u8).ok();
-    }
-    ctx.triggers.fire(trigger_ticks);
-  });
-}
\ No newline at end of file
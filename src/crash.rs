@@ -0,0 +1,62 @@
+/*
+ * Records panics and hard faults and reboots through the watchdog
+ *
+ * The i2c bus may be the cause of the fault, so the report waits in ram that is not cleared on reset
+ * and the next boot moves it to the eeprom.
+ */
+
+use core::mem::MaybeUninit;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, Ordering};
+use stm32f1xx_hal::{
+  prelude::*,
+  pac,
+  watchdog::{IndependentWatchdog}
+};
+
+use midi_clock_core::crash::{CrashReport, CRASH_REPORT_SIZE};
+
+// tells a report apart from the random content of the ram after power up
+const PENDING_MAGIC: u32 = 0x4352_5348;
+
+#[link_section = ".uninit.CRASH_MAGIC"]
+static mut MAGIC: MaybeUninit<u32> = MaybeUninit::uninit();
+#[link_section = ".uninit.CRASH_REPORT"]
+static mut PENDING: MaybeUninit<[u8; CRASH_REPORT_SIZE]> = MaybeUninit::uninit();
+
+static FAULTED: AtomicBool = AtomicBool::new(false);
+
+pub struct Crash;
+impl Crash {
+  // false if the clock faults again while it reports a fault, then it only records it
+  pub fn first_fault() -> bool {
+    return !FAULTED.swap(true, Ordering::Relaxed);
+  }
+
+  // keeps the report for the next boot and reboots, the clock stops in any case
+  pub fn record(report: &CrashReport) -> ! {
+    unsafe {
+      PENDING = MaybeUninit::new(report.to_bytes());
+      MAGIC = MaybeUninit::new(PENDING_MAGIC);
+    }
+    Crash::reboot();
+  }
+
+  // lets the watchdog reset the chip, also works if the watchdog was started before
+  pub fn reboot() -> ! {
+    cortex_m::interrupt::disable();
+    let iwdg = unsafe { pac::Peripherals::steal().IWDG };
+    IndependentWatchdog::new(iwdg).start(1.ms());
+    loop {}
+  }
+
+  // returns the report of a crash right before this boot
+  pub fn take_pending() -> Option<CrashReport> {
+    // volatile, the compiler can not know the content from before the reset
+    unsafe {
+      if ptr::addr_of!(MAGIC).read_volatile().assume_init() != PENDING_MAGIC { return None }
+      MAGIC = MaybeUninit::new(0);
+      return CrashReport::from_bytes(&ptr::addr_of!(PENDING).read_volatile().assume_init());
+    }
+  }
+}
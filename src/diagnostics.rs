@@ -0,0 +1,89 @@
+/*
+ * Measures the intervals of the midi clock ticks with the cycle counter of the cpu
+ */
+
+use core::sync::atomic::{AtomicU32, AtomicU8, Ordering};
+use cortex_m::interrupt::{ CriticalSection };
+use cortex_m::peripheral::{DWT};
+
+use midi_clock_core::diagnostics::{TickStats};
+use crate::utils::{CSCell};
+
+// cpu cycles per us at 72mhz sysclk
+pub const CYCLES_PER_US: u32 = 72;
+
+// cycles per minute divided by 24 ticks per quarter note
+const CYCLES_PER_TICK_AT_1_BPM: f32 = 72_000_000.0 * 60.0 / 24.0;
+
+static STATS: CSCell<TickStats> = CSCell::new(TickStats::new());
+
+// cycle count of the previous midi tick, none after the clock was restarted
+static LAST_TICK: CSCell<Option<u32>> = CSCell::new(None);
+
+// ideal cycles between two clock ticks, 0 while following an external clock
+static IDEAL_PERIOD: AtomicU32 = AtomicU32::new(0);
+static DIVISION: AtomicU8 = AtomicU8::new(1);
+
+// bytes that did not fit into the transmit register
+static DROPPED_BYTES: AtomicU32 = AtomicU32::new(0);
+
+pub struct Diagnostics;
+impl Diagnostics {
+  pub fn set_bpm(bpm: f32) {
+    IDEAL_PERIOD.store((CYCLES_PER_TICK_AT_1_BPM / bpm) as u32, Ordering::Relaxed);
+  }
+
+  pub fn set_external() {
+    IDEAL_PERIOD.store(0, Ordering::Relaxed);
+  }
+
+  // division of midi out1+2, the measured port
+  pub fn set_division(division: u8) {
+    DIVISION.store(division.max(1), Ordering::Relaxed);
+  }
+
+  // the next interval starts from scratch, e.g. after the clock was stopped
+  pub fn restart() {
+    cortex_m::interrupt::free(|cs| LAST_TICK.set(None, cs));
+  }
+
+  pub fn reset() {
+    cortex_m::interrupt::free(|cs| {
+      STATS.set(TickStats::new(), cs);
+      LAST_TICK.set(None, cs);
+    });
+    DROPPED_BYTES.store(0, Ordering::Relaxed);
+  }
+
+  pub fn stats() -> TickStats {
+    return cortex_m::interrupt::free(|cs| *STATS.get(cs));
+  }
+
+  pub fn dropped() -> u32 {
+    return DROPPED_BYTES.load(Ordering::Relaxed);
+  }
+
+  pub fn count_dropped() {
+    DROPPED_BYTES.fetch_add(1, Ordering::Relaxed);
+  }
+
+  // largest deviation from the ideal interval in us
+  pub fn jitter_us() -> u32 {
+    return Diagnostics::stats().jitter() / CYCLES_PER_US;
+  }
+
+  // gets called when midi out1+2 sends a clock tick
+  pub fn on_midi_tick(cs: &CriticalSection) {
+    let now = DWT::get_cycle_count();
+    let last = LAST_TICK.get(cs).replace(now);
+
+    let ideal = IDEAL_PERIOD.load(Ordering::Relaxed) as i64 * DIVISION.load(Ordering::Relaxed) as i64;
+    if ideal == 0 { return }
+
+    // cycle counter wraps after ~60s, which is longer than any tick interval
+    last.map(|last| {
+      let deviation = now.wrapping_sub(last) as i64 - ideal;
+      STATS.get(cs).add(deviation.max(i32::MIN as i64).min(i32::MAX as i64) as i32);
+    });
+  }
+}
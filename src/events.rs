@@ -0,0 +1,26 @@
+/*
+ * Queue of input events, posted by the interrupts and consumed by the main loop in order
+ */
+
+use cortex_m::interrupt::{CriticalSection};
+use heapless::spsc::{Queue};
+
+use crate::utils::{CSCell};
+
+pub use midi_clock_core::events::{Event};
+
+const EVENT_QUEUE_SIZE: usize = 32;
+
+static EVENTS: CSCell<Queue<Event, EVENT_QUEUE_SIZE>> = CSCell::new(Queue::new());
+
+pub struct EventQueue;
+impl EventQueue {
+  // drops the event if the queue is full, the main loop got stuck then anyway
+  pub fn post(event: Event, cs: &CriticalSection) {
+    EVENTS.get(cs).enqueue(event).ok();
+  }
+
+  pub fn next() -> Option<Event> {
+    return cortex_m::interrupt::free(|cs| EVENTS.get(cs).dequeue());
+  }
+}
@@ -0,0 +1,86 @@
+use stm32f1xx_hal::{
+  delay::{Delay}
+};
+
+use crate::peripherals::{DisplayPins, DisplayLcd, DISPLAY_GEOMETRY};
+use crate::display::{Display, Content, FrameBuffer, update_time_arrived};
+use crate::glyphs::*;
+
+use crate::trace;
+
+use midi_clock_core::st7066::ST7066;
+
+/* Character lcd, shows the pages as text */
+pub struct LcdDisplay {
+  lcd: DisplayLcd,
+  content: Content,
+  frame: FrameBuffer,
+  shown: FrameBuffer, // characters currently on the lcd
+  shown_valid: bool // false if the lcd content is unknown
+}
+
+impl LcdDisplay {
+
+  pub fn new(pins: DisplayPins, delay: Delay) -> LcdDisplay {
+
+    let lcd = ST7066::new(pins.rs, pins.en, pins.d4, pins.d5, pins.d6, pins.d7, delay, DISPLAY_GEOMETRY);
+    let geometry = lcd.geometry();
+
+    return LcdDisplay {
+      lcd: lcd,
+      content: Content::new(),
+      frame: FrameBuffer::new(geometry.columns, geometry.rows),
+      shown: FrameBuffer::new(geometry.columns, geometry.rows),
+      shown_valid: false
+    };
+  }
+
+  // sends only the characters that differ from the lcd content
+  fn flush(&mut self) {
+    let (columns, rows) = (self.frame.columns, self.frame.rows);
+    for row in 0..rows {
+      let mut column = 0;
+      while column < columns {
+        if self.shown_valid && self.frame.cells[row][column] == self.shown.cells[row][column] {
+          column += 1;
+          continue;
+        }
+
+        // the lcd moves its cursor forward after every character
+        self.lcd.set_cursor((column as u8, row as u8));
+        while column < columns && (!self.shown_valid || self.frame.cells[row][column] != self.shown.cells[row][column]) {
+          self.lcd.write_char(self.frame.cells[row][column]);
+          self.shown.cells[row][column] = self.frame.cells[row][column];
+          column += 1;
+        }
+      }
+    }
+    self.shown_valid = true;
+  }
+}
+
+impl Display for LcdDisplay {
+
+  fn content(&mut self) -> &mut Content {
+    return &mut self.content;
+  }
+
+  fn init(&mut self) {
+    self.lcd.init();
+    for (i, glyph) in GLYPHS.iter().enumerate() {
+      self.lcd.create_char(i as u8, glyph);
+    }
+    self.shown_valid = false;
+  }
+
+  fn render(&mut self) {
+    self.content.expire_message();
+    let update_time_arrived = update_time_arrived();
+    if self.content.updated && update_time_arrived {
+      trace!("display render");
+      self.content.render_page(&mut self.frame);
+      self.flush();
+      self.content.updated = false;
+    }
+  }
+}
@@ -0,0 +1,88 @@
+/*
+ * Leveled logging on the debug uart
+ *
+ * The records are formatted in place and sent later by the main loop, so the macros can be used
+ * in interrupts. Levels above MAX_LEVEL are removed by the compiler together with their arguments.
+ */
+
+use midi_clock_core::log::{LogBuffer, LogLine};
+use crate::context::{Context};
+use crate::utils::{CSCell};
+
+pub use midi_clock_core::log::{Level};
+
+const LOG_UART: u8 = 1;
+
+// bytes waiting to be sent, a power of two keeps the queue cheap
+const LOG_BUFFER_SIZE: usize = 256;
+
+// the debug feature logs up to info, the trace feature everything, otherwise uart1 is midi out
+pub const MAX_LEVEL: Option<Level> = if cfg!(feature = "trace") {
+  Some(Level::Trace)
+} else if cfg!(feature = "debug") {
+  Some(Level::Info)
+} else {
+  None
+};
+
+static BUFFER: CSCell<LogBuffer<LOG_BUFFER_SIZE>> = CSCell::new(LogBuffer::new());
+
+#[inline(always)]
+pub fn enabled(level: Level) -> bool {
+  return MAX_LEVEL.map_or(false, |max| max.includes(level));
+}
+
+pub fn write(level: Level, args: core::fmt::Arguments) {
+  let line = LogLine::format(level, args);
+  cortex_m::interrupt::free(|cs| BUFFER.get(cs).push(&line));
+}
+
+pub struct Log;
+impl Log {
+  // sends what the uart takes without waiting, call it from the main loop
+  pub fn flush() {
+    if MAX_LEVEL.is_none() { return }
+    cortex_m::interrupt::free(|cs| {
+      Context::get_instance(cs, &|ctx| {
+        BUFFER.get(cs).flush(|byte| ctx.serial.write(LOG_UART, byte).is_ok());
+      });
+    });
+  }
+
+  // waits until everything is sent, e.g. before other output on the uart or in the panic handler
+  pub fn flush_all() {
+    if MAX_LEVEL.is_none() { return }
+    while !cortex_m::interrupt::free(|cs| BUFFER.get(cs).is_empty()) {
+      Log::flush();
+    }
+  }
+}
+
+#[macro_export]
+macro_rules! log {
+  ($level:expr, $($arg:tt)+) => {
+    if $crate::log::enabled($level) {
+      $crate::log::write($level, format_args!($($arg)+));
+    }
+  }
+}
+
+#[macro_export]
+macro_rules! error {
+  ($($arg:tt)+) => { $crate::log!($crate::log::Level::Error, $($arg)+) }
+}
+
+#[macro_export]
+macro_rules! warn {
+  ($($arg:tt)+) => { $crate::log!($crate::log::Level::Warn, $($arg)+) }
+}
+
+#[macro_export]
+macro_rules! info {
+  ($($arg:tt)+) => { $crate::log!($crate::log::Level::Info, $($arg)+) }
+}
+
+#[macro_export]
+macro_rules! trace {
+  ($($arg:tt)+) => { $crate::log!($crate::log::Level::Trace, $($arg)+) }
+}
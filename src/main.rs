@@ -1,7 +1,7 @@
 #![no_std]
 #![no_main]
 
-use cortex_m_rt::entry;
+use cortex_m_rt::{entry, exception, ExceptionFrame};
 use cortex_m::interrupt;
 use cortex_m::interrupt::{CriticalSection};
 
@@ -14,117 +14,146 @@ mod serial;
 use serial::{SerialWriter};
 
 mod buttons;
-use buttons::{Buttons, BUTTON1_MASK, BUTTON2_MASK, BUTTON3_MASK, BUTTON4_MASK, buttons_on_timer_tick};
+use buttons::{Buttons};
+
+mod events;
+use events::{EventQueue, Event};
 
 mod timers;
 use timers::{Timer3};
 
-mod debug;
+mod log;
+use log::{Log};
 
 mod utils;
 
 mod encoder;
-use encoder::{Encoder};
 
 mod clock;
 use clock::{Clock};
 
 mod triggers;
-use triggers::{Triggers, TRIGGER4_MASK};
+use triggers::{Triggers};
 
-mod statemachine;
-use statemachine::{Statemachine, State, RunState};
+use midi_clock_core::statemachine;
+use statemachine::{Statemachine, State, DEFAULT_STATE};
 
 mod context;
 use context::{Context, CONTEXT};
 
-mod display;
-use display::{Display};
+use midi_clock_core::display;
+use display::{Display, Priority};
+
+#[cfg(not(feature = "tft"))]
+mod lcd_display;
+#[cfg(not(feature = "tft"))]
+use lcd_display::{LcdDisplay};
+
+#[cfg(feature = "tft")]
+mod soft_spi;
+#[cfg(feature = "tft")]
+mod tft_display;
+#[cfg(feature = "tft")]
+use tft_display::{TftDisplay};
 
 mod midi;
-use midi::{MidiMessage};
+use midi::{MidiIn};
 
-mod st7066;
+use midi_clock_core::glyphs;
 
-mod eeprom;
-use eeprom::{Eeprom};
+use midi_clock_core::eeprom::{Eeprom, CHIP_24C64};
 
 mod memory;
 use memory::{Memory};
 
-fn on_button_press(statemachine: &mut Statemachine, changes: u8, state: u8) {
-  if (changes & BUTTON1_MASK) > 0 {
-    statemachine.button1_pressed(changes & BUTTON1_MASK & state > 0);
-  }
-  if (changes & BUTTON2_MASK) > 0 {
-    statemachine.button2_pressed(changes & BUTTON2_MASK & state > 0);
-  }
-  if (changes & BUTTON3_MASK) > 0 {
-    statemachine.button3_pressed(changes & BUTTON3_MASK & state > 0);
+
+mod diagnostics;
+use diagnostics::{Diagnostics};
+use midi_clock_core::diagnostics::{Request, dump_sysex, crash_sysex};
+
+mod crash;
+use crash::{Crash};
+
+mod watchdog;
+use watchdog::{Watchdog};
+use midi_clock_core::crash::{CrashReport, FaultCode};
+
+#[cfg(feature = "shell")]
+mod shell;
+#[cfg(feature = "shell")]
+use shell::{Shell};
+
+use midi_clock_core::setlist;
+use setlist::{Setlist};
+
+use midi_clock_core::boot::{BootMode, Confirmation, safe_state};
+
+// shows messages for events that do not change the state
+fn on_event(event: &Event, display: &mut impl Display, memory: &mut Memory) {
+  match event {
+    Event::RampEnd(_) => display.show_message("ramp done", Priority::Info),
+    Event::SyncLost => display.show_message("MIDI sync lost", Priority::Warning),
+    Event::DiagnosticsRequest(Request::Dump) => dump_diagnostics(),
+    Event::DiagnosticsRequest(Request::Reset) => {
+      Diagnostics::reset();
+      display.show_message("stats reset", Priority::Info);
+    },
+    Event::DiagnosticsRequest(Request::CrashReport) => {
+      let (sysex, length) = crash_sysex(memory.load_crash_report().as_ref());
+      send_sysex(&sysex[..length]);
+    },
+    _ => {}
   }
-  if (changes & BUTTON4_MASK) > 0 {
-    statemachine.encoder_pressed(changes & BUTTON4_MASK & state > 0);
+}
+
+// asks on the display, the clock boots on after the answer
+fn confirm_factory_reset(display: &mut impl Display) -> bool {
+  let mut confirmation = Confirmation::new(Buttons::millis());
+  display.update(&DEFAULT_STATE);
+  loop {
+    if let Some(confirmed) = confirmation.poll(Buttons::debounced(), Buttons::millis()) {
+      // the question would hold off the warnings of the boot
+      display.clear_message();
+      return confirmed;
+    }
+    // the question must not time out
+    display.show_message("reset?  push enc", Priority::Error);
+    display.render();
   }
 }
 
-fn on_encoder_change(statemachine: &mut Statemachine, rotation: i16) {
-  statemachine.encoder_turn(rotation);
+// sends the timing statistics as sysex on midi out3+4 and as text on the debug uart
+fn dump_diagnostics() {
+  let stats = Diagnostics::stats();
+  let dropped = Diagnostics::dropped();
+
+  send_sysex(&dump_sysex(&stats, dropped));
+
+  info!("ticks {} jitter {}us std dev {}us dropped {}", stats.count,
+    stats.jitter() / diagnostics::CYCLES_PER_US, stats.std_dev() / diagnostics::CYCLES_PER_US, dropped);
+}
+
+// clock ticks may be sent in between, realtime messages are allowed within sysex
+fn send_sysex(sysex: &[u8]) {
+  for byte in sysex.iter() {
+    SerialWriter::write_waiting(2, *byte);
+  }
 }
 
-fn on_state_change(state: &State, clock: &mut Clock, display: &mut Display) {
+fn on_state_change(state: &State, clock: &mut Clock, display: &mut impl Display) {
   static mut PREV_STATE : Option<State> = None;
 
-  debug!("state change");
-  debug!(*state);
+  info!("state bpm {} run {} div {},{}", state.bpm, state.running as u8,
+    state.clock_divisions[0], state.clock_divisions[1]);
+  Watchdog::save(state);
 
   if let Some(prev_state) = unsafe { PREV_STATE } {
-    // check for state changes
-    if prev_state.running != state.running {
-      clock.set_runstate(state.running);
-      send_midi_ctrl_msg(state.running);
-    }
-    if prev_state.bpm != state.bpm {
-      clock.set_bpm(state.bpm);
-    }
-    if prev_state.clock_trigger_multiplier != state.clock_trigger_multiplier {
-      clock.set_trigger_multiplier(state.clock_trigger_multiplier);
-    }
-    if prev_state.clock_divisions[0] != state.clock_divisions[0] || prev_state.clock_divisions[1] != state.clock_divisions[1] {
-      clock.set_divisions(state.clock_divisions);
-    }
-    if prev_state.clock_bar_length != state.clock_bar_length {
-      clock.set_bar_length(state.clock_bar_length);
-    } 
-    if prev_state.clock_sync != state.clock_sync {
-      clock.sync(state.clock_sync);
-    }
+    clock.on_state_change(&prev_state, state);
     display.update(state);
   }
   unsafe { PREV_STATE = Some(*state) }
 }
 
-fn send_midi_ctrl_msg(current: RunState) {
-  interrupt::free(|cs| {
-    Context::get_instance(cs, &|ctx| {
-      match current {
-        RunState::RUNNING => { 
-          ctx.serial.write(2, MidiMessage::Continue as u8).ok(); 
-        },
-        RunState::PAUSED => { 
-          ctx.serial.write(2, MidiMessage::Stop as u8).ok(); 
-        },
-        RunState::STOPPING => { 
-          ctx.serial.write(2, MidiMessage::Stop as u8).ok(); 
-        },
-        RunState::STOPPED => { 
-          ctx.serial.write(2, MidiMessage::Start as u8).ok();
-          ctx.triggers.fire(TRIGGER4_MASK); // send sync reset trigger
-        }
-      }
-    });
-  });
-}
-
 #[entry]
 fn main() -> ! {
 
@@ -132,22 +161,55 @@ fn main() -> ! {
   let peripherals = Peripherals::init();
 
   // init eeprom memory chip
-  let mut memory = Memory::new(Eeprom::new(peripherals.i2c1.unwrap()));
+  let mut memory = Memory::new(Eeprom::new(peripherals.i2c1.unwrap(), CHIP_24C64));
+
+  // initializes all buttons and sets debounce timer, the gestures get posted by its interrupt
+  Buttons::init(peripherals.button1.unwrap(), peripherals.button2.unwrap(),
+    peripherals.button3.unwrap(), peripherals.button4.unwrap());
+  Timer3::add_handler(0, Buttons::on_timer_tick);
+
+  // buttons held at power up choose the boot mode
+  let boot_mode = BootMode::from_buttons(Buttons::held());
+
+  // setup display
+  #[cfg(not(feature = "tft"))]
+  let mut display = LcdDisplay::new(peripherals.display.unwrap(), peripherals.delay.unwrap());
+  #[cfg(feature = "tft")]
+  let mut display = TftDisplay::new(peripherals.display.unwrap(), peripherals.delay.unwrap());
+  Timer3::add_handler(1, display::on_timer_tick);
+  display.init();
+
+  if boot_mode == BootMode::FactoryReset && confirm_factory_reset(&mut display) {
+    warn!("factory reset");
+    match memory.factory_reset() {
+      Ok(()) => display.show_message("settings reset", Priority::Warning),
+      Err(_) => display.show_message("reset failed", Priority::Error)
+    }
+  }
+
+  // keep the report of a crash right before this boot
+  let crashed = Crash::take_pending().map(|report| {
+    error!("crashed: {}", report);
+    memory.write_crash_report(&report).ok();
+  }).is_some();
+
+  // safe mode starts like a clock with an empty memory, in case a stored setting breaks it
+  let safe_mode = boot_mode == BootMode::SafeMode;
+
+  // the tempo and transport from before a reboot by the watchdog win over the stored state,
+  // unless a crash caused the reboot, restarting right into it could crash again
+  let recovered = Watchdog::restore(peripherals.reset_by_watchdog).filter(|_| !safe_mode && !crashed);
 
   // initialize statemachine and read state from memory
-  let mut statemachine = Statemachine::new(memory.load_state());
+  let stored_state = if safe_mode { Some(safe_state()) } else { memory.load_state() };
+  let restored_state = recovered.map(|snapshot| snapshot.restore(&stored_state.unwrap_or(DEFAULT_STATE)));
+  let setlist = if safe_mode { Setlist::new() } else { memory.load_setlist() };
+  let mut statemachine = Statemachine::new(restored_state.or(stored_state), setlist);
   let initial_state = statemachine.get_state();
 
-  // initializes all buttons and sets debounce timer
-  let buttons = Buttons::new(peripherals.button1.unwrap(), peripherals.button2.unwrap(), 
-    peripherals.button3.unwrap(), peripherals.button4.unwrap());
-  Timer3::add_handler(0, buttons_on_timer_tick);
-
   // initialize clock, sends triggers and MIDI CLOCK msgs in regular intervals
   let mut clock = Clock::new(&initial_state);
-  
-  // initialize rotary encoder
-  let encoder = Encoder::new();
+  Timer3::add_handler(3, Clock::on_sync_timer_tick);
 
   // create global context to share peripherals among interrupts
   {
@@ -164,39 +226,91 @@ fn main() -> ! {
       let context = Context { triggers: triggers, serial: serial };
       CONTEXT.borrow(cs).replace(Some(context));
     });
+
+    // start receiving midi in, needs the serial of the context
+    MidiIn::init();
+  }
+
+  // followers lost their position with the clock, rewind them together with the clock
+  if recovered.map_or(false, |snapshot| snapshot.needs_restart()) {
+    warn!("recovered from reboot");
+    clock.restart(initial_state.running);
   }
 
-  // setup display
-  let mut display = Display::new(peripherals.display.unwrap(), peripherals.delay.unwrap());
-  Timer3::add_handler(1, Display::on_timer_tick);
-  display.init();
   display.update(&initial_state);
+  if safe_mode {
+    warn!("safe mode, firmware {}", env!("CARGO_PKG_VERSION"));
+    display.show_message(concat!("safe    v", env!("CARGO_PKG_VERSION")), Priority::Warning);
+  }
+  if stored_state.is_none() {
+    display.show_message("EEPROM error", Priority::Error);
+  }
+  if recovered.is_some() {
+    display.show_message("recovered", Priority::Warning);
+  }
+  if crashed {
+    display.show_message("crashed: see log", Priority::Error);
+  }
+
+  #[cfg(feature = "shell")]
+  let mut shell = Shell::new();
 
-  debug!("start");
+  // init may take longer than the timeout, e.g. the tft
+  let mut watchdog = Watchdog::start(peripherals.watchdog.unwrap());
+
+  // the buttons held at power up must not reach the statemachine, play would start the clock
+  Buttons::enable_events();
+
+  info!("start");
   
   // main loop
   loop {
-    buttons.on_change().map(|(changes, reading)| {
-      on_button_press(&mut statemachine, changes, reading);
-    });
-    encoder.on_change().map(|rotation| {
-      on_encoder_change(&mut statemachine, rotation);
-    });
+    // every event gets its own state change, so no edges get merged
+    while let Some(event) = EventQueue::next() {
+      on_event(&event, &mut display, &mut memory);
+      statemachine.on_event(event);
+      statemachine.on_change().map(|state| {
+        on_state_change(&state, &mut clock, &mut display);
+      });
+    }
+    #[cfg(feature = "shell")]
+    shell.poll(&mut statemachine, &mut memory);
+    // changes are not written to the eeprom, the settings get stored by the save command of the shell
     statemachine.on_change().map(|state| {
       on_state_change(&state, &mut clock, &mut display);
-      // memory.write_state(&state).ok();
     });
+    display.update_ramp(clock.ramp_progress());
+    display.update_position(clock.position());
+    display.update_activity(clock.output_activity());
+    display.update_diagnostics(Diagnostics::jitter_us(), Diagnostics::dropped());
     display.render();
+    Log::flush();
+    watchdog.feed();
   }
 }
 
 // Call this function when panic occurs
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
-  debug!("PANIC");
-  if let Some(s) = info.payload().downcast_ref::<&str>() {
-    debug!(*s);
+  if Crash::first_fault() {
+    error!("{}", info);
+    Log::flush_all();
   }
-  
-  loop {}
+
+  let mut report = CrashReport::new(FaultCode::Panic);
+  info.location().map(|location| report.set_location(location.file(), location.line()));
+  report.set_message(format_args!("{}", info.message()));
+  Crash::record(&report);
+}
+
+#[exception]
+unsafe fn HardFault(frame: &ExceptionFrame) -> ! {
+  if Crash::first_fault() {
+    error!("hard fault at {:#010x}", frame.pc);
+    Log::flush_all();
+  }
+
+  let mut report = CrashReport::new(FaultCode::HardFault);
+  report.address = frame.pc;
+  Crash::record(&report);
 }
\ No newline at end of file
@@ -1,13 +1,57 @@
-use crate::statemachine::{RunState};
-use cortex_m::interrupt;
-use crate::context::{Context};
-use crate::triggers::{TRIGGER4_MASK};
-
-// #[derive(Copy,Clone)]
-pub enum MidiMessage {
-  Start = 0xFA,
-  TimingClock = 0xF8,
-  Continue = 0xFB,
-  Stop = 0xFC,
-  // Reset = 0xFF
-}
\ No newline at end of file
+use stm32f1xx_hal::{
+  pac::{interrupt},
+  pac
+};
+use cortex_m::interrupt::{CriticalSection};
+
+use crate::{CONTEXT};
+use crate::clock::{Clock};
+use crate::events::{EventQueue, Event};
+use crate::utils::{CSCell};
+
+use midi_clock_core::diagnostics::{parse_request};
+
+pub use midi_clock_core::midi::{MidiParser, MidiInput};
+
+static PARSER: CSCell<MidiParser> = CSCell::new(MidiParser::new());
+
+/* Receives the midi in port on usart1 and forwards clock messages to the clock */
+pub struct MidiIn;
+impl MidiIn {
+  // call after the context is set up, the interrupt reads from its serial
+  pub fn init() {
+    unsafe {
+      pac::NVIC::unmask(pac::Interrupt::USART1);
+    }
+  }
+
+  unsafe fn on_receive(byte: u8, cs: &CriticalSection) {
+    match PARSER.get(cs).parse(byte) {
+      Some(MidiInput::TimingClock) => Clock::on_external_tick(cs),
+      Some(MidiInput::Start) => Clock::on_external_start(cs),
+      Some(MidiInput::ControlChange(controller, value)) => {
+        EventQueue::post(Event::ControlChange(controller, value), cs);
+      },
+      Some(MidiInput::SysEx(data, length)) => {
+        data.get(..length).and_then(parse_request).map(|request| {
+          EventQueue::post(Event::DiagnosticsRequest(request), cs);
+        });
+      },
+      None => {}
+    }
+  }
+}
+
+#[interrupt]
+unsafe fn USART1() {
+  cortex_m::interrupt::free(|cs| {
+    let mut context = CONTEXT.borrow(cs).borrow_mut();
+    let byte = context.as_mut().and_then(|ctx| ctx.serial.read(1).ok());
+    drop(context);
+
+    #[cfg(not(feature = "shell"))]
+    byte.map(|b| MidiIn::on_receive(b, cs));
+    #[cfg(feature = "shell")]
+    byte.map(|b| crate::shell::Shell::on_receive(b, cs));
+  });
+}
@@ -7,13 +7,18 @@ use stm32f1xx_hal::{
   prelude::*,
   gpio,
   afio,
-  serial::{Serial, Config},
+  serial::{Serial, Config, Event},
   delay::{Delay},
-  i2c::{BlockingI2c, DutyCycle, Mode}
+  i2c::{BlockingI2c, DutyCycle, Mode},
+  watchdog::{IndependentWatchdog}
 };
 
 use crate::timers::*;
 use crate::encoder::*;
+#[cfg(not(feature = "tft"))]
+use midi_clock_core::st7066::{ST7066, Geometry, GEOMETRY_8X2};
+#[cfg(feature = "tft")]
+use crate::soft_spi::{SoftSpi};
 
 use stm32f1xx_hal::pac::{USART1, USART2};
 
@@ -30,15 +35,39 @@ pub type Button2Gpio = gpio::gpiob::PB13<gpio::Input<gpio::PullUp>>;
 pub type Button3Gpio = gpio::gpioa::PA7<gpio::Input<gpio::PullUp>>;
 pub type Button4Gpio = gpio::gpioa::PA6<gpio::Input<gpio::PullUp>>;
 
+pub type DisplayRsGpio = gpio::gpioa::PA8<gpio::Output<gpio::PushPull>>;
+pub type DisplayEnGpio = gpio::gpiob::PB15<gpio::Output<gpio::PushPull>>;
+pub type DisplayD4Gpio = gpio::gpiob::PB11<gpio::Output<gpio::PushPull>>;
+pub type DisplayD5Gpio = gpio::gpiob::PB10<gpio::Output<gpio::PushPull>>;
+pub type DisplayD6Gpio = gpio::gpioa::PA4<gpio::Output<gpio::PushPull>>;
+pub type DisplayD7Gpio = gpio::gpioa::PA5<gpio::Output<gpio::PushPull>>;
+
 pub struct DisplayPins {
-  pub rs: gpio::gpioa::PA8<gpio::Output<gpio::PushPull>>,
-  pub en: gpio::gpiob::PB15<gpio::Output<gpio::PushPull>>,
-  pub d4: gpio::gpiob::PB11<gpio::Output<gpio::PushPull>>,
-  pub d5: gpio::gpiob::PB10<gpio::Output<gpio::PushPull>>,
-  pub d6: gpio::gpioa::PA4<gpio::Output<gpio::PushPull>>,
-  pub d7: gpio::gpioa::PA5<gpio::Output<gpio::PushPull>>
+  pub rs: DisplayRsGpio,
+  pub en: DisplayEnGpio,
+  pub d4: DisplayD4Gpio,
+  pub d5: DisplayD5Gpio,
+  pub d6: DisplayD6Gpio,
+  pub d7: DisplayD7Gpio
 }
 
+// size of the mounted lcd, units with bigger displays change this
+#[cfg(not(feature = "tft"))]
+pub const DISPLAY_GEOMETRY: Geometry = GEOMETRY_8X2;
+
+// r/w of the lcd is tied to ground
+#[cfg(not(feature = "tft"))]
+pub type DisplayLcd = ST7066<
+  DisplayRsGpio, DisplayEnGpio, DisplayD4Gpio, DisplayD5Gpio, DisplayD6Gpio, DisplayD7Gpio, Delay
+>;
+
+// the tft gets connected to the lcd header, en and d4 are the spi clock and data
+#[cfg(feature = "tft")]
+pub type DisplayTft = st7789::ST7789<
+  display_interface_spi::SPIInterface<SoftSpi<DisplayEnGpio, DisplayD4Gpio>, DisplayRsGpio, DisplayD5Gpio>,
+  DisplayD6Gpio
+>;
+
 pub type I2c1Port = BlockingI2c<pac::I2C1, (
   gpio::gpiob::PB6<gpio::Alternate<gpio::OpenDrain>>,
   gpio::gpiob::PB7<gpio::Alternate<gpio::OpenDrain>>)>;
@@ -66,14 +95,28 @@ pub struct Peripherals {
   pub usart2: Option<Usart2Serial>,
   pub display: Option<DisplayPins>,
   pub i2c1: Option<I2c1Port>,
-  pub delay: Option<Delay>
+  pub delay: Option<Delay>,
+  pub watchdog: Option<IndependentWatchdog>,
+  pub reset_by_watchdog: bool // the clock was running before this boot
 }
 
 impl Peripherals {
   pub fn init() -> Peripherals {
 
     let dp = pac::Peripherals::take().unwrap();
-    let cp = cortex_m::Peripherals::take().unwrap();
+    let mut cp = cortex_m::Peripherals::take().unwrap();
+
+    // cycle counter timestamps the clock ticks for the diagnostics
+    cp.DCB.enable_trace();
+    cp.DWT.enable_cycle_counter();
+
+    // read and clear the reset flags before the rcc gets configured
+    let reset_by_watchdog = dp.RCC.csr.read().iwdgrstf().bit_is_set();
+    dp.RCC.csr.modify(|_, w| w.rmvf().set_bit());
+
+    // keep the watchdog from resetting while the chip is halted by the debugger
+    let watchdog = IndependentWatchdog::new(dp.IWDG);
+    watchdog.stop_on_debug(&dp.DBGMCU, true);
 
     let rcc = dp.RCC.constrain();
     let mut flash = dp.FLASH.constrain();
@@ -126,7 +169,10 @@ impl Peripherals {
       i2c1: Peripherals::init_i2c1(dp.I2C1, gpiob.pb6, gpiob.pb7, &mut gpiob.crl, 
         &mut afio, &clocks, &mut apb1),
 
-      delay: Some(delay)
+      delay: Some(delay),
+
+      watchdog: Some(watchdog),
+      reset_by_watchdog: reset_by_watchdog
     };
   }
 
@@ -210,7 +256,7 @@ impl Peripherals {
     let tx = pa9.into_alternate_push_pull(crh);
     let rx = pa10;
 
-    let serial = Serial::usart1(
+    let mut serial = Serial::usart1(
       usart1,
       (tx, rx),
       &mut afio.mapr,
@@ -218,6 +264,8 @@ impl Peripherals {
       *clocks,
       apb2,
     );
+    // pa10 is the midi in port
+    serial.listen(Event::Rxne);
     return Some(serial);
   }
 
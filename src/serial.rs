@@ -2,12 +2,15 @@
  * Wrapper for Serial Interfaces
  */
 
+use core::cell::{Cell};
 use core::convert::Infallible;
 use stm32f1xx_hal::{
   prelude::*, 
 };
 
 use crate::peripherals::{Usart1Serial, Usart2Serial};
+use crate::diagnostics::{Diagnostics};
+use crate::context::{Context};
 
 pub struct SerialWriter {
   serial1: Usart1Serial,
@@ -34,13 +37,34 @@ impl SerialWriter {
     }
   }
 
+  // drops the byte if the uart is still busy, so interrupts never wait for it
+  pub fn send(&mut self, uart: u8, byte: u8) {
+    if self.write(uart, byte).is_err() {
+      Diagnostics::count_dropped();
+    }
+  }
+
+  // waits until the uart takes the byte, every try gets its own short critical section,
+  // so the timer interrupts keep their timing while the main loop waits
+  pub fn write_waiting(uart: u8, byte: u8) {
+    let sent = Cell::new(false);
+    while !sent.get() {
+      cortex_m::interrupt::free(|cs| {
+        Context::get_instance(cs, &|ctx| sent.set(ctx.serial.write(uart, byte).is_ok()));
+      });
+    }
+  }
+
   pub fn write_str(&mut self, uart: u8, str: &str) -> nb::Result<(), Infallible> {
     let _ = str.bytes().map(|c| nb::block!(self.write(uart, c))).last();
     Ok(())
   }
 
-  pub fn read(&mut self, uart: u8) -> Result<u8,SerialError> {
-    let b = nb::block!(self.serial1.read());
-    return b.map_err(|e| SerialError::ReadError);
+  pub fn read(&mut self, uart: u8) -> nb::Result<u8,SerialError> {
+    match uart {
+      1 => return self.serial1.read().map_err(|e| e.map(|_| SerialError::ReadError)),
+      2 => return self.serial2.read().map_err(|e| e.map(|_| SerialError::ReadError)),
+      _ => return Err(nb::Error::WouldBlock)
+    }
   }
 }
\ No newline at end of file
@@ -0,0 +1,118 @@
+/*
+ * Command shell on the debug uart, the commands are parsed and run by the core
+ */
+
+use core::fmt;
+use cortex_m::interrupt::{CriticalSection};
+use cortex_m::peripheral::{SCB};
+use heapless::spsc::{Queue};
+
+use midi_clock_core::shell::{Device, LineBuffer, parse, execute};
+use midi_clock_core::statemachine::{Statemachine, State};
+use midi_clock_core::diagnostics::{TickStats};
+use midi_clock_core::crash::{CrashReport};
+use midi_clock_core::setlist::{Song};
+
+use crate::serial::{SerialWriter};
+use crate::log::{Log};
+use crate::diagnostics::{Diagnostics, CYCLES_PER_US};
+use crate::memory::{Memory};
+use crate::utils::{CSCell};
+
+const SHELL_UART: u8 = 1;
+
+// a pasted line has to fit in until the main loop reads it
+const INPUT_SIZE: usize = 64;
+
+static INPUT: CSCell<Queue<u8, INPUT_SIZE>> = CSCell::new(Queue::new());
+
+/* Writes the output of the commands, waits for the uart outside of the critical sections */
+struct SerialOutput;
+impl fmt::Write for SerialOutput {
+  fn write_str(&mut self, s: &str) -> fmt::Result {
+    for byte in s.bytes() {
+      SerialWriter::write_waiting(SHELL_UART, byte);
+    }
+    return Ok(());
+  }
+}
+
+struct ClockDevice<'a> {
+  memory: &'a mut Memory
+}
+
+impl<'a> Device for ClockDevice<'a> {
+  const CYCLES_PER_US: u32 = CYCLES_PER_US;
+
+  fn save(&mut self, state: &State) -> bool {
+    return self.memory.write_state(state).is_ok();
+  }
+
+  fn load(&mut self) -> Option<State> {
+    return self.memory.load_state();
+  }
+
+  fn save_song(&mut self, song: &Song) -> bool {
+    return self.memory.write_song(song).is_ok();
+  }
+
+  fn read_eeprom(&mut self, address: u16, buffer: &mut [u8]) -> bool {
+    return self.memory.read_bytes(address, buffer).is_ok();
+  }
+
+  fn stats(&mut self) -> (TickStats, u32) {
+    return (Diagnostics::stats(), Diagnostics::dropped());
+  }
+
+  fn reset_stats(&mut self) {
+    Diagnostics::reset();
+  }
+
+  fn crash_report(&mut self) -> Option<CrashReport> {
+    return self.memory.load_crash_report();
+  }
+
+  fn clear_crash_report(&mut self) -> bool {
+    return self.memory.clear_crash_report().is_ok();
+  }
+
+  fn reset(&mut self) {
+    SCB::sys_reset();
+  }
+}
+
+pub struct Shell {
+  line: LineBuffer
+}
+
+impl Shell {
+  pub fn new() -> Shell {
+    return Shell { line: LineBuffer::new() };
+  }
+
+  // gets called by the uart interrupt, drops the byte if the main loop is behind
+  pub fn on_receive(byte: u8, cs: &CriticalSection) {
+    INPUT.get(cs).enqueue(byte).ok();
+  }
+
+  // runs the commands of the lines received since the last call
+  pub fn poll(&mut self, statemachine: &mut Statemachine, memory: &mut Memory) {
+    let mut out = SerialOutput;
+    while let Some(byte) = cortex_m::interrupt::free(|cs| INPUT.get(cs).dequeue()) {
+      let result = match self.line.push(byte) {
+        Some(line) => line.and_then(|line| parse(&line)),
+        None => continue
+      };
+      // pending log lines would get mixed into the answer
+      Log::flush_all();
+      let result = result.and_then(|command| {
+        return execute(command, statemachine, &mut ClockDevice { memory: memory }, &mut out);
+      });
+
+      match result {
+        Ok(()) => fmt::Write::write_str(&mut out, "ok\r\n").ok(),
+        Err(error) => fmt::Write::write_fmt(&mut out, format_args!("error: {}\r\n", error.message())).ok()
+      };
+    }
+  }
+}
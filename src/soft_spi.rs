@@ -0,0 +1,43 @@
+/*
+ * Write only spi on gpio pins, the tft uses the pins of the character lcd header
+ */
+
+use embedded_hal::digital::v2::{OutputPin};
+use embedded_hal::blocking::spi::{Write};
+
+// spi mode 3, clock idles high and data gets sampled on the rising edge
+pub struct SoftSpi<SCK: OutputPin, MOSI: OutputPin> {
+  sck: SCK,
+  mosi: MOSI
+}
+
+impl<SCK: OutputPin, MOSI: OutputPin> SoftSpi<SCK, MOSI> {
+  pub fn new(sck: SCK, mosi: MOSI) -> SoftSpi<SCK, MOSI> {
+    let mut spi = SoftSpi {
+      sck: sck,
+      mosi: mosi
+    };
+    spi.sck.set_high().ok();
+    return spi;
+  }
+}
+
+impl<SCK: OutputPin, MOSI: OutputPin> Write<u8> for SoftSpi<SCK, MOSI> {
+  type Error = ();
+
+  fn write(&mut self, words: &[u8]) -> Result<(), ()> {
+    for word in words {
+      // msb first
+      for bit in (0..8).rev() {
+        self.sck.set_low().ok();
+        if (word >> bit) & 1 > 0 {
+          self.mosi.set_high().ok();
+        } else {
+          self.mosi.set_low().ok();
+        }
+        self.sck.set_high().ok();
+      }
+    }
+    return Ok(());
+  }
+}
@@ -0,0 +1,77 @@
+/*
+ * Graphical ui on a 240x240 st7789 tft, the pages are drawn by the core
+ */
+
+use stm32f1xx_hal::{
+  delay::{Delay}
+};
+use embedded_hal::digital::v2::{OutputPin};
+use embedded_graphics::prelude::*;
+use display_interface_spi::{SPIInterface};
+use st7789::{ST7789};
+
+use crate::peripherals::{DisplayPins, DisplayTft, DisplayD7Gpio};
+use crate::display::{Display, Content, update_time_arrived};
+use midi_clock_core::graphics::{Screen, SCREEN_SIZE, BACKGROUND};
+use crate::soft_spi::{SoftSpi};
+
+use crate::trace;
+
+pub struct TftDisplay {
+  tft: DisplayTft,
+  backlight: DisplayD7Gpio,
+  delay: Delay,
+  content: Content,
+  screen: Screen,
+  activity: u8 // outputs that ticked since the last render
+}
+
+impl TftDisplay {
+  pub fn new(pins: DisplayPins, delay: Delay) -> TftDisplay {
+    // rs -> dc, en -> sck, d4 -> mosi, d5 -> cs, d6 -> reset, d7 -> backlight
+    let spi = SoftSpi::new(pins.en, pins.d4);
+    let interface = SPIInterface::new(spi, pins.rs, pins.d5);
+
+    return TftDisplay {
+      tft: ST7789::new(interface, pins.d6, SCREEN_SIZE, SCREEN_SIZE),
+      backlight: pins.d7,
+      delay: delay,
+      content: Content::new(),
+      screen: Screen::new(),
+      activity: 0
+    }
+  }
+}
+
+impl Display for TftDisplay {
+
+  fn content(&mut self) -> &mut Content {
+    return &mut self.content;
+  }
+
+  fn init(&mut self) {
+    self.tft.init(&mut self.delay).ok();
+    self.tft.clear(BACKGROUND).ok();
+    self.backlight.set_high().ok();
+    self.screen.draw_labels(&mut self.tft).ok();
+  }
+
+  fn render(&mut self) {
+    self.content.expire_message();
+    let update_time_arrived = update_time_arrived();
+    if update_time_arrived && self.screen.activity_changed(self.activity) {
+      self.content.updated = true;
+    }
+
+    if self.content.updated && update_time_arrived {
+      trace!("display render");
+      self.screen.draw(&mut self.tft, &self.content, self.activity).ok();
+      self.activity = 0;
+      self.content.updated = false;
+    }
+  }
+
+  fn update_activity(&mut self, outputs: u8) {
+    self.activity |= outputs;
+  }
+}
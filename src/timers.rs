@@ -4,7 +4,7 @@ use stm32f1xx_hal::{
   prelude::*,
   timer::{Event, Timer, CountDownTimer},
 };
-use core::sync::atomic::{AtomicU32, Ordering};
+use core::sync::atomic::{AtomicU32, AtomicBool, Ordering};
 
 use cortex_m::interrupt::{CriticalSection, Mutex};
 use core::cell::{RefCell};
@@ -14,13 +14,20 @@ use crate::utils::{CSCell};
 type CSTimerHandler = unsafe fn(&CriticalSection);
 type TimerHandler = unsafe fn();
 
-const MAX_TIM2_HANDLERS: usize = 3;
+// buttons, display, triggers and sync
+const MAX_TIM3_HANDLERS: usize = 4;
 
 
 
 static TIMER_2_HANDLER: CSCell<Option<CSTimerHandler>> = CSCell::new(None);
+static TIMER_2_BASE_HANDLER: CSCell<Option<CSTimerHandler>> = CSCell::new(None);
 static G_TIM2: Mutex<RefCell<Option<CountDownTimer<TIM2>>>> = Mutex::new(RefCell::new(None));
 static TIMER2_OVERFLOWS: AtomicU32 = AtomicU32::new(1);
+static TIMER2_RUNNING: AtomicBool = AtomicBool::new(false);
+
+// set by the interrupts, cleared by the health check of the watchdog
+static TIMER2_ALIVE: AtomicBool = AtomicBool::new(false);
+static TIMER3_ALIVE: AtomicBool = AtomicBool::new(false);
 
 /* Timer2 is used only to send trigger and midi tick messages to the clock */
 pub struct Timer2;
@@ -44,6 +51,7 @@ impl Timer2  {
       if PREV_RUNNING == running { return; }
       PREV_RUNNING = running;
     }
+    TIMER2_RUNNING.store(running, Ordering::Relaxed);
 
     cortex_m::interrupt::free(|cs| {
       let mut tim2 = G_TIM2.borrow(cs).borrow_mut();
@@ -77,6 +85,18 @@ impl Timer2  {
       TIMER_2_HANDLER.set(Some(cb), cs);
     })
   }
+
+  // handler gets called on every 50us overflow
+  pub fn set_base_handler(cb: CSTimerHandler) {
+    cortex_m::interrupt::free(|cs| {
+      TIMER_2_BASE_HANDLER.set(Some(cb), cs);
+    })
+  }
+
+  // true if the interrupt ran since the last call or the timer is not supposed to run
+  pub fn is_alive() -> bool {
+    return TIMER2_ALIVE.swap(false, Ordering::Relaxed) || !TIMER2_RUNNING.load(Ordering::Relaxed);
+  }
 }
 
 #[interrupt]
@@ -91,6 +111,9 @@ unsafe fn TIM2() {
     } else {
       *OVERFLOWS += 1;
     }
+    TIMER_2_BASE_HANDLER.get(cs).map(|f| f(cs) );
+    TIMER2_ALIVE.store(true, Ordering::Relaxed);
+
     // reset interrupt
     let mut tim2 = G_TIM2.borrow(cs).borrow_mut();
     tim2.as_mut().map(|t| {
@@ -100,7 +123,7 @@ unsafe fn TIM2() {
   });
 }
 
-static TIMER_3_HANDLERS: CSCell<[Option<TimerHandler>; MAX_TIM2_HANDLERS]> = CSCell::new([None; MAX_TIM2_HANDLERS]);
+static TIMER_3_HANDLERS: CSCell<[Option<TimerHandler>; MAX_TIM3_HANDLERS]> = CSCell::new([None; MAX_TIM3_HANDLERS]);
 static G_TIM3: Mutex<RefCell<Option<CountDownTimer<TIM3>>>> = Mutex::new(RefCell::new(None));
 
 /* Timer3 is used as a general purpose trigger for debouncing, pulse generation, etc */
@@ -127,6 +150,11 @@ impl Timer3 {
       TIMER_3_HANDLERS.set_unsafe(*handlers);
     }
   }
+
+  // true if the interrupt ran since the last call
+  pub fn is_alive() -> bool {
+    return TIMER3_ALIVE.swap(false, Ordering::Relaxed);
+  }
 }
 
 #[interrupt]
@@ -135,6 +163,7 @@ unsafe fn TIM3() {
   for handler in handlers {
     handler.map(|f| f());
   }
+  TIMER3_ALIVE.store(true, Ordering::Relaxed);
 
   cortex_m::interrupt::free(|cs| {
     let mut tim3 = G_TIM3.borrow(cs).borrow_mut();
@@ -1,27 +1,22 @@
+/*
+ * Trigger outputs, the pulses end after the length counted by the core
+ */
+
 use crate::peripherals::{Trigger1Gpio, Trigger2Gpio, Trigger3Gpio, Trigger4Gpio};
 
 use embedded_hal::digital::v2::{OutputPin};
 use cortex_m::interrupt;
-use core::sync::atomic::{AtomicU8, Ordering};
 
 use crate::{CONTEXT};
-
-// bitmasks for triggers
-pub const TRIGGER1_MASK : u8 = 0b00000001;
-pub const TRIGGER2_MASK : u8 = 0b00000010;
-pub const TRIGGER3_MASK : u8 = 0b00000100;
-pub const TRIGGER4_MASK : u8 = 0b00001000;
-
-// stop pulse after 5ms
-const TIMER_OVERFLOW_COUNT: u8 = 5;
-
-static TRIGGERS_STARTED: AtomicU8 = AtomicU8::new(0);
+use midi_clock_core::clock::{TRIGGER1_MASK, TRIGGER2_MASK, TRIGGER3_MASK, TRIGGER4_MASK};
+use midi_clock_core::triggers::{TriggerPulses};
 
 pub struct Triggers {
   trigger1: Trigger1Gpio, // midi out1+2
   trigger2: Trigger2Gpio, // midi out3+4
   trigger3: Trigger3Gpio, // trigger
-  trigger4: Trigger4Gpio  // reset trigger
+  trigger4: Trigger4Gpio, // reset trigger
+  pulses: TriggerPulses
 }
 
 impl Triggers  {
@@ -36,6 +31,7 @@ impl Triggers  {
       trigger2: trigger2,
       trigger3: trigger3,
       trigger4: trigger4,
+      pulses: TriggerPulses::new()
     }
   }
 
@@ -44,72 +40,45 @@ impl Triggers  {
   }
 
   fn start_pulse(&mut self, triggers: u8) {
-    TRIGGERS_STARTED.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| {
-      if (triggers & TRIGGER1_MASK) > 0 {
-        self.trigger1.set_high().ok();
-      }
-      if (triggers & TRIGGER2_MASK) > 0 {
-        self.trigger2.set_high().ok();
-      }
-      if (triggers & TRIGGER3_MASK) > 0 {
-        self.trigger3.set_high().ok();
-      }
-      if (triggers & TRIGGER4_MASK) > 0 {
-        self.trigger4.set_high().ok();
-      }
-      return Some(x | triggers);
-    }).ok();
+    if (triggers & TRIGGER1_MASK) > 0 {
+      self.trigger1.set_high().ok();
+    }
+    if (triggers & TRIGGER2_MASK) > 0 {
+      self.trigger2.set_high().ok();
+    }
+    if (triggers & TRIGGER3_MASK) > 0 {
+      self.trigger3.set_high().ok();
+    }
+    if (triggers & TRIGGER4_MASK) > 0 {
+      self.trigger4.set_high().ok();
+    }
+    self.pulses.start(triggers);
   }
 
   fn stop_pulse(&mut self, triggers: u8) {
-    TRIGGERS_STARTED.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| {
-      if (triggers & TRIGGER1_MASK) > 0 {
-        self.trigger1.set_low().ok();
-      }
-      if (triggers & TRIGGER2_MASK) > 0 {
-        self.trigger2.set_low().ok();
-      }
-      if (triggers & TRIGGER3_MASK) > 0 {
-        self.trigger3.set_low().ok();
-      }
-      if (triggers & TRIGGER4_MASK) > 0 {
-        self.trigger4.set_low().ok();
-      }
-      return Some(x & !(triggers));
-    }).ok();
+    if (triggers & TRIGGER1_MASK) > 0 {
+      self.trigger1.set_low().ok();
+    }
+    if (triggers & TRIGGER2_MASK) > 0 {
+      self.trigger2.set_low().ok();
+    }
+    if (triggers & TRIGGER3_MASK) > 0 {
+      self.trigger3.set_low().ok();
+    }
+    if (triggers & TRIGGER4_MASK) > 0 {
+      self.trigger4.set_low().ok();
+    }
   }
 
   pub fn on_timer_tick() {
-    static mut OVERFLOWS: [u8;4] = [0,0,0,0];
-
-    let triggers_started = TRIGGERS_STARTED.load(Ordering:: Relaxed);
-    let mut triggers_ended: u8 = 0;
-
-    // resets overflow is not started, else counts until TIMER_OVERFLOW_COUNT
-    pub fn check_trigger(trigger_started: u8, overflows: &mut u8) -> u8  {
-      if trigger_started > 0 {
-        if *overflows > TIMER_OVERFLOW_COUNT {
-          return trigger_started;
+    interrupt::free(|cs| {
+      let mut context = CONTEXT.borrow(cs).borrow_mut();
+      context.as_mut().map(|ctx| {
+        let ended = ctx.triggers.pulses.on_timer_tick();
+        if ended > 0 {
+          ctx.triggers.stop_pulse(ended);
         }
-        *overflows += 1;
-      } else {
-        *overflows = 0;
-      }
-      return 0;
-    }
-
-    unsafe {
-      triggers_ended |= check_trigger(triggers_started & TRIGGER1_MASK, &mut OVERFLOWS[0]);
-      triggers_ended |= check_trigger(triggers_started & TRIGGER2_MASK, &mut OVERFLOWS[1]);
-      triggers_ended |= check_trigger(triggers_started & TRIGGER3_MASK, &mut OVERFLOWS[2]);
-      triggers_ended |= check_trigger(triggers_started & TRIGGER4_MASK, &mut OVERFLOWS[3]);
-    }
-
-    if triggers_ended > 0 {
-      interrupt::free(|cs| {
-        let mut context = CONTEXT.borrow(cs).borrow_mut();
-        context.as_mut().map(|ctx| ctx.triggers.stop_pulse(triggers_ended) );
-      })
-    }
+      });
+    })
   }
-}
\ No newline at end of file
+}
@@ -1,31 +1,6 @@
-use numtoa::NumToA;
 use cortex_m::interrupt::{ CriticalSection };
 use core::cell::{ UnsafeCell };
 
-pub fn u16_to_string<'a>(number: u16) -> &'a str {
-  static mut STRING_BUFFER : [u8; 5] = [0; 5];
-  unsafe { 
-    STRING_BUFFER = [0; 5];
-    return number.numtoa_str(10, &mut STRING_BUFFER); 
-  }
-}
-
-pub fn u32_to_string<'a>(number: u32) -> &'a str {
-  static mut STRING_BUFFER : [u8; 10] = [0; 10];
-  unsafe { 
-    STRING_BUFFER = [0; 10];
-    return number.numtoa_str(10, &mut STRING_BUFFER); 
-  }
-}
-
-pub fn i16_to_string<'a>(number: i16) -> &'a str {
-  static mut STRING_BUFFER : [u8; 5] = [0; 5];
-  unsafe { 
-    STRING_BUFFER = [0; 5];
-    return number.numtoa_str(5, &mut STRING_BUFFER); 
-  }
-}
-
 /* Struct holds a thread safe value to be shared between interrupts */
 pub struct CSCell<T>( UnsafeCell<T> );
 impl<T> CSCell<T> {
@@ -0,0 +1,54 @@
+/*
+ * Independent watchdog, resets the chip if the main loop or the timer interrupts stop
+ *
+ * The tempo and run state are kept in ram that is not cleared on reset, so the clock continues after
+ * the reboot.
+ */
+
+use core::mem::MaybeUninit;
+use core::ptr;
+use stm32f1xx_hal::{
+  prelude::*,
+  watchdog::{IndependentWatchdog}
+};
+
+use midi_clock_core::recovery::{Snapshot};
+use midi_clock_core::statemachine::{State};
+use crate::timers::{Timer2, Timer3};
+
+// longest time the main loop may block, e.g. for an eeprom write
+const WATCHDOG_TIMEOUT_MS: u32 = 500;
+
+#[link_section = ".uninit.SNAPSHOT"]
+static mut SNAPSHOT: MaybeUninit<u32> = MaybeUninit::uninit();
+
+pub struct Watchdog {
+  iwdg: IndependentWatchdog
+}
+
+impl Watchdog {
+  // once started the watchdog can not be stopped anymore
+  pub fn start(mut iwdg: IndependentWatchdog) -> Watchdog {
+    iwdg.start(WATCHDOG_TIMEOUT_MS.ms());
+    return Watchdog { iwdg: iwdg };
+  }
+
+  // call from the main loop, only feeds while the timers are running as well
+  pub fn feed(&mut self) {
+    if Timer3::is_alive() && Timer2::is_alive() {
+      self.iwdg.feed();
+    }
+  }
+
+  // call on every state change
+  pub fn save(state: &State) {
+    unsafe { SNAPSHOT = MaybeUninit::new(Snapshot::from_state(state).pack()) }
+  }
+
+  // returns the state from before a reboot by the watchdog
+  pub fn restore(reset_by_watchdog: bool) -> Option<Snapshot> {
+    if !reset_by_watchdog { return None }
+    // volatile, the compiler can not know the content from before the reset
+    return Snapshot::unpack(unsafe { ptr::addr_of!(SNAPSHOT).read_volatile().assume_init() });
+  }
+}
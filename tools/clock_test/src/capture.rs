@@ -0,0 +1,81 @@
+/*
+ * Tick timestamps of a capture, grouped into streams
+ *
+ * Every line is one of
+ *   <us>                       timestamp of the clock_analyzer firmware, stream "clock"
+ *   <stream> <us>              timestamp of a named stream
+ *   <us>,<type>,<output>,<v>   timeline of the simulator, midi clock bytes and rising trigger edges
+ * Other lines are skipped, like the start message of the firmware.
+ */
+
+const TIMING_CLOCK: u8 = 0xF8;
+
+pub struct Stream {
+  pub name: String,
+  pub timestamps: Vec<u64>, // us
+  last_raw: u64,
+  wraps: u64
+}
+
+pub struct Capture {
+  streams: Vec<Stream>
+}
+
+impl Capture {
+  pub fn new() -> Capture {
+    return Capture { streams: Vec::new() };
+  }
+
+  pub fn parse(text: &str) -> Capture {
+    let mut capture = Capture::new();
+    for line in text.lines() {
+      capture.parse_line(line);
+    }
+    return capture;
+  }
+
+  // returns the stream the line added a timestamp to
+  pub fn parse_line(&mut self, line: &str) -> Option<&Stream> {
+    let line = line.trim();
+    let fields: Vec<&str> = line.split(',').collect();
+    if fields.len() == 4 {
+      let value = fields[3].parse::<u8>().ok()?;
+      let name = match fields[1] {
+        "midi" if value == TIMING_CLOCK => format!("midi{}", fields[2]),
+        "trigger" if value == 1 => format!("trigger{}", fields[2]),
+        _ => return None
+      };
+      return Some(self.add(&name, fields[0].parse().ok()?));
+    }
+
+    let words: Vec<&str> = line.split_whitespace().collect();
+    return match words.len() {
+      1 => Some(self.add("clock", words[0].parse().ok()?)),
+      2 => Some(self.add(words[0], words[1].parse().ok()?)),
+      _ => None
+    };
+  }
+
+  pub fn add(&mut self, name: &str, time: u64) -> &Stream {
+    let index = match self.streams.iter().position(|s| s.name == name) {
+      Some(index) => index,
+      None => {
+        self.streams.push(Stream { name: String::from(name), timestamps: Vec::new(), last_raw: 0, wraps: 0 });
+        self.streams.len() - 1
+      }
+    };
+
+    let stream = &mut self.streams[index];
+    // micros() of the arduino overflows after 71 minutes
+    if time < stream.last_raw && stream.last_raw - time > 1 << 31 {
+      stream.wraps += 1;
+    }
+    stream.last_raw = time;
+    stream.timestamps.push(time + (stream.wraps << 32));
+    return stream;
+  }
+
+  pub fn streams(&self) -> &[Stream] {
+    return &self.streams;
+  }
+}
@@ -0,0 +1,67 @@
+/*
+ * Timing analysis of captured midi clock ticks, replaces the python scripts of the clock test
+ */
+
+pub mod capture;
+pub mod stats;
+
+use std::fmt::Write;
+
+use capture::{Capture};
+use stats::{Stats, Limits};
+
+pub struct Options {
+  pub ppq: u32, // ticks per quarter note of the streams
+  pub expected_bpm: Option<f64>,
+  pub skip: usize, // ticks at the start of every stream that are not analyzed
+  pub stream: Option<String>, // analyzes only this stream
+  pub limits: Limits
+}
+
+pub const DEFAULT_OPTIONS: Options = Options {
+  ppq: 24,
+  expected_bpm: None,
+  skip: 0,
+  stream: None,
+  limits: Limits { max_jitter: None, max_bpm_error: None, max_drift: None }
+};
+
+// returns the report and if all streams stayed within the limits
+pub fn analyze(capture: &Capture, options: &Options) -> (String, bool) {
+  let mut report = String::new();
+  let mut passed = true;
+
+  let streams: Vec<_> = capture.streams().iter()
+    .filter(|stream| options.stream.as_ref().map_or(true, |name| *name == stream.name))
+    .collect();
+  if streams.is_empty() {
+    return (String::from("no ticks captured\n"), false);
+  }
+
+  for stream in streams {
+    let timestamps = &stream.timestamps[options.skip.min(stream.timestamps.len())..];
+    let stats = match Stats::from_timestamps(timestamps, options.ppq, options.expected_bpm) {
+      Some(stats) => stats,
+      None => {
+        writeln!(report, "{}: not enough ticks\n", stream.name).unwrap();
+        passed = false;
+        continue;
+      }
+    };
+
+    writeln!(report, "{}: {} intervals", stream.name, stats.intervals).unwrap();
+    writeln!(report, "  mean      {:.1}us", stats.mean).unwrap();
+    writeln!(report, "  std dev   {:.1}us", stats.std_dev).unwrap();
+    writeln!(report, "  min/max   {:.0}us / {:.0}us", stats.min, stats.max).unwrap();
+    writeln!(report, "  jitter    {:.1}us", stats.jitter).unwrap();
+    writeln!(report, "  bpm       {:.3} (expected {}, error {:+.3}%)", stats.bpm, stats.expected_bpm, stats.bpm_error).unwrap();
+    writeln!(report, "  drift     {:+.0}us ({:+.0}ppm)", stats.drift, stats.drift_ppm).unwrap();
+
+    for failure in options.limits.check(&stats) {
+      writeln!(report, "  FAIL {}", failure).unwrap();
+      passed = false;
+    }
+    report.push('\n');
+  }
+  return (report, passed);
+}
@@ -0,0 +1,90 @@
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::process;
+
+use midi_clock_analyzer::{analyze, DEFAULT_OPTIONS};
+use midi_clock_analyzer::capture::{Capture};
+
+const USAGE: &str = "usage: midi_clock_analyzer <capture file|serial port> [--stream name] [--bpm n] [--ppq n] [--skip n] [--count n]
+  [--max-jitter us] [--max-bpm-error percent] [--max-drift ppm]";
+
+// baudrate of the clock_analyzer firmware and of the midi clock with the clock_test feature
+const SERIAL_BAUDRATE: u32 = 38400;
+
+const DEFAULT_COUNT: usize = 1000;
+
+// exit codes, failing limits can gate a release
+const EXIT_LIMITS: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+
+fn main() {
+  let args: Vec<String> = env::args().skip(1).collect();
+  let mut options = DEFAULT_OPTIONS;
+  let mut source: Option<&str> = None;
+  let mut count = DEFAULT_COUNT;
+
+  let mut i = 0;
+  while i < args.len() {
+    let value = || -> f64 {
+      return args.get(i + 1).and_then(|s| s.parse().ok()).unwrap_or_else(|| exit(USAGE, EXIT_USAGE));
+    };
+    match args[i].as_str() {
+      "--stream" => options.stream = Some(args.get(i + 1).cloned().unwrap_or_else(|| exit(USAGE, EXIT_USAGE))),
+      "--bpm" => options.expected_bpm = Some(value()),
+      "--ppq" => options.ppq = value() as u32,
+      "--skip" => options.skip = value() as usize,
+      "--count" => count = value() as usize,
+      "--max-jitter" => options.limits.max_jitter = Some(value()),
+      "--max-bpm-error" => options.limits.max_bpm_error = Some(value()),
+      "--max-drift" => options.limits.max_drift = Some(value()),
+      path if source.is_none() && !path.starts_with("--") => {
+        source = Some(path);
+        i += 1;
+        continue;
+      },
+      _ => exit(USAGE, EXIT_USAGE)
+    }
+    i += 2;
+  }
+  if options.ppq == 0 { exit(USAGE, EXIT_USAGE) }
+
+  let source = source.unwrap_or_else(|| exit(USAGE, EXIT_USAGE));
+  let capture = if source.starts_with("/dev/") {
+    read_serial(source, count + options.skip)
+  } else {
+    Capture::parse(&fs::read_to_string(source).unwrap_or_else(|e| exit(&format!("{}: {}", source, e), EXIT_USAGE)))
+  };
+
+  let (report, passed) = analyze(&capture, &options);
+  print!("{}", report);
+  if !passed {
+    process::exit(EXIT_LIMITS);
+  }
+}
+
+// reads the timestamps the clock_analyzer firmware sends until the stream has the given ticks
+fn read_serial(port: &str, ticks: usize) -> Capture {
+  let flag = if cfg!(target_os = "macos") { "-f" } else { "-F" };
+  let configured = process::Command::new("stty")
+    .args(&[flag, port, &SERIAL_BAUDRATE.to_string(), "raw", "-echo"])
+    .status()
+    .map_or(false, |status| status.success());
+  if !configured {
+    exit(&format!("{}: could not set the baudrate", port), EXIT_USAGE);
+  }
+
+  let file = fs::File::open(port).unwrap_or_else(|e| exit(&format!("{}: {}", port, e), EXIT_USAGE));
+  let mut capture = Capture::new();
+  for line in BufReader::new(file).lines() {
+    let line = line.unwrap_or_else(|e| exit(&format!("{}: {}", port, e), EXIT_USAGE));
+    let captured = capture.parse_line(&line).map_or(0, |stream| stream.timestamps.len());
+    if captured >= ticks { break }
+  }
+  return capture;
+}
+
+fn exit(message: &str, code: i32) -> ! {
+  eprintln!("{}", message);
+  process::exit(code);
+}
@@ -0,0 +1,78 @@
+/*
+ * Interval statistics of a stream of ticks
+ */
+
+#[derive(Copy, Clone, Debug)]
+pub struct Stats {
+  pub intervals: usize,
+  pub mean: f64, // us
+  pub std_dev: f64,
+  pub min: f64,
+  pub max: f64,
+  pub jitter: f64, // largest deviation of an interval from the mean
+  pub bpm: f64,
+  pub expected_bpm: f64,
+  pub bpm_error: f64, // percent
+  pub drift: f64, // us the stream is late at its end compared to the expected bpm
+  pub drift_ppm: f64
+}
+
+impl Stats {
+  // expected bpm defaults to the measured one rounded, the clock only runs on whole bpm
+  pub fn from_timestamps(timestamps: &[u64], ppq: u32, expected_bpm: Option<f64>) -> Option<Stats> {
+    if timestamps.len() < 2 { return None }
+
+    let intervals: Vec<f64> = timestamps.windows(2).map(|w| w[1].saturating_sub(w[0]) as f64).collect();
+    let count = intervals.len() as f64;
+    let mean = intervals.iter().sum::<f64>() / count;
+    let variance = intervals.iter().map(|i| (i - mean) * (i - mean)).sum::<f64>() / count;
+    let min = intervals.iter().cloned().fold(f64::MAX, f64::min);
+    let max = intervals.iter().cloned().fold(0.0, f64::max);
+    if mean <= 0.0 { return None }
+
+    let bpm = 60.0 * 1000.0 * 1000.0 / (mean * ppq as f64);
+    let expected_bpm = expected_bpm.unwrap_or(bpm.round());
+    let period = 60.0 * 1000.0 * 1000.0 / (expected_bpm * ppq as f64);
+    let duration = (timestamps[timestamps.len() - 1] - timestamps[0]) as f64;
+    let drift = duration - count * period;
+
+    return Some(Stats {
+      intervals: intervals.len(),
+      mean: mean,
+      std_dev: variance.sqrt(),
+      min: min,
+      max: max,
+      jitter: (max - mean).max(mean - min),
+      bpm: bpm,
+      expected_bpm: expected_bpm,
+      bpm_error: (bpm - expected_bpm) / expected_bpm * 100.0,
+      drift: drift,
+      drift_ppm: drift / (count * period) * 1000.0 * 1000.0
+    });
+  }
+}
+
+/* Thresholds a stream has to stay within */
+#[derive(Copy, Clone, Default)]
+pub struct Limits {
+  pub max_jitter: Option<f64>, // us
+  pub max_bpm_error: Option<f64>, // percent
+  pub max_drift: Option<f64> // ppm
+}
+
+impl Limits {
+  // returns the exceeded limits
+  pub fn check(&self, stats: &Stats) -> Vec<String> {
+    let mut failures = Vec::new();
+    if let Some(limit) = self.max_jitter.filter(|limit| stats.jitter > *limit) {
+      failures.push(format!("jitter {:.1}us exceeds {}us", stats.jitter, limit));
+    }
+    if let Some(limit) = self.max_bpm_error.filter(|limit| stats.bpm_error.abs() > *limit) {
+      failures.push(format!("bpm error {:.3}% exceeds {}%", stats.bpm_error, limit));
+    }
+    if let Some(limit) = self.max_drift.filter(|limit| stats.drift_ppm.abs() > *limit) {
+      failures.push(format!("drift {:.0}ppm exceeds {}ppm", stats.drift_ppm, limit));
+    }
+    return failures;
+  }
+}
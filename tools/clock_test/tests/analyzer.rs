@@ -0,0 +1,75 @@
+use midi_clock_analyzer::{analyze, Options, DEFAULT_OPTIONS};
+use midi_clock_analyzer::capture::{Capture};
+use midi_clock_analyzer::stats::{Stats, Limits};
+
+// ticks at 120 bpm are 20833us apart
+fn ticks(count: u64, period: u64, start: u64) -> Vec<u64> {
+  return (0..count).map(|i| start + i * period).collect();
+}
+
+#[test]
+fn stats_of_a_steady_clock() {
+  let stats = Stats::from_timestamps(&ticks(97, 20833, 1000), 24, None).unwrap();
+  assert_eq!(stats.intervals, 96);
+  assert_eq!(stats.mean, 20833.0);
+  assert_eq!(stats.std_dev, 0.0);
+  assert_eq!(stats.jitter, 0.0);
+  assert_eq!(stats.expected_bpm, 120.0);
+  assert!(stats.bpm_error.abs() < 0.01);
+  // 96 intervals of 20833.33us
+  assert!((stats.drift + 32.0).abs() < 0.1);
+}
+
+#[test]
+fn stats_show_jitter_and_bpm_error() {
+  let mut timestamps = ticks(25, 20000, 0);
+  timestamps[10] += 300;
+  let stats = Stats::from_timestamps(&timestamps, 24, Some(120.0)).unwrap();
+  assert_eq!(stats.min, 19700.0);
+  assert_eq!(stats.max, 20300.0);
+  assert_eq!(stats.jitter, 300.0);
+  assert!((stats.bpm_error - 4.1667).abs() < 0.001);
+
+  let limits = Limits { max_jitter: Some(250.0), max_bpm_error: Some(5.0), max_drift: None };
+  assert_eq!(limits.check(&stats).len(), 1);
+  assert!(Stats::from_timestamps(&[5], 24, None).is_none());
+}
+
+#[test]
+fn capture_reads_all_formats() {
+  let capture = Capture::parse("start\n100\n200\nmidi2 50\n4294967200\ntime_us,type,output,value\n10,midi,1,248\n10,trigger,3,1\n16,trigger,3,0\n20,midi,1,252\n");
+  let streams = capture.streams();
+  assert_eq!(streams.len(), 4);
+  assert_eq!(streams[0].name, "clock");
+  assert_eq!(streams[0].timestamps, vec![100, 200, 4294967200]);
+  assert_eq!(streams[1].name, "midi2");
+  assert_eq!(streams[2].timestamps, vec![10]);
+  assert_eq!(streams[3].name, "trigger3");
+  assert_eq!(streams[3].timestamps, vec![10]);
+}
+
+#[test]
+fn capture_continues_after_the_micros_overflow() {
+  let capture = Capture::parse("4294967000\n200\n");
+  assert_eq!(capture.streams()[0].timestamps, vec![4294967000, 4294967496]);
+}
+
+#[test]
+fn analyze_fails_streams_outside_the_limits() {
+  let mut capture = Capture::new();
+  for time in ticks(50, 20833, 0) {
+    capture.add("midi1", time);
+  }
+  capture.add("midi2", 0);
+
+  let options = Options { limits: Limits { max_jitter: Some(100.0), ..Default::default() }, ..DEFAULT_OPTIONS };
+  let (report, passed) = analyze(&capture, &options);
+  assert!(!passed);
+  assert!(report.contains("midi1: 49 intervals"));
+  assert!(report.contains("midi2: not enough ticks"));
+
+  let options = Options { stream: Some(String::from("midi1")), ..DEFAULT_OPTIONS };
+  let (report, passed) = analyze(&capture, &options);
+  assert!(passed);
+  assert!(!report.contains("midi2"));
+}
@@ -0,0 +1,17 @@
+/*
+ * Host simulation of the midi clock, runs a script of input events through the core crate
+ * and logs every midi byte and trigger edge
+ */
+
+pub mod script;
+pub mod simulator;
+pub mod timeline;
+
+use timeline::{Format};
+
+// parses and runs the script, returns the timeline in the given format
+pub fn simulate(script: &str, format: Format, duration: Option<u32>) -> Result<String, String> {
+  let steps = script::parse(script)?;
+  let records = simulator::Simulator::new().run(&steps, duration);
+  return Ok(timeline::write(&records, format));
+}
@@ -0,0 +1,48 @@
+use std::env;
+use std::fs;
+use std::process;
+
+use midi_clock_simulator::{simulate};
+use midi_clock_simulator::timeline::{Format};
+
+const USAGE: &str = "usage: midi_clock_simulator <script> [--format csv|json] [--duration ms]";
+
+fn main() {
+  let args: Vec<String> = env::args().skip(1).collect();
+  let mut script_path: Option<&str> = None;
+  let mut format = Format::Csv;
+  let mut duration: Option<u32> = None;
+
+  let mut i = 0;
+  while i < args.len() {
+    match args[i].as_str() {
+      "--format" => {
+        i += 1;
+        format = match args.get(i).map(|s| s.as_str()) {
+          Some("csv") => Format::Csv,
+          Some("json") => Format::Json,
+          _ => exit(USAGE)
+        };
+      },
+      "--duration" => {
+        i += 1;
+        duration = Some(args.get(i).and_then(|s| s.parse().ok()).unwrap_or_else(|| exit(USAGE)));
+      },
+      path if script_path.is_none() && !path.starts_with("--") => script_path = Some(path),
+      _ => exit(USAGE)
+    }
+    i += 1;
+  }
+
+  let path = script_path.unwrap_or_else(|| exit(USAGE));
+  let script = fs::read_to_string(path).unwrap_or_else(|e| exit(&format!("{}: {}", path, e)));
+  match simulate(&script, format, duration) {
+    Ok(timeline) => print!("{}", timeline),
+    Err(e) => exit(&format!("{}: {}", path, e))
+  }
+}
+
+fn exit(message: &str) -> ! {
+  eprintln!("{}", message);
+  process::exit(1);
+}
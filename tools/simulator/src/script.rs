@@ -0,0 +1,87 @@
+/*
+ * Scripted input of a simulation, one command per line: <ms> <command> [arguments]
+ *
+ *   0 press play          button play, stop, sync or encoder goes down
+ *   80 release play       button goes up
+ *   500 turn 4 2          encoder detents with an optional acceleration
+ *   1000 bpm 140          sets the bpm directly
+ *   1500 cc 20 127        midi in control change
+ *   2000 midi_start       midi in start message
+ *   2000 midi_clock 120   midi in sends clock ticks at the bpm, 0 stops them
+ *   9000 end              simulation ends, defaults to 1s after the last command
+ *
+ * Everything after a # is a comment.
+ */
+
+use midi_clock_core::gestures::{BUTTON1_MASK, BUTTON2_MASK, BUTTON3_MASK, BUTTON4_MASK};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Command {
+  Press(u8), // button mask
+  Release(u8),
+  Turn(i16, u8), // detents and acceleration
+  Bpm(u16),
+  ControlChange(u8, u8),
+  MidiStart,
+  MidiClock(u16), // bpm of the external clock, 0 stops it
+  End
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Step {
+  pub time: u32, // ms since the start of the simulation
+  pub command: Command
+}
+
+pub fn parse(script: &str) -> Result<Vec<Step>, String> {
+  let mut steps: Vec<Step> = Vec::new();
+  for (i, line) in script.lines().enumerate() {
+    let line = line.split('#').next().unwrap_or("").trim();
+    if line.is_empty() { continue }
+
+    let step = parse_line(line).map_err(|e| format!("line {}: {}", i + 1, e))?;
+    if steps.last().map_or(false, |last| last.time > step.time) {
+      return Err(format!("line {}: time goes backwards", i + 1));
+    }
+    steps.push(step);
+  }
+  return Ok(steps);
+}
+
+fn parse_line(line: &str) -> Result<Step, String> {
+  let words: Vec<&str> = line.split_whitespace().collect();
+  let time = number::<u32>(&words, 0)?;
+  let name = *words.get(1).ok_or("missing command")?;
+
+  let command = match name {
+    "press" => Command::Press(button(&words)?),
+    "release" => Command::Release(button(&words)?),
+    "turn" => {
+      let acceleration = if words.len() > 3 { number::<u8>(&words, 3)? } else { 1 };
+      Command::Turn(number::<i16>(&words, 2)?, acceleration)
+    },
+    "bpm" => Command::Bpm(number::<u16>(&words, 2)?),
+    "cc" => Command::ControlChange(number::<u8>(&words, 2)?, number::<u8>(&words, 3)?),
+    "midi_start" => Command::MidiStart,
+    "midi_clock" => Command::MidiClock(number::<u16>(&words, 2)?),
+    "end" => Command::End,
+    _ => return Err(format!("unknown command {}", name))
+  };
+  return Ok(Step { time: time, command: command });
+}
+
+fn number<T: std::str::FromStr>(words: &[&str], index: usize) -> Result<T, String> {
+  let word = words.get(index).ok_or("missing argument")?;
+  return word.parse::<T>().map_err(|_| format!("invalid number {}", word));
+}
+
+fn button(words: &[&str]) -> Result<u8, String> {
+  return match words.get(2) {
+    Some(&"play") => Ok(BUTTON1_MASK),
+    Some(&"stop") => Ok(BUTTON2_MASK),
+    Some(&"sync") => Ok(BUTTON3_MASK),
+    Some(&"encoder") => Ok(BUTTON4_MASK),
+    Some(name) => Err(format!("unknown button {}", name)),
+    None => Err(String::from("missing button"))
+  };
+}
@@ -0,0 +1,244 @@
+/*
+ * Runs the transport, statemachine and gesture detection of the core the way the firmware does,
+ * with virtual timers in place of timer2 and timer3.
+ *
+ * Differences to the hardware: the timer has no interrupt latency, so clock ticks fall exactly
+ * on the 50us grid. Buttons are not debounced. Midi bytes are logged when they are written to the port.
+ */
+
+use std::collections::VecDeque;
+
+use midi_clock_core::clock::{CLOCK_TICKS_PER_QUARTER_NOTE};
+use midi_clock_core::events::{Event};
+use midi_clock_core::gestures::{GestureDetector, DEFAULT_GESTURE_CONFIG};
+use midi_clock_core::midi::{MidiMessage, MidiParser, MidiInput};
+use midi_clock_core::setlist::{Setlist};
+use midi_clock_core::statemachine::{Statemachine, State};
+use midi_clock_core::transport::{Transport, ClockHardware};
+use midi_clock_core::triggers::{TriggerPulses};
+
+use crate::script::{Step, Command};
+use crate::timeline::{Record, Output};
+
+// timer2 base interval, clock ticks and delays are multiples of it
+const BASE_TICK_US: u64 = 50;
+const BASE_TICKS_PER_MS: u64 = 20;
+
+// how long the simulation runs after the last command if the script has no end
+const DEFAULT_TAIL_MS: u32 = 1000;
+
+/* Virtual timers and outputs, logs what the clock sends */
+struct Hardware {
+  now: u64, // us since the start
+  events: VecDeque<Event>,
+  timer_running: bool,
+  timer_interval: u32, // base ticks between clock ticks
+  triggers: u8, // trigger outputs that are high
+  pulses: TriggerPulses,
+  records: Vec<Record>
+}
+
+impl ClockHardware for Hardware {
+  fn set_interval(&mut self, us: u32) {
+    // the timer calls the clock when its overflows reach the interval, so it ticks one base tick later
+    self.timer_interval = (us / BASE_TICK_US as u32).saturating_sub(1);
+  }
+
+  fn set_timer_running(&mut self, running: bool) {
+    self.timer_running = running;
+  }
+
+  fn send_midi(&mut self, port: u8, byte: u8) {
+    self.records.push(Record { time: self.now, output: Output::Midi(port), value: byte });
+  }
+
+  fn fire(&mut self, triggers: u8) {
+    self.pulses.start(triggers);
+    self.set_triggers(self.triggers | triggers);
+  }
+
+  fn post(&mut self, event: Event) {
+    self.events.push_back(event);
+  }
+}
+
+impl Hardware {
+  // logs the edges of the changed triggers
+  fn set_triggers(&mut self, triggers: u8) {
+    let changed = self.triggers ^ triggers;
+    for i in 0..4 {
+      if changed & (1 << i) > 0 {
+        let level = (triggers >> i) & 1;
+        self.records.push(Record { time: self.now, output: Output::Trigger(i + 1), value: level });
+      }
+    }
+    self.triggers = triggers;
+  }
+}
+
+pub struct Simulator {
+  base_ticks: u64, // 50us steps since the start
+  statemachine: Statemachine,
+  gestures: GestureDetector,
+  buttons: u8, // buttons held down
+  prev_state: State,
+
+  // clock, timer2 calls the transport when its overflows reach the interval
+  transport: Transport,
+  hardware: Hardware,
+  timer_overflows: u32,
+
+  // midi in
+  parser: MidiParser,
+  external_interval: Option<f64>, // us between the ticks of the external clock
+  next_external_tick: f64
+}
+
+impl Simulator {
+  pub fn new() -> Simulator {
+    let statemachine = Statemachine::new(None, Setlist::new());
+    let state = statemachine.get_state();
+
+    let mut simulator = Simulator {
+      base_ticks: 0,
+      statemachine: statemachine,
+      gestures: GestureDetector::new(DEFAULT_GESTURE_CONFIG),
+      buttons: 0,
+      prev_state: state,
+
+      transport: Transport::new(),
+      hardware: Hardware {
+        now: 0,
+        events: VecDeque::new(),
+        timer_running: false,
+        timer_interval: 1,
+        triggers: 0,
+        pulses: TriggerPulses::new(),
+        records: Vec::new()
+      },
+      timer_overflows: 0,
+
+      parser: MidiParser::new(),
+      external_interval: None,
+      next_external_tick: 0.0
+    };
+
+    simulator.transport.init(&state, &mut simulator.hardware);
+    return simulator;
+  }
+
+  // runs the script and returns everything the clock sent
+  pub fn run(mut self, steps: &[Step], duration: Option<u32>) -> Vec<Record> {
+    let end = duration
+      .or(steps.iter().find(|step| step.command == Command::End).map(|step| step.time))
+      .unwrap_or(steps.last().map_or(0, |step| step.time) + DEFAULT_TAIL_MS);
+
+    let mut next_step = 0;
+    for base_tick in 0..end as u64 * BASE_TICKS_PER_MS {
+      self.base_ticks = base_tick;
+      self.hardware.now = base_tick * BASE_TICK_US;
+      let ms_start = base_tick % BASE_TICKS_PER_MS == 0;
+      let now = (base_tick / BASE_TICKS_PER_MS) as u32;
+
+      if ms_start {
+        while next_step < steps.len() && steps[next_step].time <= now {
+          self.apply(steps[next_step].command, now);
+          next_step += 1;
+        }
+      }
+
+      if let Some(interval) = self.external_interval {
+        while self.next_external_tick <= self.now() as f64 {
+          self.receive(MidiMessage::TimingClock as u8);
+          self.next_external_tick += interval;
+        }
+      }
+
+      if self.hardware.timer_running {
+        self.on_timer2_tick();
+      }
+      if ms_start {
+        self.on_timer3_tick(now);
+      }
+      self.main_loop();
+    }
+    return self.hardware.records;
+  }
+
+  fn now(&self) -> u64 {
+    return self.base_ticks * BASE_TICK_US;
+  }
+
+  fn apply(&mut self, command: Command, now: u32) {
+    match command {
+      Command::Press(button) => {
+        self.buttons |= button;
+        self.gestures.on_change(button, self.buttons, now);
+      },
+      Command::Release(button) => {
+        self.buttons &= !button;
+        self.gestures.on_change(button, self.buttons, now);
+      },
+      Command::Turn(detents, acceleration) => self.hardware.events.push_back(Event::EncoderTurn(detents, acceleration)),
+      Command::Bpm(bpm) => { self.statemachine.set_bpm(bpm); },
+      Command::ControlChange(controller, value) => {
+        for byte in [0xB0, controller, value].iter() {
+          self.receive(*byte);
+        }
+      },
+      Command::MidiStart => self.receive(MidiMessage::Start as u8),
+      Command::MidiClock(0) => self.external_interval = None,
+      Command::MidiClock(bpm) => {
+        self.external_interval = Some(60.0 * 1000.0 * 1000.0 / (bpm as f64 * CLOCK_TICKS_PER_QUARTER_NOTE as f64));
+        self.next_external_tick = self.now() as f64;
+      },
+      Command::End => {}
+    }
+  }
+
+  // every event gets its own state change, like in the main loop of the firmware
+  fn main_loop(&mut self) {
+    while let Some(event) = self.hardware.events.pop_front() {
+      self.statemachine.on_event(event);
+      self.statemachine.on_change().map(|state| self.on_state_change(&state));
+    }
+    self.statemachine.on_change().map(|state| self.on_state_change(&state));
+  }
+
+  fn on_state_change(&mut self, state: &State) {
+    self.transport.on_state_change(&self.prev_state, state, &mut self.hardware);
+    self.prev_state = *state;
+  }
+
+  fn on_timer2_tick(&mut self) {
+    if self.timer_overflows >= self.hardware.timer_interval {
+      self.transport.on_timer_tick(&mut self.hardware);
+      self.timer_overflows = 0;
+    } else {
+      self.timer_overflows += 1;
+    }
+    self.transport.on_base_tick(&mut self.hardware);
+  }
+
+  // same order as the timer3 handlers of the firmware
+  fn on_timer3_tick(&mut self, now: u32) {
+    while let Some(event) = self.gestures.poll(now) {
+      self.hardware.events.push_back(Event::Button(event));
+    }
+
+    let ended = self.hardware.pulses.on_timer_tick();
+    self.hardware.set_triggers(self.hardware.triggers & !ended);
+
+    self.transport.on_sync_timer_tick(&mut self.hardware);
+  }
+
+  fn receive(&mut self, byte: u8) {
+    match self.parser.parse(byte) {
+      Some(MidiInput::TimingClock) => self.transport.on_external_tick(&mut self.hardware),
+      Some(MidiInput::Start) => self.transport.on_external_start(),
+      Some(MidiInput::ControlChange(controller, value)) => self.hardware.events.push_back(Event::ControlChange(controller, value)),
+      // timing diagnostics only exist on the device
+      Some(MidiInput::SysEx(_, _)) | None => {}
+    }
+  }
+}
@@ -0,0 +1,58 @@
+/*
+ * Timestamped log of everything the clock sends
+ */
+
+use std::fmt::Write;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Output {
+  Midi(u8), // port 1: midi out1+2, port 2: midi out3+4
+  Trigger(u8) // trigger 1-4, value is the level after the edge
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Record {
+  pub time: u64, // us since the start of the simulation
+  pub output: Output,
+  pub value: u8
+}
+
+impl Record {
+  fn fields(&self) -> (&str, u8) {
+    return match self.output {
+      Output::Midi(port) => ("midi", port),
+      Output::Trigger(trigger) => ("trigger", trigger)
+    };
+  }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Format {
+  Csv,
+  Json
+}
+
+pub fn write(records: &[Record], format: Format) -> String {
+  let mut out = String::new();
+  match format {
+    Format::Csv => {
+      out.push_str("time_us,type,output,value\n");
+      for record in records {
+        let (kind, output) = record.fields();
+        writeln!(out, "{},{},{},{}", record.time, kind, output, record.value).unwrap();
+      }
+    },
+    Format::Json => {
+      // one record per line, so the golden files diff well
+      out.push_str("[\n");
+      for (i, record) in records.iter().enumerate() {
+        let (kind, output) = record.fields();
+        let separator = if i + 1 < records.len() { "," } else { "" };
+        writeln!(out, "  {{\"time_us\": {}, \"type\": \"{}\", \"output\": {}, \"value\": {}}}{}",
+          record.time, kind, output, record.value, separator).unwrap();
+      }
+      out.push_str("]\n");
+    }
+  }
+  return out;
+}
@@ -0,0 +1,46 @@
+use std::env;
+use std::fs;
+use std::path::{Path};
+
+use midi_clock_simulator::{simulate};
+use midi_clock_simulator::timeline::{Format};
+
+// runs every script and compares its timeline with the golden file, UPDATE_GOLDEN=1 rewrites them
+#[test]
+fn timelines_match_the_golden_files() {
+  let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+  let update = env::var("UPDATE_GOLDEN").is_ok();
+
+  let mut scripts: Vec<_> = fs::read_dir(root.join("scripts")).unwrap().map(|entry| entry.unwrap().path()).collect();
+  scripts.sort();
+  assert!(!scripts.is_empty());
+
+  for script in scripts {
+    let name = script.file_stem().unwrap().to_str().unwrap().to_string();
+    let timeline = simulate(&fs::read_to_string(&script).unwrap(), Format::Csv, None).unwrap();
+
+    let golden = root.join("golden").join(format!("{}.csv", name));
+    if update {
+      fs::write(&golden, &timeline).unwrap();
+      continue;
+    }
+    let expected = fs::read_to_string(&golden).unwrap_or_else(|_| panic!("missing golden file for {}", name));
+    assert!(timeline == expected, "timeline of {} differs from {}", name, golden.display());
+  }
+}
+
+#[test]
+fn json_has_one_record_per_line() {
+  let timeline = simulate("0 bpm 300\n100 end\n", Format::Json, None).unwrap();
+  let lines: Vec<&str> = timeline.lines().collect();
+  assert_eq!(lines.first(), Some(&"["));
+  assert_eq!(lines.last(), Some(&"]"));
+  assert!(lines[1].starts_with("  {\"time_us\": ") && lines[1].ends_with("\"type\": \"midi\", \"output\": 1, \"value\": 248},"));
+  assert!(lines[lines.len() - 2].ends_with("}"));
+}
+
+#[test]
+fn script_errors_name_the_line() {
+  assert_eq!(simulate("0 press play\nten bpm 120\n", Format::Csv, None), Err(String::from("line 2: invalid number ten")));
+  assert_eq!(simulate("100 bpm 120\n50 bpm 100\n", Format::Csv, None), Err(String::from("line 2: time goes backwards")));
+}